@@ -0,0 +1,102 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::progression::*;
+use crate::libr::scales::{ ModeObj, HeptatonicScaleNamer };
+use crate::query::{ find_chordscales, find_scale_superset };
+use super::tensions::*;
+
+/// A scale to play over a chord, how well it fits and which of its tones to avoid.
+pub struct ChordScale{
+    pub mode: ModeObj,
+    pub score: i32,
+    // Intervals above the root of the chord.
+    pub avoid: Vec<Note>,
+}
+
+impl ChordScale{
+    pub fn name(&self, namer: &HeptatonicScaleNamer) -> String{
+        if self.mode.mode_name.is_empty() { namer.name(&self.mode.steps) } else { self.mode.mode_name.clone() }
+    }
+
+    pub fn avoid_labels(&self) -> Vec<String>{
+        self.avoid.iter().map(|i| interval_chord_extension(as_tension(*i))).collect()
+    }
+}
+
+// Every library scale on the root holding all chord tones, the best fit for the key first.
+// Tones of the key count twice, avoid notes twice against, and when the next chord is known its tones
+// count three times, a scale tone a half step off one of them against as much.
+pub fn chord_scales(chord: &RootedChord, key: &[PC], next: Option<&RootedChord>) -> Vec<ChordScale>{
+    let root = chord.root.to_pc();
+    let mut pcs = chord.to_scale().into_pcs();
+    pcs.dedup();
+    let strict = find_chordscales(&pcs);
+    let mut res: Vec<(bool, ChordScale)> = Vec::new();
+    for (tonic, mode) in find_scale_superset(pcs, true){
+        if tonic != root || res.iter().any(|(_, cs)| cs.mode.steps == mode.steps) { continue; }
+        let scale = mode.steps.to_scale(0).0;
+        let scale_pcs = mode.steps.to_scale(chord.root).into_pcs();
+        let avoid = avoid_notes(chord, &scale, &TensionRules::default());
+        let mut score = 2 * scale_pcs.iter().filter(|pc| key.contains(pc)).count() as i32 - 2 * avoid.len() as i32;
+        if let Some(next) = next{
+            for pc in next.to_scale().into_pcs(){
+                if scale_pcs.contains(&pc) { score += 3; }
+                else if scale_pcs.iter().any(|s| (s.0 - pc.0).rem_euclid(OCTAVE) == 1 || (pc.0 - s.0).rem_euclid(OCTAVE) == 1) { score -= 3; }
+            }
+        }
+        let is_strict = strict.iter().any(|m| m.steps == mode.steps);
+        res.push((is_strict, ChordScale{ mode, score, avoid }));
+    }
+    // Scales built in thirds on the chord win a tie.
+    res.sort_by_key(|(strict, cs)| (-cs.score, !strict));
+    res.into_iter().map(|(_, cs)| cs).collect()
+}
+
+// The scales for every chord, each seen in the key and going to the one after it.
+pub fn chord_scales_in_key(chords: &[RootedChord], tonic: Note, steps: &Steps) -> Vec<Vec<ChordScale>>{
+    let key = steps.to_scale(tonic).into_pcs();
+    chords.iter().enumerate().map(|(i, c)| chord_scales(c, &key, chords.get(i + 1))).collect()
+}
+
+pub fn progression_chord_scales(progression: &Progression, tonic: Note, steps: &Steps) -> Vec<(ProgressionChord, Vec<ChordScale>)>{
+    let chords = progression.chords().cloned().collect::<Vec<_>>();
+    let scales = chord_scales_in_key(&chords.iter().map(|c| c.chord.clone()).collect::<Vec<_>>(), tonic, steps);
+    chords.into_iter().zip(scales).collect()
+}
+
+// As written over a lead sheet: Dm7 – Dorian; G7 – Mixolidian; Cmaj7 – Ionian/Lydian.
+// Scales scoring within the margin of the best one are given as alternatives.
+pub fn chord_scale_annotation(assigned: &[(ProgressionChord, Vec<ChordScale>)], margin: i32) -> String{
+    let namer = HeptatonicScaleNamer::new();
+    assigned.iter().map(|(chord, scales)|{
+        let best = scales.first().map(|s| s.score).unwrap_or(0);
+        let names = scales.iter().take_while(|s| s.score + margin >= best).map(|s| s.name(&namer)).collect::<Vec<_>>();
+        let names = if names.is_empty() { String::from("?") } else { names.join("/") };
+        format!("{} – {}", chord.name(), names)
+    }).collect::<Vec<_>>().join("; ")
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_chord_scales(){
+        let c = C.0 + 36;
+        let prog = Progression::parse("| Dm7 G7 | Cmaj7 |").unwrap();
+        let assigned = progression_chord_scales(&prog, c, &ionian::steps());
+        assert_eq!(chord_scale_annotation(&assigned, 0), "Dm7 – Dorian; G7 – Mixolidian; Cmaj7 – Ionian/Lydian");
+        // Ionian has the 4 a half step above the third.
+        assert_eq!(assigned[2].1[0].avoid, vec![PERFECT_FOURTH]);
+        assert_eq!(assigned[2].1[0].avoid_labels(), vec!["♮11"]);
+        // In C minor the same G7 takes the ♭9 and ♭13 of the key.
+        let prog = Progression::parse("| Dm7b5 G7 | Cm |").unwrap();
+        let assigned = progression_chord_scales(&prog, c, &ionian::steps().mode(5));
+        assert_eq!(assigned[1].1[0].mode.mode_name, "Phrygian Dominant");
+        let prog = Progression::parse("| G7alt | Cmaj7 |").unwrap();
+        let assigned = progression_chord_scales(&prog, c, &ionian::steps());
+        assert_eq!(chord_scale_annotation(&assigned, 0), "G7alt – Altered Scale; Cmaj7 – Ionian/Lydian");
+    }
+}
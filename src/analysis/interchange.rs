@@ -0,0 +1,103 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::spell_note;
+use crate::theory::symbol::ChordSymbol;
+use crate::libr::scales::{ ScaleObj, HeptatonicScaleNamer };
+use crate::utils::roman_numerals::to_roman_num;
+
+/// A chord on a degree of a parallel mode, as seen from the home key.
+#[derive(Clone,PartialEq,Eq)]
+pub struct InterchangeChord{
+    pub chord: RootedChord,
+    pub name: String,
+    pub roman: String,
+    // Not the chord on the same degree of the home mode.
+    pub borrowed: bool,
+}
+
+/// The triads and seventh chords of one mode on the tonic.
+pub struct InterchangeRow{
+    pub mode_name: String,
+    pub home: bool,
+    pub triads: Vec<InterchangeChord>,
+    pub sevenths: Vec<InterchangeChord>,
+}
+
+fn accidentals(semis: Note) -> String{
+    let sign = if semis < 0 { "♭" } else { "♯" };
+    sign.repeat(semis.unsigned_abs() as usize)
+}
+
+fn degree_chords(tonic: EnharmonicNote, home: &[RootedChord], steps: &Steps, size: usize, styling: ChordStyling) -> Vec<InterchangeChord>{
+    rooted_scale_chords(steps, tonic.to_note(36), size).into_iter().zip(home).enumerate().map(|(i, (chord, own))|{
+        // The degree raised or lowered from the home key, like the ♭ of ♭VI.
+        let semis = (chord.root - own.root + 6).rem_euclid(OCTAVE) - 6;
+        let root = spell_note(chord.root, tonic.letter() + i as u8);
+        InterchangeChord{
+            name: ChordSymbol::from_rooted(&chord, root).as_string(styling),
+            roman: chord.chord.quality(format!("{}{}", accidentals(semis), to_roman_num(i + 1)), true, styling),
+            borrowed: own.root != chord.root || own.chord != chord.chord,
+            chord,
+        }
+    }).collect()
+}
+
+// Every mode of the families on the tonic, next to the home mode, for the modes as long as it.
+pub fn interchange_table(tonic: EnharmonicNote, home: &Steps, families: Vec<ScaleObj>, styling: ChordStyling) -> Vec<InterchangeRow>{
+    let namer = HeptatonicScaleNamer::new();
+    let home_triads = rooted_scale_chords(home, tonic.to_note(36), 3);
+    let home_sevenths = rooted_scale_chords(home, tonic.to_note(36), 4);
+    families.into_iter().flat_map(|sc| sc.get_modes()).filter(|m| m.steps.len() == home.len()).map(|mode|{
+        InterchangeRow{
+            mode_name: if mode.mode_name.is_empty() { namer.name(&mode.steps) } else { mode.mode_name.clone() },
+            home: &mode.steps == home,
+            triads: degree_chords(tonic, &home_triads, &mode.steps, 3, styling),
+            sevenths: degree_chords(tonic, &home_sevenths, &mode.steps, 4, styling),
+        }
+    }).collect()
+}
+
+// A row of triads and a row of sevenths per mode, the borrowed chords marked with a star.
+pub fn interchange_table_string(rows: &[InterchangeRow], names: bool) -> String{
+    let cell = |c: &InterchangeChord|{
+        let text = if names { format!("{} {}", c.roman, c.name) } else { c.roman.clone() };
+        format!("{}{}", if c.borrowed { "*" } else { " " }, text)
+    };
+    let label = |r: &InterchangeRow| if r.home { format!("{} (home)", r.mode_name) } else { r.mode_name.clone() };
+    let label_width = rows.iter().map(|r| label(r).chars().count()).max().unwrap_or(0) + 2;
+    let width = rows.iter().flat_map(|r| r.triads.iter().chain(r.sevenths.iter())).map(|c| cell(c).chars().count()).max().unwrap_or(0) + 2;
+    let line = |head: String, chords: &[InterchangeChord]|{
+        let cells = chords.iter().map(|c|{
+            let s = cell(c);
+            let pad = width - s.chars().count();
+            format!("{}{}", s, " ".repeat(pad))
+        }).collect::<String>();
+        format!("{}{}{}\n", head, " ".repeat(label_width - head.chars().count()), cells).trim_end().to_string() + "\n"
+    };
+    rows.iter().map(|r| line(label(r), &r.triads) + &line(String::new(), &r.sevenths)).collect()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::{ ionian, melodic_minor };
+    #[test]
+    fn test_interchange(){
+        let c = "C".to_string().to_enharmonic_note().unwrap();
+        let rows = interchange_table(c, &ionian::steps(), vec![ionian::obj(), melodic_minor::obj()], ChordStyling::Std);
+        assert_eq!(rows.len(), 14);
+        assert!(rows[0].home && rows[0].triads.iter().all(|t| !t.borrowed));
+        let aeolian = &rows[5];
+        assert_eq!(aeolian.mode_name, "Aeolian");
+        let romans = aeolian.triads.iter().map(|t| t.roman.clone()).collect::<Vec<_>>();
+        assert_eq!(romans, vec!["i", "ii°", "♭III", "iv", "v", "♭VI", "♭VII"]);
+        assert_eq!(aeolian.triads.iter().filter(|t| t.borrowed).count(), 7);
+        assert_eq!(aeolian.sevenths[5].name, "A♭∆");
+        // F is the IV of Dorian as well.
+        assert!(!rows[1].triads[3].borrowed);
+        let table = interchange_table_string(&rows, false);
+        assert!(table.starts_with("Ionian (home)"));
+        assert!(table.lines().nth(10).unwrap().contains("*♭VI"));
+    }
+}
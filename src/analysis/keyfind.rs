@@ -0,0 +1,155 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::scale::ModeTrait;
+use crate::libr::scales::{ ModeObj, HeptatonicScaleNamer, get_all_scale_objs, ionian };
+
+// How well every tone fits a major or minor key, from the tonic up.
+pub const KRUMHANSL_MAJOR: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+pub const KRUMHANSL_MINOR: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+pub const TEMPERLEY_MAJOR: [f32; 12] = [5.0, 2.0, 3.5, 2.0, 4.5, 4.0, 2.0, 4.5, 2.0, 3.5, 1.5, 4.0];
+pub const TEMPERLEY_MINOR: [f32; 12] = [5.0, 2.0, 3.5, 4.5, 2.0, 4.0, 2.0, 4.5, 3.5, 2.0, 1.5, 4.0];
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum KeyProfile{
+    Krumhansl,
+    Temperley,
+}
+
+impl KeyProfile{
+    fn major(&self) -> [f32; 12]{
+        match self{
+            KeyProfile::Krumhansl => KRUMHANSL_MAJOR,
+            KeyProfile::Temperley => TEMPERLEY_MAJOR,
+        }
+    }
+
+    fn minor(&self) -> [f32; 12]{
+        match self{
+            KeyProfile::Krumhansl => KRUMHANSL_MINOR,
+            KeyProfile::Temperley => TEMPERLEY_MINOR,
+        }
+    }
+}
+
+/// A key the notes could be in and how well they correlate with it.
+pub struct KeyCandidate{
+    pub tonic: PC,
+    pub mode: ModeObj,
+    pub score: f32,
+}
+
+impl KeyCandidate{
+    pub fn name(&self, namer: &HeptatonicScaleNamer) -> String{
+        let mode = if self.mode.mode_name.is_empty() { namer.name(&self.mode.steps) } else { self.mode.mode_name.clone() };
+        format!("{} {}", self.tonic, mode)
+    }
+}
+
+// Time spent on every pitchclass, indexed like PC.
+pub fn pc_distribution(notes: &[(Note, f32)]) -> [f32; 12]{
+    let mut res = [0.0; 12];
+    for (note, duration) in notes{
+        res[note.to_pc().0 as usize] += duration;
+    }
+    res
+}
+
+// Tonic, rest of the tonic triad, rest of the scale or outside it, for every interval above the tonic.
+fn tone_roles(steps: &Steps) -> [usize; 12]{
+    let triad = rooted_scale_chords(steps, 0, 3).swap_remove(0).chord.0;
+    let scale = steps.to_scale(0).0;
+    let has = |notes: &[Note], i: Note| notes.iter().any(|n| n.rem_euclid(OCTAVE) == i);
+    std::array::from_fn(|i|{
+        let i = i as Note;
+        if i == 0 { 0 } else if has(&triad, i) { 1 } else if has(&scale, i) { 2 } else { 3 }
+    })
+}
+
+// The published profiles for major and natural minor. Other seven note modes take the one
+// with the same third, every degree with the weight of the same degree there, the tones
+// outside the mode with those of the tones it altered. Other modes weigh every tone by its
+// role, like the tonic or the rest of the tonic triad, on average.
+pub fn mode_profile(steps: &Steps, profile: KeyProfile) -> [f32; 12]{
+    let (major, minor) = (ionian::steps(), ionian::steps().mode(5));
+    if steps == &major { return profile.major(); }
+    if steps == &minor { return profile.minor(); }
+    let roles = tone_roles(steps);
+    let (base, base_steps) = if roles[MINOR_THIRD as usize] == 1 { (profile.minor(), minor) } else { (profile.major(), major) };
+    if steps.len() == 7{
+        let (degrees, base_degrees) = (steps.to_scale(0).0, base_steps.to_scale(0).0);
+        let outside = |scale: &[Note]| (0..OCTAVE).filter(|i| !scale.contains(i)).collect::<Vec<_>>();
+        let mut res = [0.0; 12];
+        for (d, b) in degrees.iter().zip(&base_degrees).chain(outside(&degrees).iter().zip(&outside(&base_degrees))){
+            res[*d as usize] = base[*b as usize];
+        }
+        return res;
+    }
+    let mut sums = [0.0; 4];
+    let mut counts = [0.0; 4];
+    for (w, r) in base.iter().zip(tone_roles(&base_steps)){
+        sums[r] += w;
+        counts[r] += 1.0;
+    }
+    roles.map(|r| sums[r] / counts[r])
+}
+
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32{
+    let (ma, mb) = (a.iter().sum::<f32>() / 12.0, b.iter().sum::<f32>() / 12.0);
+    let cov = a.iter().zip(b).map(|(x, y)| (x - ma) * (y - mb)).sum::<f32>();
+    let (va, vb) = (a.iter().map(|x| (x - ma).powi(2)).sum::<f32>(), b.iter().map(|y| (y - mb).powi(2)).sum::<f32>());
+    if va == 0.0 || vb == 0.0 { 0.0 } else { cov / (va * vb).sqrt() }
+}
+
+// The modes on every tonic ranked by the correlation of their profile with the distribution.
+pub fn find_keys(distribution: &[f32; 12], modes: &[ModeObj], profile: KeyProfile) -> Vec<KeyCandidate>{
+    let mut res = Vec::new();
+    for mode in modes{
+        let weights = mode_profile(&mode.steps, profile);
+        for tonic in 0..12{
+            let rotated = std::array::from_fn(|pc| weights[(pc as Note - tonic).rem_euclid(OCTAVE) as usize]);
+            res.push(KeyCandidate{
+                tonic: PC(tonic),
+                mode: ModeObj{ steps: mode.steps.clone(), fam_name: mode.fam_name.clone(), mode_name: mode.mode_name.clone(), mode_nr: mode.mode_nr },
+                score: correlation(distribution, &rotated),
+            });
+        }
+    }
+    // Stable, so a tie goes to the mode listed first.
+    res.sort_by(|a, b| b.score.total_cmp(&a.score));
+    res
+}
+
+// Among the seven note modes of the library.
+pub fn find_keys_in_library(distribution: &[f32; 12], profile: KeyProfile) -> Vec<KeyCandidate>{
+    let modes = get_all_scale_objs().into_iter().flat_map(|sc| sc.get_modes()).filter(|m| m.steps.len() == 7).collect::<Vec<_>>();
+    find_keys(distribution, &modes, profile)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_find_keys(){
+        assert_eq!(mode_profile(&ionian::steps(), KeyProfile::Temperley), TEMPERLEY_MAJOR);
+        assert_eq!(mode_profile(&ionian::steps().mode(5), KeyProfile::Krumhansl), KRUMHANSL_MINOR);
+        // Dorian is minor with the weights of the sixths swapped.
+        assert_eq!(mode_profile(&ionian::steps().mode(1), KeyProfile::Temperley), [5.0, 2.0, 3.5, 4.5, 2.0, 4.0, 2.0, 4.5, 2.0, 3.5, 1.5, 4.0]);
+        let c = C.0 + 36;
+        let melody = [(c, 2.0), (c + 2, 0.5), (c + 4, 1.0), (c + 5, 0.5), (c + 7, 1.5), (c + 9, 0.5), (c + 11, 0.5), (c + 12, 2.0)];
+        let keys = find_keys_in_library(&pc_distribution(&melody), KeyProfile::Krumhansl);
+        assert_eq!(keys[0].tonic, C);
+        assert_eq!(keys[0].mode.mode_name, "Ionian");
+        // The first note is not the tonic here.
+        let a = A.0 + 36;
+        let melody = [(a + 7, 0.5), (a + 8, 0.5), (a + 11, 0.5), (a, 2.0), (a + 2, 0.5), (a + 3, 1.0), (a + 5, 0.5), (a + 7, 1.5), (a, 2.0)];
+        let keys = find_keys_in_library(&pc_distribution(&melody), KeyProfile::Krumhansl);
+        assert_eq!(keys[0].tonic, A);
+        assert_eq!(keys[0].mode.mode_name, "Harmonic Minor");
+        assert!(keys[0].score > keys[1].score);
+        let melody = [(a, 1.0), (a + 2, 0.5), (a + 3, 1.0), (a + 5, 0.5), (a + 7, 1.0), (a + 8, 0.5), (a + 10, 0.5), (a + 12, 2.0), (a + 7, 1.0), (a + 3, 1.0), (a, 2.0)];
+        let keys = find_keys_in_library(&pc_distribution(&melody), KeyProfile::Krumhansl);
+        assert_eq!(keys[0].tonic, A);
+        assert_eq!(keys[0].mode.mode_name, "Aeolian");
+    }
+}
@@ -0,0 +1,13 @@
+pub mod chordscale;
+pub mod tensions;
+pub mod reharm;
+pub mod interchange;
+pub mod keyfind;
+pub mod modulation;
+
+pub use chordscale::*;
+pub use tensions::*;
+pub use reharm::*;
+pub use interchange::*;
+pub use keyfind::*;
+pub use modulation::*;
@@ -0,0 +1,159 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::{ spell_note, key_signature };
+use crate::theory::symbol::ChordSymbol;
+use crate::libr::scales::{ HeptatonicScaleNamer, ionian };
+use crate::query::find_mode_name_by_steps;
+use crate::theory::scale::ModeTrait;
+use crate::utils::roman_numerals::to_roman_num;
+
+/// A tonic and the mode on it.
+#[derive(Clone)]
+pub struct Key{
+    pub tonic: EnharmonicNote,
+    pub steps: Steps,
+}
+
+impl Key{
+    pub fn new(tonic: EnharmonicNote, steps: Steps) -> Self{
+        Self{ tonic, steps }
+    }
+
+    pub fn name(&self, namer: &HeptatonicScaleNamer) -> String{
+        format!("{} {}", self.tonic.to_string_name(), find_mode_name_by_steps(&self.steps, namer))
+    }
+
+    // The same tonic, however it is spelled, and mode.
+    pub fn same_key(&self, other: &Key) -> bool{
+        self.tonic.to_pc() == other.tonic.to_pc() && self.steps == other.steps
+    }
+
+    fn chords(&self, size: usize) -> Vec<RootedChord>{
+        rooted_scale_chords(&self.steps, self.tonic.to_note(36), size)
+    }
+}
+
+/// A chord both keys have, with its numeral in each.
+pub struct PivotChord{
+    pub chord: RootedChord,
+    pub name: String,
+    pub from_roman: String,
+    pub to_roman: String,
+}
+
+// Every triad and seventh chord common to both keys, in the order of the first key.
+pub fn pivot_chords(from: &Key, to: &Key, styling: ChordStyling) -> Vec<PivotChord>{
+    let mut res = Vec::new();
+    for size in [3, 4]{
+        let theirs = to.chords(size);
+        for (i, chord) in from.chords(size).into_iter().enumerate(){
+            let Some(j) = theirs.iter().position(|c| c.same_in_any_octave(&chord)) else { continue; };
+            let root = spell_note(chord.root, from.tonic.letter() + i as u8);
+            res.push(PivotChord{
+                name: ChordSymbol::from_rooted(&chord, root).as_string(styling),
+                from_roman: chord.chord.quality(to_roman_num(i + 1), true, styling),
+                to_roman: chord.chord.quality(to_roman_num(j + 1), true, styling),
+                chord,
+            });
+        }
+    }
+    res
+}
+
+// How many fifths apart the signatures of the keys are, the short way round the circle.
+pub fn fifths_distance(a: &Key, b: &Key) -> Note{
+    let d = (key_signature(a.tonic, &a.steps).fifths - key_signature(b.tonic, &b.steps).fifths).rem_euclid(OCTAVE);
+    d.min(OCTAVE - d)
+}
+
+// Every tonic in the modes of both keys and in major and minor, spelled with the fewest accidentals.
+pub fn intermediate_keys(from: &Key, to: &Key) -> Vec<Key>{
+    let mut modes: Vec<Steps> = Vec::new();
+    for steps in [from.steps.clone(), to.steps.clone(), ionian::steps(), ionian::steps().mode(5)]{
+        if !modes.contains(&steps) { modes.push(steps); }
+    }
+    let mut res = Vec::new();
+    for steps in modes{
+        for pc in 0..OCTAVE{
+            let en = pc.to_enharmonic_note().expect("intermediate_keys: should not be None");
+            let tonic = [en, en.next_enharmonic(), en.prev_enharmonic()].iter().copied()
+                .filter(|en| en.accidental().abs() <= 1)
+                .min_by_key(|en| key_signature(*en, &steps).fifths.abs())
+                .expect("intermediate_keys: should be impossible");
+            let key = Key::new(tonic, steps.clone());
+            if !res.iter().any(|k: &Key| k.same_key(&key)) { res.push(key); }
+        }
+    }
+    res
+}
+
+// The shortest way round the circle of fifths from key to key, every step over a pivot chord.
+// Fewer steps win a tie, so a direct pivot is taken when there is one.
+pub fn modulation_path(from: &Key, to: &Key) -> Option<Vec<Key>>{
+    let mut keys = vec![from.clone(), to.clone()];
+    keys.extend(intermediate_keys(from, to).into_iter().filter(|k| !k.same_key(from) && !k.same_key(to)));
+    let n = keys.len();
+    let mut best: Vec<Option<(Note, usize)>> = vec![None; n];
+    let mut prev = vec![None; n];
+    let mut done = vec![false; n];
+    best[0] = Some((0, 0));
+    while let Some(i) = (0..n).filter(|i| !done[*i] && best[*i].is_some()).min_by_key(|i| best[*i]){
+        done[i] = true;
+        if i == 1 { break; }
+        let (dist, hops) = best[i].expect("modulation_path: should be reached");
+        for j in 0..n{
+            if done[j] || pivot_chords(&keys[i], &keys[j], ChordStyling::Std).is_empty() { continue; }
+            let cost = (dist + fifths_distance(&keys[i], &keys[j]), hops + 1);
+            if best[j].is_none_or(|b| cost < b){
+                best[j] = Some(cost);
+                prev[j] = Some(i);
+            }
+        }
+    }
+    best[1]?;
+    let mut path = vec![1];
+    while let Some(p) = prev[*path.last().expect("modulation_path: path is never empty")]{
+        path.push(p);
+    }
+    Some(path.into_iter().rev().map(|i| keys[i].clone()).collect())
+}
+
+// Like: C Ionian → G Ionian over Am (vi / ii), C (I / IV), ...
+pub fn modulation_string(path: &[Key], styling: ChordStyling) -> String{
+    let namer = HeptatonicScaleNamer::new();
+    path.windows(2).map(|w|{
+        let pivots = pivot_chords(&w[0], &w[1], styling).into_iter()
+            .map(|p| format!("{} ({} / {})", p.name, p.from_roman, p.to_roman))
+            .collect::<Vec<_>>();
+        format!("{} → {} over {}", w[0].name(&namer), w[1].name(&namer), pivots.join(", "))
+    }).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_modulation(){
+        let en = |s: &str| s.to_string().to_enharmonic_note().unwrap();
+        let c = Key::new(en("C"), ionian::steps());
+        let g = Key::new(en("G"), ionian::steps());
+        let pivots = pivot_chords(&c, &g, ChordStyling::Std);
+        let romans = pivots.iter().map(|p| format!("{} {}/{}", p.name, p.from_roman, p.to_roman)).collect::<Vec<_>>();
+        assert_eq!(romans[..4], ["C I/IV", "Em iii/vi", "G V/I", "Am vi/ii"]);
+        assert_eq!(pivots.len(), 7);
+        assert_eq!(fifths_distance(&c, &g), 1);
+        assert_eq!(fifths_distance(&c, &Key::new(en("A"), ionian::steps().mode(5))), 0);
+        let namer = HeptatonicScaleNamer::new();
+        let names = |path: Vec<Key>| path.iter().map(|k| k.name(&namer)).collect::<Vec<_>>();
+        assert_eq!(names(modulation_path(&c, &g).unwrap()), ["C Ionian", "G Ionian"]);
+        // C and F♯ major share no chord, the way goes through keys in between.
+        let fs = Key::new(en("F#"), ionian::steps());
+        let path = modulation_path(&c, &fs).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path.windows(2).map(|w| fifths_distance(&w[0], &w[1])).sum::<Note>(), 6);
+        assert!(pivot_chords(&c, &fs, ChordStyling::Std).is_empty());
+        assert_eq!(names(path.clone()), ["C Ionian", "D Ionian", "E Ionian", "F♯ Ionian"]);
+        assert!(modulation_string(&path, ChordStyling::Std).starts_with("C Ionian → D Ionian over Em (iii / ii), G (V / IV)"));
+    }
+}
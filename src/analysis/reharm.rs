@@ -0,0 +1,182 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::scale::ModeTrait;
+use crate::theory::progression::*;
+use crate::libr::scales::HeptatonicScaleNamer;
+use crate::query::find_mode_name_by_steps;
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum ReharmRule{
+    TritoneSubstitution,
+    RelatedTwoFive,
+    SecondaryDominant,
+    DiminishedPassing,
+    BackdoorDominant,
+    ModalInterchange,
+    RelativeSwap,
+}
+
+impl ReharmRule{
+    pub fn label(&self) -> &'static str{
+        match self{
+            ReharmRule::TritoneSubstitution => "tritone substitution",
+            ReharmRule::RelatedTwoFive => "related ii–V",
+            ReharmRule::SecondaryDominant => "secondary dominant",
+            ReharmRule::DiminishedPassing => "diminished passing chord",
+            ReharmRule::BackdoorDominant => "backdoor dominant",
+            ReharmRule::ModalInterchange => "modal interchange",
+            ReharmRule::RelativeSwap => "relative major/minor",
+        }
+    }
+}
+
+/// A reharmonization: the chord at the position played as the given chords instead.
+#[derive(Clone,PartialEq,Eq)]
+pub struct Reharm{
+    pub rule: ReharmRule,
+    pub position: usize,
+    pub chords: Vec<RootedChord>,
+    // Like the mode a chord is borrowed from.
+    pub detail: String,
+    // The voice leading from the chord before through the new chords to the chord after.
+    pub cost: i32,
+}
+
+fn has(chord: &RootedChord, interval: Note) -> bool{
+    chord.chord.0.iter().any(|i| i.rem_euclid(OCTAVE) == interval)
+}
+
+fn is_dominant(chord: &RootedChord) -> bool{
+    has(chord, MAJOR_THIRD) && has(chord, MINOR_SEVENTH)
+}
+
+fn pc_distance(a: Note, b: Note) -> i32{
+    let d = (a - b).rem_euclid(OCTAVE);
+    d.min(OCTAVE - d)
+}
+
+// Every tone moves to the nearest tone of the other chord, both ways, so added and dropped
+// tones count too.
+pub fn voice_leading_cost(a: &RootedChord, b: &RootedChord) -> i32{
+    let (a, b) = (a.to_scale().0, b.to_scale().0);
+    let one_way = |from: &[Note], to: &[Note]| from.iter().map(|x| to.iter().map(|y| pc_distance(*x, *y)).min().unwrap_or(0)).sum::<i32>();
+    one_way(&a, &b) + one_way(&b, &a)
+}
+
+fn path_cost(prev: Option<&RootedChord>, chords: &[RootedChord], next: Option<&RootedChord>) -> i32{
+    let path = prev.into_iter().chain(chords.iter()).chain(next).collect::<Vec<_>>();
+    path.windows(2).map(|w| voice_leading_cost(w[0], w[1])).sum()
+}
+
+// Reharmonization candidates for every chord of a sequence in the key, by position and then by cost.
+pub fn reharmonize(chords: &[RootedChord], tonic: Note, steps: &Steps) -> Vec<Reharm>{
+    let namer = HeptatonicScaleNamer::new();
+    let mut res = Vec::new();
+    let dom = |root: Note| RootedChord::from_intervals(root, DOMINANT_SEVENTH);
+    for (i, chord) in chords.iter().enumerate(){
+        let prev = if i > 0 { chords.get(i - 1) } else { None };
+        let next = chords.get(i + 1);
+        let root = chord.root;
+        let mut push = |rule: ReharmRule, new: Vec<RootedChord>, detail: String|{
+            let cost = path_cost(prev, &new, next);
+            res.push(Reharm{ rule, position: i, chords: new, detail, cost });
+        };
+        if is_dominant(chord){
+            push(ReharmRule::TritoneSubstitution, vec![dom(root + TRITONE)], String::new());
+            let two = RootedChord::from_intervals(root + PERFECT_FIFTH, MINOR_SEVENTH_CHORD);
+            if !prev.is_some_and(|p| p.same_in_any_octave(&two)){
+                push(ReharmRule::RelatedTwoFive, vec![two, chord.clone()], String::new());
+            }
+            // The ♭VII7 of the chord a dominant resolves to.
+            if let Some(target) = next.filter(|n| (n.root - root).rem_euclid(OCTAVE) == PERFECT_FOURTH && has(n, MAJOR_THIRD)){
+                push(ReharmRule::BackdoorDominant, vec![dom(target.root - MAJOR_SECOND)], format!("♭VII7 of {}", target.as_string(false, ChordStyling::Std)));
+            }
+        } else if has(chord, PERFECT_FIFTH) && (root - tonic).rem_euclid(OCTAVE) != 0{
+            let five = dom(root + PERFECT_FIFTH);
+            if !prev.is_some_and(|p| p.same_in_any_octave(&five)){
+                push(ReharmRule::SecondaryDominant, vec![five, chord.clone()], format!("V7 of {}", chord.as_string(false, ChordStyling::Std)));
+            }
+        }
+        // A diminished seventh on the half step between roots a whole step apart.
+        if let Some(next) = next{
+            let step = (next.root - root).rem_euclid(OCTAVE);
+            if step == MAJOR_SECOND || step == OCTAVE - MAJOR_SECOND{
+                let passing = root + if step == MAJOR_SECOND { 1 } else { -1 };
+                push(ReharmRule::DiminishedPassing, vec![chord.clone(), RootedChord::from_intervals(passing, DIMINISHED_SEVENTH_CHORD)], String::new());
+            }
+        }
+        if !is_dominant(chord) && has(chord, PERFECT_FIFTH){
+            let seventh = chord.chord.len() > 2;
+            if has(chord, MAJOR_THIRD){
+                push(ReharmRule::RelativeSwap, vec![RootedChord::from_intervals(root - MINOR_THIRD, if seventh { MINOR_SEVENTH_CHORD } else { MINOR })], String::new());
+            } else if has(chord, MINOR_THIRD){
+                push(ReharmRule::RelativeSwap, vec![RootedChord::from_intervals(root + MINOR_THIRD, if seventh { MAJOR_SEVENTH_CHORD } else { MAJOR })], String::new());
+            }
+        }
+        // The chord on the same degree of the parallel modes.
+        let size = chord.chord.len() + 1;
+        let home = rooted_scale_chords(steps, tonic, size);
+        if let Some(degree) = home.iter().position(|c| c.same_in_any_octave(chord)){
+            let mut borrowed: Vec<RootedChord> = Vec::new();
+            for m in 1..steps.len(){
+                let parallel = steps.clone().mode(m as u8);
+                let other = rooted_scale_chords(&parallel, tonic, size).swap_remove(degree);
+                if other.same_in_any_octave(chord) || borrowed.iter().any(|b| b.same_in_any_octave(&other)) { continue; }
+                borrowed.push(other.clone());
+                push(ReharmRule::ModalInterchange, vec![other], format!("from {}", find_mode_name_by_steps(&parallel, &namer)));
+            }
+        }
+    }
+    res.sort_by_key(|r| (r.position, r.cost));
+    res
+}
+
+pub fn progression_reharms(progression: &Progression, tonic: Note, steps: &Steps) -> Vec<Reharm>{
+    let chords = progression.chords().map(|c| c.chord.clone()).collect::<Vec<_>>();
+    reharmonize(&chords, tonic, steps)
+}
+
+// Like: G7 → C♯7 (tritone substitution, cost 4)
+pub fn reharm_string(original: &[RootedChord], reharm: &Reharm, styling: ChordStyling) -> String{
+    let from = original.get(reharm.position).map(|c| c.as_string(false, styling)).unwrap_or_default();
+    let to = reharm.chords.iter().map(|c| c.as_string(false, styling)).collect::<Vec<_>>().join(" ");
+    let detail = if reharm.detail.is_empty() { String::new() } else { format!(" {}", reharm.detail) };
+    format!("{} → {} ({}{}, cost {})", from, to, reharm.rule.label(), detail, reharm.cost)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    #[test]
+    fn test_reharmonize(){
+        let c = C.0 + 36;
+        let prog = Progression::parse("| Dm7 G7 | Cmaj7 |").unwrap();
+        let chords = prog.chords().map(|c| c.chord.clone()).collect::<Vec<_>>();
+        let reharms = progression_reharms(&prog, c, &ionian::steps());
+        let of = |rule: ReharmRule| reharms.iter().filter(|r| r.rule == rule).collect::<Vec<_>>();
+        let tritone = of(ReharmRule::TritoneSubstitution);
+        assert_eq!(tritone.len(), 1);
+        assert_eq!(tritone[0].position, 1);
+        assert_eq!(tritone[0].chords[0].root.rem_euclid(OCTAVE), (G.0 + TRITONE).rem_euclid(OCTAVE));
+        // Db7 is a half step from Dm7 and Cmaj7 with every voice.
+        assert!(tritone[0].cost <= voice_leading_cost(&chords[0], &chords[1]) + voice_leading_cost(&chords[1], &chords[2]) + 2);
+        // G7 already follows its ii.
+        assert!(of(ReharmRule::RelatedTwoFive).is_empty());
+        let backdoor = of(ReharmRule::BackdoorDominant);
+        assert_eq!(backdoor[0].chords[0].root.rem_euclid(OCTAVE), (C.0 - MAJOR_SECOND).rem_euclid(OCTAVE));
+        let secondary = of(ReharmRule::SecondaryDominant);
+        assert_eq!(secondary.len(), 1);
+        assert!(secondary[0].chords[0].same_in_any_octave(&RootedChord::from_intervals(A.0, DOMINANT_SEVENTH)));
+        assert!(of(ReharmRule::RelativeSwap).iter().any(|r| r.chords[0].same_in_any_octave(&RootedChord::from_intervals(A.0, MINOR_SEVENTH_CHORD))));
+        // Cmaj7 goes to Cm7 from Dorian, Aeolian and Phrygian, counted once.
+        let borrowed = of(ReharmRule::ModalInterchange);
+        assert_eq!(borrowed.iter().filter(|r| r.position == 2 && r.chords[0].same_in_any_octave(&RootedChord::from_intervals(c, MINOR_SEVENTH_CHORD))).count(), 1);
+        assert!(borrowed.iter().any(|r| r.position == 2 && r.detail == "from Mixolidian" && is_dominant(&r.chords[0])));
+        let passing = reharmonize(&[RootedChord::from_intervals(c, MAJOR), RootedChord::from_intervals(c + 2, MINOR)], c, &ionian::steps());
+        let dim = passing.iter().find(|r| r.rule == ReharmRule::DiminishedPassing).unwrap();
+        assert!(dim.chords[1] == RootedChord::from_intervals(c + 1, DIMINISHED_SEVENTH_CHORD));
+        assert_eq!(voice_leading_cost(&chords[1], &tritone[0].chords[0]), 4);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::libr::scales::{ ModeObj, HeptatonicScaleNamer, get_all_scale_objs };
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum ToneKind{
+    ChordTone,
+    Tension,
+    Avoid,
+}
+
+/// When a scale tone that is not in the chord is avoided.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TensionRules{
+    // A minor ninth above a chord tone.
+    pub minor_ninth: bool,
+    // Except the ♭9 and ♭13 of a dominant seventh chord.
+    pub dominant_exceptions: bool,
+    // The 11 over a major third, also when minor ninths are allowed.
+    pub eleven_over_major_third: bool,
+    // The 13 over a minor seventh chord, as some teach for Dorian.
+    pub thirteen_over_minor_seventh: bool,
+    // Intervals above the root that are always avoided or always available.
+    pub avoid: Vec<Note>,
+    pub available: Vec<Note>,
+}
+
+impl Default for TensionRules{
+    fn default() -> Self{
+        Self{ minor_ninth: true, dominant_exceptions: true, eleven_over_major_third: true, thirteen_over_minor_seventh: false, avoid: Vec::new(), available: Vec::new() }
+    }
+}
+
+/// A tone of the scale seen from the root of the chord.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ScaleTone{
+    pub interval: Note,
+    pub kind: ToneKind,
+    pub label: String,
+}
+
+// Seconds, fourths and sixths above a chord are read as 9, 11 and 13.
+pub fn as_tension(interval: Note) -> Note{
+    let int = interval.rem_euclid(OCTAVE);
+    if [MINOR_SECOND, MAJOR_SECOND, PERFECT_FOURTH, TRITONE, MINOR_SIXTH, MAJOR_SIXTH].contains(&int) { int + OCTAVE } else { int }
+}
+
+// The tones of the chord in the octave above the root, the root included.
+fn chord_tones(chord: &RootedChord) -> Vec<Note>{
+    std::iter::once(0).chain(chord.chord.0.iter().map(|i| i.rem_euclid(OCTAVE))).collect()
+}
+
+pub fn tone_kind(chord: &RootedChord, interval: Note, rules: &TensionRules) -> ToneKind{
+    let tones = chord_tones(chord);
+    let int = interval.rem_euclid(OCTAVE);
+    if tones.contains(&int) { return ToneKind::ChordTone; }
+    if rules.available.contains(&int) { return ToneKind::Tension; }
+    if rules.avoid.contains(&int) { return ToneKind::Avoid; }
+    let major_third = tones.contains(&MAJOR_THIRD);
+    let dominant = major_third && tones.contains(&MINOR_SEVENTH);
+    let minor_seventh = tones.contains(&MINOR_THIRD) && tones.contains(&MINOR_SEVENTH);
+    let avoid = (rules.minor_ninth && tones.contains(&(int - 1).rem_euclid(OCTAVE))
+            && !(rules.dominant_exceptions && dominant && (int == FLAT_NINETH - OCTAVE || int == FLAT_THIRTEENTH - OCTAVE)))
+        || (rules.eleven_over_major_third && major_third && int == PERFECT_FOURTH)
+        || (rules.thirteen_over_minor_seventh && minor_seventh && int == MAJOR_SIXTH);
+    if avoid { ToneKind::Avoid } else { ToneKind::Tension }
+}
+
+// The label of a tone: chord tones as in the chord, the others as tensions, a ♯9 next to the
+// major third and a ♯5 in place of the fifth.
+pub fn tone_label(chord: &RootedChord, interval: Note) -> String{
+    let tones = chord_tones(chord);
+    let int = interval.rem_euclid(OCTAVE);
+    if int == AUGMENTED_FIFTH && tones.contains(&int) && !tones.contains(&PERFECT_FIFTH){
+        return String::from("♯5");
+    }
+    let label = match int{
+        _ if tones.contains(&int) => int,
+        MINOR_THIRD if tones.contains(&MAJOR_THIRD) => SHARP_NINETH,
+        _ => as_tension(int),
+    };
+    interval_chord_extension(label)
+}
+
+// Every tone of the mode built on the root of the chord, as chord tone, tension or avoid note.
+pub fn scale_tensions(chord: &RootedChord, mode: &ModeObj, rules: &TensionRules) -> Vec<ScaleTone>{
+    mode.steps.to_scale(0).0.into_iter()
+        .map(|i| ScaleTone{ interval: i, kind: tone_kind(chord, i, rules), label: tone_label(chord, i) })
+        .collect()
+}
+
+pub fn avoid_notes(chord: &RootedChord, scale: &[Note], rules: &TensionRules) -> Vec<Note>{
+    scale.iter().copied().filter(|i| tone_kind(chord, *i, rules) == ToneKind::Avoid).collect()
+}
+
+// Like: chord tones R ♮3 ♮5 ♮7, tensions ♮9 ♯11 ♮13, avoid -
+pub fn tensions_string(tones: &[ScaleTone]) -> String{
+    let of = |kind: ToneKind|{
+        let labels = tones.iter().filter(|t| t.kind == kind).map(|t| t.label.clone()).collect::<Vec<_>>();
+        if labels.is_empty() { String::from("-") } else { labels.join(" ") }
+    };
+    format!("chord tones {}, tensions {}, avoid {}", of(ToneKind::ChordTone), of(ToneKind::Tension), of(ToneKind::Avoid))
+}
+
+// Every mode of the library over the seventh chord on its first degree.
+pub fn all_scale_tensions(rules: &TensionRules) -> Vec<(String, Vec<ScaleTone>)>{
+    let namer = HeptatonicScaleNamer::new();
+    let mut res = Vec::new();
+    for mode in get_all_scale_objs().into_iter().flat_map(|sc| sc.get_modes()){
+        let chord = rooted_scale_chords(&mode.steps, C.0, 4).swap_remove(0);
+        let name = if mode.mode_name.is_empty() { namer.name(&mode.steps) } else { mode.mode_name.clone() };
+        let header = format!("{} ({})", name, chord.as_string(false, ChordStyling::Std));
+        res.push((header, scale_tensions(&chord, &mode, rules)));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::query::find_mode_by_name;
+    #[test]
+    fn test_tensions(){
+        let c = C.0 + 36;
+        let cmaj7 = RootedChord::from_intervals(c, MAJOR_SEVENTH_CHORD);
+        let ionian = find_mode_by_name("Ionian").unwrap();
+        let tones = scale_tensions(&cmaj7, &ionian, &TensionRules::default());
+        assert_eq!(tensions_string(&tones), "chord tones R ♮3 ♮5 ♮7, tensions ♮9 ♮13, avoid ♮11");
+        let lydian = find_mode_by_name("Lydian").unwrap();
+        assert_eq!(tensions_string(&scale_tensions(&cmaj7, &lydian, &TensionRules::default())), "chord tones R ♮3 ♮5 ♮7, tensions ♮9 ♯11 ♮13, avoid -");
+        // Over a dominant the ♭9 and ♭13 are available, the 11 is not.
+        let c7 = RootedChord::from_intervals(c, DOMINANT_SEVENTH);
+        let phrygian_dominant = find_mode_by_name("Phrygian Dominant").unwrap();
+        let tones = scale_tensions(&c7, &phrygian_dominant, &TensionRules::default());
+        assert_eq!(tensions_string(&tones), "chord tones R ♮3 ♮5 ♭7, tensions ♭9 ♭13, avoid ♮11");
+        let altered = find_mode_by_name("Altered Scale").unwrap();
+        let c7alt = RootedChord::from_intervals(c, &[MAJOR_THIRD, MINOR_SEVENTH]);
+        let labels = scale_tensions(&c7alt, &altered, &TensionRules::default()).into_iter().map(|t| t.label).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["R", "♭9", "♯9", "♮3", "♯11", "♭13", "♭7"]);
+        let cm7 = RootedChord::from_intervals(c, MINOR_SEVENTH_CHORD);
+        let dorian = find_mode_by_name("Dorian").unwrap();
+        let strict = TensionRules{ thirteen_over_minor_seventh: true, ..Default::default() };
+        assert_eq!(avoid_notes(&cm7, &dorian.steps.to_scale(0).0, &TensionRules::default()), Vec::<Note>::new());
+        assert_eq!(avoid_notes(&cm7, &dorian.steps.to_scale(0).0, &strict), vec![MAJOR_SIXTH]);
+        let all = all_scale_tensions(&TensionRules::default());
+        assert_eq!(all.len(), 70);
+        assert_eq!(all[9].0, "Ionian ♯5 (C+(♮7))");
+        assert_eq!(tensions_string(&all[9].1), "chord tones R ♮3 ♯5 ♮7, tensions ♮9, avoid ♮11 ♮13");
+    }
+}
@@ -0,0 +1,251 @@
+use crate::theory::note::*;
+use crate::theory::interval::OCTAVE;
+use crate::theory::chord::{ RootedChord, ChordBook, ChordStyling, STD_CHORD_BOOK };
+use crate::theory::tuning::ConcertPitch;
+use super::fft::magnitude_spectrum;
+use super::wav::Wav;
+
+/// Energy per pitchclass, indexed by the number of the PC.
+pub type Chroma = [f32; 12];
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct ChromaExtractor{
+    // Power of two.
+    pub frame: usize,
+    pub hop: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    pub concert: ConcertPitch,
+}
+
+impl Default for ChromaExtractor{
+    fn default() -> Self{
+        Self{
+            frame: 4096,
+            hop: 2048,
+            min_freq: 55.0,
+            max_freq: 4000.0,
+            concert: ConcertPitch::default(),
+        }
+    }
+}
+
+impl ChromaExtractor{
+    // Every fft bin is folded onto the pitchclass nearest to its frequency.
+    pub fn chroma(&self, frame: &[f32], sample_rate: u32) -> Chroma{
+        let mut chroma = [0.0; 12];
+        let spectrum = magnitude_spectrum(frame);
+        let bin_hz = sample_rate as f32 / (2 * spectrum.len()) as f32;
+        for (i, mag) in spectrum.into_iter().enumerate().skip(1){
+            let hz = i as f32 * bin_hz;
+            if hz < self.min_freq || hz > self.max_freq { continue; }
+            let pc = self.concert.from_frequency(hz).pc();
+            chroma[pc.0 as usize] += mag;
+        }
+        chroma
+    }
+
+    pub fn chromagram(&self, samples: &[f32], sample_rate: u32) -> Vec<Chroma>{
+        let mut res = Vec::new();
+        let mut start = 0;
+        while start + self.frame <= samples.len(){
+            res.push(self.chroma(&samples[start..start + self.frame], sample_rate));
+            start += self.hop;
+        }
+        res
+    }
+}
+
+/// A chord over a stretch of time in seconds, None being no chord.
+#[derive(Clone,PartialEq)]
+pub struct TimedChord{
+    pub chord: Option<RootedChord>,
+    pub onset: f32,
+    pub duration: f32,
+}
+
+impl TimedChord{
+    pub fn as_string(&self, styling: ChordStyling) -> String{
+        match &self.chord{
+            Some(c) => c.as_string(false, styling),
+            None => String::from("N.C."),
+        }
+    }
+}
+
+impl std::fmt::Display for TimedChord{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{:.2}s {} ({:.2}s)", self.onset, self.as_string(ChordStyling::Std), self.duration)
+    }
+}
+
+/// Matches chroma frames against chord templates.
+#[derive(Clone,PartialEq)]
+pub struct ChordRecognizer{
+    pub extractor: ChromaExtractor,
+    pub templates: Vec<RootedChord>,
+    // Amount of frames averaged and voted over.
+    pub smoothing: usize,
+    // Chords shorter than this many seconds are merged into the previous one.
+    pub min_duration: f32,
+    // Frames quieter than this (rms) have no chord.
+    pub silence: f32,
+}
+
+impl Default for ChordRecognizer{
+    fn default() -> Self{
+        Self{
+            extractor: ChromaExtractor::default(),
+            templates: chord_templates(STD_CHORD_BOOK, ChordStyling::Std),
+            smoothing: 3,
+            min_duration: 0.3,
+            silence: 0.01,
+        }
+    }
+}
+
+// Every chord of the book on every root.
+pub fn chord_templates(book: ChordBook, styling: ChordStyling) -> Vec<RootedChord>{
+    let mut res = Vec::new();
+    for root in 0..OCTAVE{
+        for (pattern, _, _, ext) in book{
+            if *ext && styling == ChordStyling::Std { continue; }
+            res.push(RootedChord::from_intervals(root, pattern));
+        }
+    }
+    res
+}
+
+fn template_score(chroma: &Chroma, template: &RootedChord) -> f32{
+    let mut mask = [false; 12];
+    for note in template.to_scale().0{
+        mask[note.to_pc().0 as usize] = true;
+    }
+    let norm = chroma.iter().map(|c| c * c).sum::<f32>().sqrt();
+    let count = mask.iter().filter(|m| **m).count() as f32;
+    if norm <= 0.0 || count == 0.0 { return 0.0; }
+    let dot = chroma.iter().zip(mask.iter()).filter(|(_, m)| **m).map(|(c, _)| c).sum::<f32>();
+    let max = chroma.iter().fold(0.0f32, |a, b| a.max(*b));
+    // Cosine similarity, ties (Am7 against C6) go to the strongest root.
+    dot / (norm * count.sqrt()) + 0.05 * chroma[template.root.to_pc().0 as usize] / max
+}
+
+impl ChordRecognizer{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn label(&self, chroma: &Chroma) -> Option<RootedChord>{
+        let mut best: Option<(f32, &RootedChord)> = None;
+        for template in &self.templates{
+            let score = template_score(chroma, template);
+            if best.map(|(s, _)| score > s).unwrap_or(true){
+                best = Some((score, template));
+            }
+        }
+        best.map(|(_, t)| t.clone())
+    }
+
+    pub fn recognize(&self, samples: &[f32], sample_rate: u32) -> Vec<TimedChord>{
+        let ext = &self.extractor;
+        let hop_time = ext.hop as f32 / sample_rate as f32;
+        let offset = ext.frame as f32 / 2.0 / sample_rate as f32;
+        let chromagram = ext.chromagram(samples, sample_rate);
+        let half = self.smoothing / 2;
+        let window = |i: usize, len: usize| (i.saturating_sub(half), (i + half + 1).min(len));
+        // Average the chroma over neighbouring frames, then label.
+        let mut labels = Vec::new();
+        for i in 0..chromagram.len(){
+            let frame = &samples[i * ext.hop..i * ext.hop + ext.frame];
+            let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+            if rms < self.silence{
+                labels.push(None);
+                continue;
+            }
+            let (from, to) = window(i, chromagram.len());
+            let mut avg = [0.0; 12];
+            for c in &chromagram[from..to]{
+                for (a, v) in avg.iter_mut().zip(c.iter()){
+                    *a += v;
+                }
+            }
+            labels.push(self.label(&avg));
+        }
+        // Majority vote to stop the labels from flickering.
+        let voted = (0..labels.len()).map(|i|{
+            let (from, to) = window(i, labels.len());
+            let cands = &labels[from..to];
+            cands.iter().max_by_key(|c| cands.iter().filter(|d| d == c).count()).cloned().flatten()
+        }).collect::<Vec<_>>();
+        let mut res: Vec<TimedChord> = Vec::new();
+        for (i, chord) in voted.into_iter().enumerate(){
+            match res.last_mut(){
+                Some(last) if last.chord == chord => { last.duration += hop_time; },
+                _ => res.push(TimedChord{ chord, onset: i as f32 * hop_time + offset, duration: hop_time }),
+            }
+        }
+        let mut merged: Vec<TimedChord> = Vec::new();
+        for tc in res{
+            match merged.last_mut(){
+                Some(last) if tc.duration < self.min_duration || last.chord == tc.chord => { last.duration += tc.duration; },
+                _ => merged.push(tc),
+            }
+        }
+        if let Some(first) = merged.first_mut(){
+            first.duration += first.onset;
+            first.onset = 0.0;
+        }
+        merged
+    }
+
+    pub fn recognize_wav(&self, wav: &Wav) -> Vec<TimedChord>{
+        self.recognize(&wav.mono(), wav.sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::theory::interval::*;
+    use crate::theory::chord::*;
+    const RATE: u32 = 8000;
+
+    fn synth(chords: &[RootedChord], seconds: f32) -> Vec<f32>{
+        let len = (seconds * RATE as f32) as usize;
+        let mut res = Vec::new();
+        for chord in chords{
+            let notes = chord.to_scale().0;
+            for i in 0..len{
+                let t = i as f32 / RATE as f32;
+                let mut s = 0.0;
+                for note in &notes{
+                    let hz = to_pitch(*note);
+                    s += (std::f32::consts::TAU * hz * t).sin() + 0.3 * (std::f32::consts::TAU * 2.0 * hz * t).sin();
+                }
+                res.push(0.2 * s / notes.len() as f32);
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn test_recognize_chords(){
+        let chords = vec![
+            RootedChord::from_intervals(A4 - MAJOR_SIXTH, MAJOR),
+            RootedChord::from_intervals(A4 - OCTAVE, MINOR),
+            RootedChord::from_intervals(A4 - MAJOR_SECOND - OCTAVE, DOMINANT_SEVENTH),
+        ];
+        let samples = synth(&chords, 1.5);
+        let res = ChordRecognizer::new().recognize(&samples, RATE);
+        let names = res.iter().map(|tc| tc.as_string(ChordStyling::Std)).collect::<Vec<_>>();
+        assert_eq!(names, vec!["C", "Am", "G⁷"]);
+        assert!((res[1].onset - 1.5).abs() < 0.3);
+        // Frames that are not a power of two are padded.
+        let mut odd = ChordRecognizer::new();
+        odd.extractor.frame = 3000;
+        let names = odd.recognize(&samples, RATE).iter().map(|tc| tc.as_string(ChordStyling::Std)).collect::<Vec<_>>();
+        assert_eq!(names.first().map(|s| s.as_str()), Some("C"));
+        assert!(names.iter().any(|n| n == "Am"));
+        assert_eq!(names.last().map(|s| s.as_str()), Some("G⁷"));
+    }
+}
@@ -0,0 +1,72 @@
+use std::f32::consts::PI;
+
+// In place radix-2 fft, None when the length is not a power of two.
+pub fn fft(re: &mut [f32], im: &mut [f32]) -> Option<()>{
+    let n = re.len();
+    if !n.is_power_of_two() || im.len() != n { return None; }
+    let mut j = 0;
+    for i in 1..n{
+        let mut bit = n >> 1;
+        while j & bit != 0{
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j{
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n{
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len){
+            for k in 0..len / 2{
+                let (wr, wi) = ((angle * k as f32).cos(), (angle * k as f32).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * wr - im[b] * wi;
+                let ti = re[b] * wi + im[b] * wr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+    Some(())
+}
+
+// Magnitudes of the positive frequencies of a Hann windowed frame.
+// The frame is zero padded up to a power of two, bin i is i * sample_rate / (2 * len).
+pub fn magnitude_spectrum(frame: &[f32]) -> Vec<f32>{
+    let n = frame.len();
+    let padded = n.next_power_of_two();
+    let mut re = frame.iter().enumerate()
+        .map(|(i, s)| s * (0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos()))
+        .chain(std::iter::repeat_n(0.0, padded - n))
+        .collect::<Vec<_>>();
+    let mut im = vec![0.0; padded];
+    if fft(&mut re, &mut im).is_none() { return Vec::new(); }
+    re.iter().zip(im.iter()).take(padded / 2).map(|(r, i)| (r * r + i * i).sqrt()).collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_fft_peak(){
+        let n = 256;
+        let frame = (0..n).map(|i| (2.0 * PI * 10.0 * i as f32 / n as f32).sin()).collect::<Vec<_>>();
+        let mags = magnitude_spectrum(&frame);
+        let peak = mags.iter().enumerate().fold((0, 0.0), |best, (i, m)| if *m > best.1 { (i, *m) } else { best });
+        assert_eq!(peak.0, 10);
+        // 300 samples are padded to 512.
+        let frame = (0..300).map(|i| (2.0 * PI * 30.0 * i as f32 / 512.0).sin()).collect::<Vec<_>>();
+        let mags = magnitude_spectrum(&frame);
+        assert_eq!(mags.len(), 256);
+        let peak = mags.iter().enumerate().fold((0, 0.0), |best, (i, m)| if *m > best.1 { (i, *m) } else { best });
+        assert_eq!(peak.0, 30);
+        assert_eq!(fft(&mut [0.0; 3], &mut [0.0; 3]), None);
+    }
+}
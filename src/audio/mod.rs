@@ -0,0 +1,11 @@
+pub mod wav;
+pub mod pitch;
+pub mod fft;
+pub mod chroma;
+pub mod synth;
+
+pub use wav::*;
+pub use pitch::*;
+pub use fft::*;
+pub use chroma::*;
+pub use synth::*;
@@ -0,0 +1,211 @@
+use crate::theory::note::*;
+use crate::theory::interval::OCTAVE;
+use crate::theory::tuning::ConcertPitch;
+use super::wav::Wav;
+
+/// A detected note: when it starts and how long it lasts in seconds,
+/// and how far it was from the tempered pitch on average.
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct NoteEvent{
+    pub note: Note,
+    pub onset: f32,
+    pub duration: f32,
+    pub cents: f32,
+}
+
+impl std::fmt::Display for NoteEvent{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        // Octaves start on C, A0 is note 0.
+        let octave = (self.note + 9).div_euclid(OCTAVE);
+        write!(f, "{:.3}s {}{} {:.3}s {:+.1}¢", self.onset, self.note.to_pc(), octave, self.duration, self.cents)
+    }
+}
+
+/// Monophonic pitch tracking with the YIN algorithm.
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct PitchTracker{
+    pub frame: usize,
+    pub hop: usize,
+    // Lower finds less pitches but makes less octave errors.
+    pub threshold: f32,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    // Frames quieter than this (rms) are silence.
+    pub silence: f32,
+    // Notes shorter than this many seconds are dropped as glitches.
+    pub min_duration: f32,
+}
+
+impl Default for PitchTracker{
+    fn default() -> Self{
+        Self{
+            frame: 2048,
+            hop: 512,
+            threshold: 0.15,
+            min_freq: 60.0,
+            max_freq: 1500.0,
+            silence: 0.01,
+            min_duration: 0.1,
+        }
+    }
+}
+
+impl PitchTracker{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    // Fundamental frequency of one frame, None when unvoiced.
+    pub fn yin(&self, frame: &[f32], sample_rate: u32) -> Option<f32>{
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len().max(1) as f32).sqrt();
+        if rms < self.silence { return None; }
+        let tau_min = ((sample_rate as f32 / self.max_freq) as usize).max(2);
+        let tau_max = (sample_rate as f32 / self.min_freq) as usize;
+        if tau_max * 2 > frame.len() { return None; }
+        let window = frame.len() - tau_max;
+        // Cumulative mean normalized difference.
+        let mut diff = vec![1.0; tau_max + 1];
+        let mut running = 0.0;
+        for (tau, d) in diff.iter_mut().enumerate().skip(1){
+            let mut sum = 0.0;
+            for i in 0..window{
+                let delta = frame[i] - frame[i + tau];
+                sum += delta * delta;
+            }
+            running += sum;
+            *d = if running > 0.0 { sum * tau as f32 / running } else { 1.0 };
+        }
+        let mut tau = tau_min;
+        while tau < tau_max{
+            if diff[tau] < self.threshold{
+                while tau + 1 < tau_max && diff[tau + 1] < diff[tau]{
+                    tau += 1;
+                }
+                break;
+            }
+            tau += 1;
+        }
+        if tau >= tau_max { return None; }
+        // Parabolic interpolation around the dip.
+        let (a, b, c) = (diff[tau - 1], diff[tau], diff[tau + 1]);
+        let denom = a + c - 2.0 * b;
+        let shift = if denom.abs() > f32::EPSILON { (a - c) / (2.0 * denom) } else { 0.0 };
+        Some(sample_rate as f32 / (tau as f32 + shift))
+    }
+
+    // The frame grows with the sample rate so it always holds two periods of min_freq.
+    pub fn frame_len(&self, sample_rate: u32) -> usize{
+        self.frame.max(2 * (sample_rate as f32 / self.min_freq) as usize)
+    }
+
+    // One frequency estimate per hop.
+    pub fn track(&self, samples: &[f32], sample_rate: u32) -> Vec<Option<f32>>{
+        let frame = self.frame_len(sample_rate);
+        let mut res = Vec::new();
+        let mut start = 0;
+        while start + frame <= samples.len(){
+            res.push(self.yin(&samples[start..start + frame], sample_rate));
+            start += self.hop;
+        }
+        res
+    }
+
+    // Joins frames with the same nearest note into note events.
+    pub fn notes(&self, samples: &[f32], sample_rate: u32, concert: ConcertPitch) -> Vec<NoteEvent>{
+        let hop_time = self.hop as f32 / sample_rate as f32;
+        // Frames are timed by their center.
+        let offset = self.frame_len(sample_rate) as f32 / 2.0 / sample_rate as f32;
+        let mut res = Vec::new();
+        // (note, first frame, frame count, summed cents)
+        let mut current: Option<(Note, usize, usize, f32)> = None;
+        let flush = |cur: Option<(Note, usize, usize, f32)>, res: &mut Vec<NoteEvent>|{
+            if let Some((note, first, count, cents)) = cur{
+                let duration = count as f32 * hop_time;
+                if duration >= self.min_duration{
+                    res.push(NoteEvent{ note, onset: first as f32 * hop_time + offset, duration, cents: cents / count as f32 });
+                }
+            }
+        };
+        for (i, freq) in self.track(samples, sample_rate).into_iter().enumerate(){
+            let reading = freq.map(|hz| concert.from_frequency(hz));
+            match (current, reading){
+                (Some((note, first, count, cents)), Some(r)) if r.note == note => {
+                    current = Some((note, first, count + 1, cents + r.cents));
+                },
+                (_, r) => {
+                    flush(current, &mut res);
+                    current = r.map(|r| (r.note, i, 1, r.cents));
+                },
+            }
+        }
+        flush(current, &mut res);
+        res
+    }
+
+    pub fn wav_notes(&self, wav: &Wav, concert: ConcertPitch) -> Vec<NoteEvent>{
+        self.notes(&wav.mono(), wav.sample_rate, concert)
+    }
+}
+
+// Pitchclasses in order of first appearance, ready for the analysis.
+pub fn events_to_pcs(events: &[NoteEvent]) -> PCs{
+    let mut res = Vec::new();
+    for event in events{
+        let pc = event.note.to_pc();
+        if !res.contains(&pc){
+            res.push(pc);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::theory::tuning::A440;
+    use crate::theory::interval::*;
+    const RATE: u32 = 8000;
+
+    fn synth(notes: &[Note], seconds: f32, saw: bool) -> Vec<f32>{
+        synth_at(notes, seconds, saw, RATE)
+    }
+
+    fn synth_at(notes: &[Note], seconds: f32, saw: bool, rate: u32) -> Vec<f32>{
+        let len = (seconds * rate as f32) as usize;
+        let mut res = Vec::new();
+        for note in notes{
+            let hz = to_pitch(*note);
+            for i in 0..len{
+                let phase = (i as f32 * hz / rate as f32).fract();
+                res.push(if saw { 0.5 * (2.0 * phase - 1.0) } else { 0.5 * (phase * std::f32::consts::TAU).sin() });
+            }
+            res.extend(std::iter::repeat_n(0.0, len / 4));
+        }
+        res
+    }
+
+    #[test]
+    fn test_track_sines(){
+        let samples = synth(&[A4, A4 + MAJOR_THIRD, A4 - PERFECT_FOURTH], 0.4, false);
+        let wav = Wav::parse(&Wav::new(RATE, 1, samples).to_bytes(16)).unwrap();
+        let events = PitchTracker::new().wav_notes(&wav, A440);
+        assert_eq!(events.iter().map(|e| e.note).collect::<Vec<_>>(), vec![A4, A4 + MAJOR_THIRD, A4 - PERFECT_FOURTH]);
+        assert!(events.iter().all(|e| e.cents.abs() < 10.0));
+        assert!((events[1].onset - 0.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_track_saws(){
+        let samples = synth(&[A4 - PERFECT_FIFTH, A4 - MINOR_THIRD], 0.4, true);
+        let events = PitchTracker::new().notes(&samples, RATE, A440);
+        assert_eq!(events_to_pcs(&events), vec![D, FS]);
+    }
+
+    #[test]
+    fn test_track_high_rate(){
+        let events = PitchTracker::new().notes(&synth_at(&[A4, A4 - OCTAVE], 0.3, false, 96000), 96000, A440);
+        assert_eq!(events.iter().map(|e| e.note).collect::<Vec<_>>(), vec![A4, A4 - OCTAVE]);
+        assert!(events[0].to_string().contains(" A4 "));
+        assert!(events[1].to_string().contains(" A3 "));
+    }
+}
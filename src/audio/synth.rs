@@ -0,0 +1,158 @@
+use crate::theory::note::*;
+use crate::theory::chord::RootedChord;
+use crate::theory::tuning::{ Tuning, A440 };
+use super::wav::Wav;
+use std::f32::consts::TAU;
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Waveform{ Sine, Triangle, Saw }
+
+impl Waveform{
+    // One period over phase 0..1.
+    pub fn sample(&self, phase: f32) -> f32{
+        match self{
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+            Waveform::Saw => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+/// Attack, decay and release in seconds, sustain as a level.
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct Envelope{
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Default for Envelope{
+    fn default() -> Self{
+        Self{ attack: 0.01, decay: 0.1, sustain: 0.7, release: 0.2 }
+    }
+}
+
+impl Envelope{
+    // Level at time t after note on, with the note released at held.
+    pub fn level(&self, t: f32, held: f32) -> f32{
+        let before_release = |t: f32|{
+            if t < self.attack { t / self.attack }
+            else if t < self.attack + self.decay { 1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay }
+            else { self.sustain }
+        };
+        if t < held { return before_release(t); }
+        let from = before_release(held);
+        if self.release <= 0.0 { return 0.0; }
+        (from * (1.0 - (t - held) / self.release)).max(0.0)
+    }
+}
+
+/// A note to play, times in seconds, velocity 0..1.
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct SynthNote{
+    pub note: Note,
+    pub start: f32,
+    pub duration: f32,
+    pub velocity: f32,
+}
+
+impl SynthNote{
+    pub fn new(note: Note, start: f32, duration: f32) -> Self{
+        Self{ note, start, duration, velocity: 0.8 }
+    }
+}
+
+pub struct Synth{
+    pub sample_rate: u32,
+    pub waveform: Waveform,
+    pub envelope: Envelope,
+    pub tuning: Box<dyn Tuning>,
+}
+
+impl Default for Synth{
+    fn default() -> Self{
+        Self{
+            sample_rate: 44100,
+            waveform: Waveform::Triangle,
+            envelope: Envelope::default(),
+            tuning: Box::new(A440),
+        }
+    }
+}
+
+impl Synth{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    // Mixes all notes into one mono track, scaled down only when it would clip.
+    pub fn render(&self, notes: &[SynthNote]) -> Wav{
+        let rate = self.sample_rate as f32;
+        let end = notes.iter().map(|n| n.start + n.duration + self.envelope.release).fold(0.0, f32::max);
+        let mut samples = vec![0.0; (end * rate).ceil() as usize];
+        for sn in notes{
+            let hz = self.tuning.to_pitch(sn.note);
+            if !hz.is_finite() { continue; }
+            let first = (sn.start * rate) as usize;
+            let len = ((sn.duration + self.envelope.release) * rate) as usize;
+            for (i, sample) in samples.iter_mut().skip(first).take(len).enumerate(){
+                let t = i as f32 / rate;
+                *sample += sn.velocity * self.envelope.level(t, sn.duration) * self.waveform.sample((t * hz).fract());
+            }
+        }
+        let peak = samples.iter().fold(0.0f32, |a, s| a.max(s.abs()));
+        if peak > 0.9{
+            samples.iter_mut().for_each(|s| *s *= 0.9 / peak);
+        }
+        Wav::new(self.sample_rate, 1, samples)
+    }
+
+    pub fn render_scale(&self, scale: &Scale, note_len: f32) -> Wav{
+        self.render(&scale_notes(scale, note_len))
+    }
+
+    pub fn render_chord(&self, chord: &RootedChord, len: f32) -> Wav{
+        self.render(&chord_notes(chord, 0.0, len))
+    }
+
+    pub fn render_arpeggio(&self, chord: &RootedChord, note_len: f32) -> Wav{
+        self.render(&scale_notes(&chord.to_scale(), note_len))
+    }
+
+    pub fn render_progression(&self, chords: &[RootedChord], chord_len: f32) -> Wav{
+        let notes = chords.iter().enumerate()
+            .flat_map(|(i, c)| chord_notes(c, i as f32 * chord_len, chord_len))
+            .collect::<Vec<_>>();
+        self.render(&notes)
+    }
+}
+
+// One after the other.
+pub fn scale_notes(scale: &Scale, note_len: f32) -> Vec<SynthNote>{
+    scale.0.iter().enumerate().map(|(i, n)| SynthNote::new(*n, i as f32 * note_len, note_len)).collect::<Vec<_>>()
+}
+
+// All at once.
+pub fn chord_notes(chord: &RootedChord, start: f32, len: f32) -> Vec<SynthNote>{
+    chord.to_scale().0.into_iter().map(|n| SynthNote::new(n, start, len)).collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::theory::chord::MAJOR;
+    use crate::audio::pitch::PitchTracker;
+    #[test]
+    fn test_render_and_track(){
+        let synth = Synth{ sample_rate: 8000, ..Synth::default() };
+        let scale = Scale(vec![A4, A4 + 2, A4 + 4]);
+        let wav = Wav::parse(&synth.render_scale(&scale, 0.5).to_bytes(24)).unwrap();
+        let tracker = PitchTracker{ frame: 512, hop: 128, ..PitchTracker::new() };
+        let events = tracker.wav_notes(&wav, A440);
+        assert_eq!(events.iter().map(|e| e.note).collect::<Vec<_>>(), scale.0);
+        let wav = synth.render_progression(&vec![RootedChord::from_intervals(A4, MAJOR); 2], 1.0);
+        assert!((wav.duration() - 2.0 - synth.envelope.release).abs() < 0.01);
+        assert!(wav.samples.iter().all(|s| s.abs() <= 0.9));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs;
+
+const PCM: u16 = 1;
+const IEEE_FLOAT: u16 = 3;
+const EXTENSIBLE: u16 = 0xFFFE;
+
+/// Decoded audio, samples interleaved per channel and scaled to -1.0..1.0.
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Wav{
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+fn u16_at(bytes: &[u8], i: usize) -> Option<u16>{
+    Some(u16::from_le_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]))
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> Option<u32>{
+    Some(u32::from_le_bytes([*bytes.get(i)?, *bytes.get(i + 1)?, *bytes.get(i + 2)?, *bytes.get(i + 3)?]))
+}
+
+fn decode_sample(bytes: &[u8], format: u16, bits: u16) -> Option<f32>{
+    Some(match (format, bits){
+        (PCM, 8) => (bytes[0] as f32 - 128.0) / 128.0,
+        (PCM, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        (PCM, 24) => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8_388_608.0,
+        (PCM, 32) => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2_147_483_648.0,
+        (IEEE_FLOAT, 32) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        (IEEE_FLOAT, 64) => f64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]) as f32,
+        _ => return None,
+    })
+}
+
+impl Wav{
+    pub fn new(sample_rate: u32, channels: u16, samples: Vec<f32>) -> Self{
+        Self{ sample_rate, channels, samples }
+    }
+
+    pub fn parse(bytes: &[u8]) -> Option<Self>{
+        if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" { return None; }
+        let mut pos = 12;
+        let mut fmt = None;
+        while pos + 8 <= bytes.len(){
+            let id = &bytes[pos..pos + 4];
+            let size = u32_at(bytes, pos + 4)? as usize;
+            let body = pos + 8;
+            let end = (body + size).min(bytes.len());
+            if id == b"fmt "{
+                let mut format = u16_at(bytes, body)?;
+                let channels = u16_at(bytes, body + 2)?;
+                let sample_rate = u32_at(bytes, body + 4)?;
+                let bits = u16_at(bytes, body + 14)?;
+                if format == EXTENSIBLE{
+                    format = u16_at(bytes, body + 24)?;
+                }
+                fmt = Some((format, channels, sample_rate, bits));
+            } else if id == b"data"{
+                let (format, channels, sample_rate, bits) = fmt?;
+                let width = (bits as usize).div_ceil(8);
+                if width == 0 || channels == 0 { return None; }
+                let mut samples = Vec::with_capacity((end - body) / width);
+                for chunk in bytes[body..end].chunks_exact(width){
+                    samples.push(decode_sample(chunk, format, bits)?);
+                }
+                return Some(Self{ sample_rate, channels, samples });
+            }
+            // Chunks are padded to an even size.
+            pos = body + size + size % 2;
+        }
+        None
+    }
+
+    // 8, 16, 24 or 32 bit integer PCM, other sizes are written as 16 bit.
+    pub fn to_bytes(&self, bits: u16) -> Vec<u8>{
+        let bits = if [8, 16, 24, 32].contains(&bits) { bits } else { 16 };
+        let width = bits as usize / 8;
+        let data_len = self.samples.len() * width;
+        // The data chunk is padded to an even size.
+        let pad = data_len % 2;
+        let mut res = Vec::with_capacity(44 + data_len + pad);
+        res.extend_from_slice(b"RIFF");
+        res.extend_from_slice(&(36 + (data_len + pad) as u32).to_le_bytes());
+        res.extend_from_slice(b"WAVEfmt ");
+        res.extend_from_slice(&16u32.to_le_bytes());
+        res.extend_from_slice(&PCM.to_le_bytes());
+        res.extend_from_slice(&self.channels.to_le_bytes());
+        res.extend_from_slice(&self.sample_rate.to_le_bytes());
+        res.extend_from_slice(&(self.sample_rate * self.channels as u32 * width as u32).to_le_bytes());
+        res.extend_from_slice(&(self.channels * width as u16).to_le_bytes());
+        res.extend_from_slice(&bits.to_le_bytes());
+        res.extend_from_slice(b"data");
+        res.extend_from_slice(&(data_len as u32).to_le_bytes());
+        for sample in &self.samples{
+            let s = sample.clamp(-1.0, 1.0) as f64;
+            match bits{
+                // 8 bit samples are unsigned.
+                8 => res.push((s * 127.0 + 128.0).round() as u8),
+                24 => res.extend_from_slice(&((s * 8_388_607.0).round() as i32).to_le_bytes()[0..3]),
+                32 => res.extend_from_slice(&((s * 2_147_483_647.0).round() as i32).to_le_bytes()),
+                _ => res.extend_from_slice(&((s * 32767.0).round() as i16).to_le_bytes()),
+            }
+        }
+        if pad == 1 { res.push(0); }
+        res
+    }
+
+    // All channels averaged.
+    pub fn mono(&self) -> Vec<f32>{
+        let channels = self.channels.max(1) as usize;
+        self.samples.chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect::<Vec<_>>()
+    }
+
+    pub fn duration(&self) -> f32{
+        self.samples.len() as f32 / self.channels.max(1) as f32 / self.sample_rate as f32
+    }
+}
+
+pub fn read_wav(path: &str) -> Option<Wav>{
+    Wav::parse(&fs::read(path).ok()?)
+}
+
+pub fn write_wav(path: &str, wav: &Wav, bits: u16) -> std::io::Result<()>{
+    if ![8, 16, 24, 32].contains(&bits){
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("can't write {} bit wav", bits)));
+    }
+    fs::write(path, wav.to_bytes(bits))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_wav_roundtrip(){
+        let samples = vec![0.0, 0.5, -0.5, 1.0, -1.0, 0.25];
+        for bits in &[8, 16, 24, 32]{
+            let wav = Wav::parse(&Wav::new(8000, 2, samples.clone()).to_bytes(*bits)).unwrap();
+            assert_eq!(wav.sample_rate, 8000);
+            assert_eq!(wav.channels, 2);
+            for (a, b) in wav.samples.iter().zip(samples.iter()){
+                assert!((a - b).abs() < 0.01);
+            }
+            assert_eq!(wav.mono().len(), 3);
+        }
+        // Three 8 bit samples take a pad byte.
+        let bytes = Wav::new(8000, 1, vec![0.0, 0.5, -0.5]).to_bytes(8);
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(u32_at(&bytes, 4), Some(40));
+        assert_eq!(u32_at(&bytes, 40), Some(3));
+        assert_eq!(Wav::parse(&bytes).unwrap().samples.len(), 3);
+    }
+}
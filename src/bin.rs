@@ -4,31 +4,411 @@ use music_theory::libr::scales::*;
 use music_theory::libr::infos::*;
 use music_theory::query::*;
 use music_theory::utils::*;
+use music_theory::audio::*;
+use music_theory::formats::*;
+use music_theory::fretted::*;
+use music_theory::render::*;
+use music_theory::analysis::*;
 // jazzbøt
 fn main(){
     let args = lapp::parse_args("
         -c, --chord (default '') comma seperated vector of notes, interpreted as chord
+        -f, --frequencies (default '') comma seperated vector of frequencies in Hz, interpreted as chord
+        --concert (default 440.0) frequency of A4 in Hz
+        -w, --wav (default '') wav file with a melody, the notes sung or played are analysed
+        --chordchart (default '') wav file with a recording, prints the chords heard over time
+        --render (default '') wav file to render the chord to, as arpeggio followed by the chord
+        --waveform (default 'triangle') can be sine, triangle, saw
+        --bits (default 16) bits per sample of the rendered wav file, 16 or 24
+        --lilypond (default '') file to write the scale catalogue to as LilyPond source
+        --abc (default '') ABC file, prints a modal analysis of every tune in it
+        --chordpro (default '') ChordPro song, printed again after --transpose, --tokey and --capo
+        --transpose (default 0) semitones to transpose the ChordPro song by
+        --tokey (default '') key to transpose the ChordPro song to, like Bb or F#m
+        --capo (default '') capo position to rewrite the ChordPro chord shapes for
+        --fretboard (default '') instrument to show --chord or --mode on: guitar, dropd, dadgad, bass, ukulele, mandolin or notes like D,A,D,G,B,E or G4,C4,E4,A4
+        --mode (default '') mode from the library, like 'Lydian Dominant', on the first note of --chord
+        --frets (default '0-12') range of frets to show
+        --labels (default 'degrees') can be degrees, names, dots
+        --staff (default '') also draw the scales of --chord and -t on a staff: treble, bass, grand or auto
+        --ascii use plain ascii for the diagrams
+        --keyboard (default '') adds a piano Keyboard section to the --chord analysis, labelled with names, degrees or fingers; notes with octaves like C3,E4,Bb4 are drawn as a voicing
+        --split (default '') note like C4, the keys below it are played by the left hand
+        --octaves (default 1) how many octaves of --mode to draw on the keyboard
+        --tensions prints the chord tones, tensions and avoid notes of --mode over --chord, or of every library mode over its seventh chord
+        --progression (default '') lead sheet like '| Dm7 G7 | Cmaj7 |', prints the scales to play over every chord in --key
+        --reharm prints reharmonization candidates for --progression instead of its scales
+        --interchange (default '') families like 'Ionian,Melodic Minor' or 'all', prints the chords of their modes on the tonic of --key, borrowed from the home --mode marked
+        --keys adds a Key section to the --chord and --wav analysis, ranking the keys the notes could be in
+        --profile (default 'krumhansl') key profile for --keys, krumhansl or temperley
+        --modulate (default '') key like E or C#m to go to from --key, prints the pivot chords or a way through keys in between
+        --key (default 'C') key of the progression like Bb or F#m, or the tonic of --mode
+        --svg (default '') file to write a --diagram of --chord, or of --mode on its first note, to
+        --diagram (default 'keyboard') can be keyboard, fretboard, fifths, clock
+        --shapes prints the easiest fingerings of --chord on the --fretboard instrument, guitar by default, with --capo
+        --tab (default '') ASCII tab file, its notes are analysed; --fretboard sets the tuning if the tab does not
+        -x, --musicxml (default '') MusicXML score, prints a chord by chord analysis
         -t, --test testing output
         --chordstyling (default 'std') can be std, extended, spelled
     ");
     let chord = args.get_string("chord");
+    // A voicing has every note with its octave.
+    let voicing = chord.split(',').map(parse_pitch).collect::<Option<Vec<_>>>().filter(|_| !chord.is_empty());
+    let chord = match &voicing{
+        Some(v) => v.iter().map(|(_, en)| en.to_string_name()).collect::<Vec<_>>().join(","),
+        None => chord,
+    };
+    let frequencies = args.get_string("frequencies");
+    let concert = ConcertPitch(args.get_float("concert"));
+    let wav = args.get_string("wav");
+    let chordchart = args.get_string("chordchart");
+    let render = args.get_string("render");
+    let waveform = match args.get_string("waveform").as_ref(){
+        "sine" => Waveform::Sine,
+        "saw" => Waveform::Saw,
+        _ => Waveform::Triangle,
+    };
+    let musicxml = args.get_string("musicxml");
+    let lilypond = args.get_string("lilypond");
+    let abc = args.get_string("abc");
+    let chordpro = args.get_string("chordpro");
+    let transpose = args.get_integer("transpose");
+    let tokey = args.get_string("tokey");
+    let capo = args.get_string("capo").parse::<i32>().ok();
+    let fretboard = args.get_string("fretboard");
+    let mode = args.get_string("mode");
+    let frets = args.get_string("frets");
+    let labels = match args.get_string("labels").as_ref(){
+        "names" => FretLabels::Names,
+        "dots" => FretLabels::Dots,
+        _ => FretLabels::Degrees,
+    };
+    let ascii = args.get_bool("ascii");
+    let staff = Staff::parse(&args.get_string("staff")).map(|staff| StaffOptions{ staff, unicode: !ascii });
+    let shapes = args.get_bool("shapes");
+    let keyboard = args.get_string("keyboard");
+    let split = parse_pitch(&args.get_string("split")).map(|(n, _)| n);
+    let octaves = args.get_integer("octaves").max(1);
+    let svg = args.get_string("svg");
+    let tensions = args.get_bool("tensions");
+    let progression = args.get_string("progression");
+    let reharm = args.get_bool("reharm");
+    let key = args.get_string("key");
+    let interchange = args.get_string("interchange");
+    let keys = args.get_bool("keys");
+    let modulate = args.get_string("modulate");
+    let profile = if args.get_string("profile") == "temperley" { KeyProfile::Temperley } else { KeyProfile::Krumhansl };
+    let diagram = args.get_string("diagram");
+    let tab = args.get_string("tab");
+    let bits = if args.get_integer("bits") == 24 { 24 } else { 16 };
     let test = args.get_bool("test");
     let style = match args.get_string("chordstyling").as_ref(){
         "extended" => ChordStyling::Extended,
         "spelled" => ChordStyling::SpelledOut,
         _ => ChordStyling::Std,
     };
-    if test { dotest(); }
+    if test { dotest(staff); }
+    if !chord.is_empty() && !render.is_empty() {
+        let pcs = chord.clone().into_enharmonic_notes().into_iter().map(|en| en.to_pc()).collect::<Vec<_>>();
+        let rchord = RootedChord::from_scale(pcs.to_scale(3));
+        let synth = Synth{ waveform, tuning: Box::new(concert), ..Synth::default() };
+        let mut notes = scale_notes(&rchord.to_scale(), 0.5);
+        notes.extend(chord_notes(&rchord, notes.len() as f32 * 0.5, 2.0));
+        if let Err(e) = write_wav(&render, &synth.render(&notes), bits){
+            println!("Could not write wav file {}: {}", render, e);
+        }
+    }
+    if !fretboard.is_empty() {
+        let instrument = match Instrument::parse(&fretboard){
+            Some(i) => i,
+            None => { println!("Unknown instrument or tuning: {}", fretboard); return; },
+        };
+        let Some((first, last)) = fret_range(&frets) else { println!("Could not read frets: {}", frets); return; };
+        let options = FretboardOptions{ first, last, labels, unicode: !ascii };
+        let ens = chord.clone().into_enharmonic_notes();
+        if let Some(root) = ens.first().copied(){
+            if mode.is_empty(){
+                let rchord = chord_of(&ens);
+                println!("{} on {}", rchord.as_string(false, style), instrument.name);
+                print_fretboard(&instrument, &chord_marks(&rchord, Some(root)), &options);
+            } else {
+                match find_mode_by_name(&mode){
+                    Some(mo) => {
+                        println!("{} {} on {}", root.to_string_name(), mo, instrument.name);
+                        print_fretboard(&instrument, &scale_marks(&mo.steps.to_scale(root.to_note(36)), Some(root)), &options);
+                    },
+                    None => println!("Unknown mode: {}", mode),
+                }
+            }
+        }
+    }
+    if !svg.is_empty() && !chord.is_empty() {
+        let ens = chord.clone().into_enharmonic_notes();
+        let Some(root) = ens.first().copied() else { println!("Could not read notes: {}", chord); return; };
+        let mo = if mode.is_empty() { None } else { find_mode_by_name(&mode) };
+        let marks = match &mo{
+            Some(mo) => scale_marks(&mo.steps.to_scale(root.to_note(36)), Some(root)),
+            None => chord_marks(&chord_of(&ens), Some(root)),
+        };
+        let options = SvgOptions{ labels, ..SvgOptions::default() };
+        let image = match diagram.as_ref(){
+            "fretboard" => {
+                let instrument = Instrument::parse(&fretboard).unwrap_or_else(Instrument::guitar);
+                let Some(range) = fret_range(&frets) else { println!("Could not read frets: {}", frets); return; };
+                fretboard_svg(&instrument, &marks, range, &options)
+            },
+            "fifths" => circle_of_fifths_svg(root, &mo.map(|mo| mo.steps).unwrap_or_else(ionian::steps), &options),
+            "clock" => pc_clock_svg(&marks, &options),
+            _ => keyboard_svg(root.to_note(36), root.to_note(36) + 23, &marks, &options),
+        };
+        if let Err(e) = write_svg(&svg, &image){
+            println!("Could not write svg file {}: {}", svg, e);
+        }
+    }
+    if tensions {
+        let rules = TensionRules::default();
+        let ens = chord.clone().into_enharmonic_notes();
+        match (ens.first(), find_mode_by_name(&mode).filter(|_| !mode.is_empty())){
+            (Some(root), Some(mo)) => {
+                let rchord = chord_of(&ens);
+                println!("{} {} over {}", root.to_string_name(), mo.mode_name, rchord.as_string(false, style));
+                println!("{}", tensions_string(&scale_tensions(&rchord, &mo, &rules)));
+            },
+            _ => {
+                for (header, tones) in all_scale_tensions(&rules){
+                    println!("{:<36} {}", header, tensions_string(&tones));
+                }
+            },
+        }
+    }
+    if !progression.is_empty() {
+        let Some(prog) = Progression::parse(&progression) else { println!("Could not read progression: {}", progression); return; };
+        let Some(sig) = parse_key(&key) else { println!("Unknown key: {}", key); return; };
+        let steps = key_steps(&sig, &mode);
+        if reharm {
+            let chords = prog.chords().map(|c| c.chord.clone()).collect::<Vec<_>>();
+            for r in progression_reharms(&prog, sig.tonic().to_note(36), &steps){
+                println!("{}", reharm_string(&chords, &r, style));
+            }
+            return;
+        }
+        let assigned = progression_chord_scales(&prog, sig.tonic().to_note(36), &steps);
+        println!("{}", chord_scale_annotation(&assigned, 0));
+        let namer = HeptatonicScaleNamer::new();
+        for (chord, scales) in &assigned{
+            println!("{}", chord.name());
+            for cs in scales.iter().take(4){
+                let avoid = cs.avoid_labels();
+                let avoid = if avoid.is_empty() { String::new() } else { format!(", avoid {}", avoid.join(" ")) };
+                println!("  {:<24} {:>3}{}", cs.name(&namer), cs.score, avoid);
+            }
+        }
+    }
+    if !interchange.is_empty() {
+        let Some(sig) = parse_key(&key) else { println!("Unknown key: {}", key); return; };
+        let home = key_steps(&sig, &mode);
+        let wanted = interchange.split(',').map(|f| f.trim().to_lowercase()).collect::<Vec<_>>();
+        let families = get_all_scale_objs().into_iter()
+            .filter(|sc| interchange == "all" || wanted.contains(&sc.family_name().to_lowercase()))
+            .collect::<Vec<_>>();
+        let rows = interchange_table(sig.tonic(), &home, families, style);
+        print!("{}", interchange_table_string(&rows, true));
+    }
+    if !modulate.is_empty() {
+        let (Some(sig), Some(target)) = (parse_key(&key), parse_key(&modulate)) else { println!("Unknown key: {} or {}", key, modulate); return; };
+        let from = Key::new(sig.tonic(), key_steps(&sig, &mode));
+        let to = Key::new(target.tonic(), key_steps(&target, ""));
+        match modulation_path(&from, &to){
+            Some(path) => println!("{}", modulation_string(&path, style)),
+            None => println!("No way found from {} to {}", key, modulate),
+        }
+    }
+    if shapes && !chord.is_empty() {
+        let instrument = Instrument::parse(&fretboard).unwrap_or_else(Instrument::guitar);
+        let ens = chord.clone().into_enharmonic_notes();
+        let rchord = chord_of(&ens);
+        let options = ShapeOptions{ capo: capo.unwrap_or(0), ..ShapeOptions::default() };
+        println!("{} on {}", rchord.as_string(false, style), instrument.name);
+        for shape in find_chord_shapes(&rchord, &instrument, &options).into_iter().take(8){
+            let score = &shape.score;
+            let mut notes = vec![format!("cost {}", score.cost()), format!("{} fingers", score.fingers)];
+            if score.barre { notes.push(String::from("barre")); }
+            if score.stretch > 2 { notes.push(format!("stretch {}", score.stretch)); }
+            if score.inner_muted > 0 { notes.push(format!("{} inner muted", score.inner_muted)); }
+            for int in &score.omitted { notes.push(format!("no {}", interval_chord_extension(*int))); }
+            if score.bass != 0 { notes.push(format!("{} in bass", interval_chord_extension(score.bass))); }
+            println!("  {:<16} {}", shape.to_string(), notes.join(", "));
+        }
+    }
     if !chord.is_empty() {
-        let res = music_theory::notes_analysis(chord, style);
+        let mut res = music_theory::notes_analysis_with(chord.clone(), style, staff);
+        let ens = chord.clone().into_enharmonic_notes();
+        if let Some(root) = ens.first().copied().filter(|_| !keyboard.is_empty()) {
+            let labels = match keyboard.as_ref(){
+                "degrees" => KeyLabels::Degrees,
+                "fingers" => KeyLabels::Fingers,
+                _ => KeyLabels::Names,
+            };
+            let options = KeyboardOptions{ labels, split, unicode: !ascii, ..KeyboardOptions::default() };
+            let mo = if mode.is_empty() { None } else { find_mode_by_name(&mode) };
+            let keys = match (&voicing, mo){
+                (Some(v), _) => voicing_keys(&v.iter().map(|(n, _)| *n).collect::<Vec<_>>(), root, &options),
+                (None, Some(mo)) => {
+                    let tonic = root.to_note(36);
+                    let mut scale = (0..octaves).flat_map(|o| mo.steps.to_scale(tonic + o * 12).0).collect::<Vec<_>>();
+                    scale.push(tonic + octaves * 12);
+                    scale_keys(&Scale(scale), Some(root), &options)
+                },
+                (None, None) => {
+                    let rchord = chord_of(&ens);
+                    chord_keys(&rchord, Some(root), &options)
+                },
+            };
+            res.insert(1.min(res.len()), ("Keyboard".to_string(), keyboard_string(&keys, &options)));
+        }
+        if keys {
+            let notes = ens.iter().map(|en| (en.to_note(36), 1.0)).collect::<Vec<_>>();
+            res.insert(1.min(res.len()), ("Key".to_string(), keys_string(&pc_distribution(&notes), profile)));
+        }
         for (header, content) in res{
             println!("\t{}", header);
             println!("{}", content);
         }
     }
+    if !frequencies.is_empty() {
+        let Some(hzs) = frequencies.split(',').map(|s| s.trim().parse::<f32>().ok().filter(|hz| hz.is_finite() && *hz > 0.0)).collect::<Option<Vec<_>>>()
+            else { println!("Could not read frequencies: {}", frequencies); return; };
+        for reading in concert.from_frequencies(&hzs){
+            println!("{}", reading);
+        }
+        let res = music_theory::frequencies_analysis(&hzs, concert, style);
+        for (header, content) in res{
+            println!("\t{}", header);
+            println!("{}", content);
+        }
+    }
+    if !chordchart.is_empty() {
+        match read_wav(&chordchart){
+            Some(wav) => {
+                let mut recognizer = ChordRecognizer::new();
+                recognizer.extractor.concert = concert;
+                for tc in recognizer.recognize_wav(&wav){
+                    println!("{}", tc);
+                }
+            },
+            None => println!("Could not read wav file: {}", chordchart),
+        }
+    }
+    if !lilypond.is_empty() {
+        let c = String::from("C").to_enharmonic_note().expect("C should be a note");
+        let ly = scale_catalogue_to_lilypond(c, &LilyOptions{ title: String::from("Scales"), ..LilyOptions::default() });
+        if let Err(e) = write_lilypond(&lilypond, &ly){
+            println!("Could not write LilyPond file {}: {}", lilypond, e);
+        }
+    }
+    if !abc.is_empty() {
+        match read_abc(&abc){
+            Some(tunes) => for tune in tunes{
+                for (header, content) in music_theory::tune_analysis(&tune){
+                    println!("\t{}", header);
+                    println!("{}", content);
+                }
+            },
+            None => println!("Could not read ABC file: {}", abc),
+        }
+    }
+    if !chordpro.is_empty() {
+        match read_chordpro(&chordpro){
+            Some(mut song) => {
+                if transpose != 0 { song = song.transposed(transpose); }
+                if let Some(key) = parse_key(&tokey) { song = song.transposed_to(&key); }
+                if let Some(capo) = capo { song = song.with_capo(capo); }
+                print!("{}", song);
+            },
+            None => println!("Could not read ChordPro file: {}", chordpro),
+        }
+    }
+    if !tab.is_empty() {
+        match read_tab(&tab, Instrument::parse(&fretboard).as_ref()){
+            Some(parsed) => {
+                println!("\tTab");
+                println!("{}, {} notes", parsed.instrument.name, parsed.notes.len());
+                for (_, chord) in parsed.chords(){
+                    let rchord = RootedChord::from_scale(chord.clone());
+                    print!("{} ", rchord.as_string(false, style));
+                }
+                println!("\n");
+                for (header, content) in music_theory::pcs_analysis(&parsed.pcs(), style){
+                    println!("\t{}", header);
+                    println!("{}", content);
+                }
+            },
+            None => println!("Could not read tab file: {}", tab),
+        }
+    }
+    if !musicxml.is_empty() {
+        match read_musicxml(&musicxml){
+            Some(score) => {
+                for (header, content) in music_theory::score_analysis(&score, style){
+                    println!("\t{}", header);
+                    println!("{}", content);
+                }
+            },
+            None => println!("Could not read MusicXML file: {}", musicxml),
+        }
+    }
+    if !wav.is_empty() {
+        let wav = match read_wav(&wav){
+            Some(wav) => wav,
+            None => { println!("Could not read wav file: {}", wav); return; },
+        };
+        let events = PitchTracker::new().wav_notes(&wav, concert);
+        for event in &events{
+            println!("{}", event);
+        }
+        let mut res = music_theory::pcs_analysis(&events_to_pcs(&events), style);
+        if keys {
+            let notes = events.iter().map(|e| (e.note, e.duration)).collect::<Vec<_>>();
+            res.insert(1.min(res.len()), ("Key".to_string(), keys_string(&pc_distribution(&notes), profile)));
+        }
+        for (header, content) in res{
+            println!("\t{}", header);
+            println!("{}", content);
+        }
+    }
+}
+
+// The most likely keys with their correlation.
+fn keys_string(distribution: &[f32; 12], profile: KeyProfile) -> String{
+    let namer = HeptatonicScaleNamer::new();
+    find_keys_in_library(distribution, profile).iter().take(8)
+        .map(|k| format!("{:<28} {:.3}", k.name(&namer), k.score))
+        .collect::<Vec<_>>().join("\n")
+}
+
+// The mode given with --mode on the tonic of the key, else its major or minor.
+fn key_steps(sig: &KeySignature, mode: &str) -> Steps{
+    match find_mode_by_name(mode){
+        Some(mo) if !mode.is_empty() => mo.steps,
+        _ if sig.mode == "minor" => ionian::steps().mode(5),
+        _ => ionian::steps(),
+    }
+}
+
+// The notes as a chord on the first of them.
+fn chord_of(ens: &[EnharmonicNote]) -> RootedChord{
+    RootedChord::from_scale(ens.iter().map(|en| en.to_pc()).collect::<Vec<_>>().to_scale(3))
+}
+
+// A range of frets like 5-9, the first twelve when there is none. None when it can't be read.
+fn fret_range(frets: &str) -> Option<(Note, Note)>{
+    if frets.trim().is_empty() { return Some((0, 12)); }
+    let (a, b) = frets.split_once('-')?;
+    let (first, last) = (a.trim().parse::<Note>().ok()?, b.trim().parse::<Note>().ok()?);
+    if first < 0 || last < first { None } else { Some((first, last)) }
 }
 
-fn dotest(){
+fn dotest(staff: Option<StaffOptions>){
     for named in vec![C,CS,E,F,G,GS,AS].to_scale(3).0 {
         print!("{}, ", named.to_pc());
     }
@@ -37,7 +417,8 @@ fn dotest(){
     for modeobj in find_steps_superseq(&vec![A,B,C,D].into_steps()){
         println!("{}", modeobj);
     }
-    print_scales(ChordStyling::Extended);
+    // Every mode drawn from middle C
+    print_scales_with(ChordStyling::Extended, |steps| staff.map(|options| scale_staff(&steps.to_scale(C.0 + 36), None, &options)).unwrap_or_default());
     let subset = vec![C,E,G,B];
     print!("Scales which are an superset to {{");
     for n in &subset{
@@ -0,0 +1,592 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::*;
+use crate::theory::symbol::*;
+use crate::libr::scales::{ get_all_scale_objs, ModeObj };
+use crate::query::find_scale_superset;
+use std::collections::HashMap;
+use std::fs;
+
+// ABC mode abbreviations, matched on their first three letters, and which mode of Ionian they are.
+const ABC_MODES: [(&str, usize); 9] = [
+    ("maj", 0), ("ion", 0), ("min", 5), ("aeo", 5), ("dor", 1), ("phr", 2), ("lyd", 3), ("mix", 4), ("loc", 6),
+];
+
+pub fn ionian_mode_obj(mode_nr: usize) -> Option<ModeObj>{
+    get_all_scale_objs().into_iter().find(|s| s.family_name() == "Ionian")?.get_modes().into_iter().nth(mode_nr)
+}
+
+/// The K: field: a tonic with the mode it is in and accidentals added to the signature.
+pub struct AbcKey{
+    pub tonic: EnharmonicNote,
+    // None for K:none and K:HP.
+    pub mode: Option<ModeObj>,
+    pub extra: Vec<EnharmonicNote>,
+    // With exp only the extra accidentals count.
+    pub explicit: bool,
+}
+
+fn abc_accidental(s: &str) -> Option<(i8, usize)>{
+    Some(match s{
+        _ if s.starts_with("^^") => (2, 2),
+        _ if s.starts_with("__") => (-2, 2),
+        _ if s.starts_with('^') => (1, 1),
+        _ if s.starts_with('_') => (-1, 1),
+        _ if s.starts_with('=') => (0, 1),
+        _ => return None,
+    })
+}
+
+impl AbcKey{
+    pub fn parse(field: &str) -> Option<Self>{
+        let mut tokens = field.split_whitespace().filter(|t| !t.contains('=') || t.starts_with('='));
+        let first = match tokens.next(){
+            Some(t) => t,
+            None => return Some(Self{ tonic: EnharmonicNote::default(), mode: None, extra: Vec::new(), explicit: false }),
+        };
+        let (tonic, mut mode_str) = match first{
+            "none" | "HP" => ("C".to_string(), String::from("none")),
+            "Hp" => ("A".to_string(), String::from("mix")),
+            _ => {
+                let len = first.char_indices().skip(1).find(|(_, c)| *c != '#' && *c != 'b').map(|(i, _)| i).unwrap_or(first.len());
+                (first[..len].to_string(), first[len..].to_string())
+            },
+        };
+        let tonic = tonic.to_enharmonic_note()?;
+        let mut extra = Vec::new();
+        let mut explicit = false;
+        for t in tokens{
+            if t.eq_ignore_ascii_case("exp") { explicit = true; }
+            else if let Some((acc, len)) = abc_accidental(t){
+                let en = t[len..].to_string().to_enharmonic_note()?;
+                extra.push(EnharmonicNote::new(en.letter(), acc));
+            }
+            else if mode_str.is_empty() { mode_str = t.to_string(); }
+        }
+        let mode = if mode_str == "none" { None } else {
+            let lower = mode_str.to_lowercase();
+            let nr = match lower.as_ref(){
+                "" => 0,
+                "m" => 5,
+                _ => ABC_MODES.iter().find(|(abbr, _)| lower.starts_with(abbr)).map(|(_, nr)| *nr)?,
+            };
+            Some(ionian_mode_obj(nr)?)
+        };
+        Some(Self{ tonic, mode, extra, explicit })
+    }
+
+    pub fn signature(&self) -> Option<KeySignature>{
+        self.mode.as_ref().map(|m| key_signature(self.tonic, &m.steps))
+    }
+
+    // The accidental of every letter A..G.
+    pub fn accidentals(&self) -> [i8; 7]{
+        let mut res = if self.explicit { [0; 7] } else { self.signature().map(|s| s.accidentals()).unwrap_or([0; 7]) };
+        for en in &self.extra{
+            res[en.letter() as usize] = en.accidental();
+        }
+        res
+    }
+
+    pub fn as_string(&self) -> String{
+        match &self.mode{
+            Some(m) => format!("{} {}", self.tonic.to_string_name(), m.mode_name),
+            None => String::from("none"),
+        }
+    }
+}
+
+/// A note of a tune, times in quarter notes from the start.
+#[derive(Clone,Copy,Debug)]
+pub struct AbcNote{
+    pub note: Note,
+    pub spelling: EnharmonicNote,
+    pub onset: f32,
+    pub duration: f32,
+}
+
+#[derive(Clone)]
+pub struct AbcChord{
+    pub symbol: ChordSymbol,
+    pub onset: f32,
+}
+
+pub struct AbcTune{
+    pub index: u32,
+    pub title: String,
+    pub meter: Option<(u32, u32)>,
+    // The default note length L: as a fraction of a whole note.
+    pub unit: (u32, u32),
+    pub key: Option<AbcKey>,
+    pub notes: Vec<AbcNote>,
+    pub chords: Vec<AbcChord>,
+}
+
+fn parse_meter(s: &str) -> Option<(u32, u32)>{
+    match s.trim(){
+        "C" => Some((4, 4)),
+        "C|" => Some((2, 2)),
+        s => {
+            let (n, d) = s.split_once('/')?;
+            let n = n.split('+').map(|x| x.trim().parse::<u32>().ok()).sum::<Option<u32>>()?;
+            Some((n, d.trim().parse().ok()?))
+        },
+    }
+}
+
+fn parse_fraction(s: &str) -> Option<(u32, u32)>{
+    let (n, d) = s.trim().split_once('/')?;
+    let (n, d) = (n.trim().parse().ok()?, d.trim().parse().ok()?);
+    if n == 0 || d == 0 { None } else { Some((n, d)) }
+}
+
+// Reads a length like 2, /2, 3/2 or //, as a multiple of the unit. None for a zero denominator.
+fn parse_length(chars: &[char], i: &mut usize) -> Option<f32>{
+    let digits = |i: &mut usize|{
+        let start = *i;
+        while *i < chars.len() && chars[*i].is_ascii_digit() { *i += 1; }
+        chars[start..*i].iter().collect::<String>().parse::<f32>().ok()
+    };
+    let mut len = digits(i).unwrap_or(1.0);
+    while *i < chars.len() && chars[*i] == '/'{
+        *i += 1;
+        let d = digits(i).unwrap_or(2.0);
+        if d == 0.0 { return None; }
+        len /= d;
+    }
+    Some(len)
+}
+
+struct TuneReader{
+    tune: AbcTune,
+    pos: f32,
+    key_acc: [i8; 7],
+    bar_acc: HashMap<(u8, Note), i8>,
+    // Duration factor and how many notes are left in the tuplet.
+    tuplet: Option<(f32, u32)>,
+    broken: f32,
+    // Indices of the notes last written and their duration.
+    last: (Vec<usize>, f32),
+}
+
+impl TuneReader{
+    fn new(index: u32) -> Self{
+        Self{
+            tune: AbcTune{ index, title: String::new(), meter: None, unit: (0, 0), key: None, notes: Vec::new(), chords: Vec::new() },
+            pos: 0.0,
+            key_acc: [0; 7],
+            bar_acc: HashMap::new(),
+            tuplet: None,
+            broken: 1.0,
+            last: (Vec::new(), 0.0),
+        }
+    }
+
+    fn unit_quarters(&self) -> f32{
+        let (n, d) = self.tune.unit;
+        if d == 0{
+            // Without L: short meters default to sixteenths, others to eighths.
+            match self.tune.meter{
+                Some((mn, md)) if (mn as f32 / md as f32) < 0.75 => 0.25,
+                _ => 0.5,
+            }
+        } else { 4.0 * n as f32 / d as f32 }
+    }
+
+    fn field(&mut self, name: char, value: &str) -> Option<()>{
+        match name{
+            'T' if self.tune.title.is_empty() => self.tune.title = value.trim().to_string(),
+            'M' => self.tune.meter = parse_meter(value),
+            'L' => self.tune.unit = parse_fraction(value)?,
+            'K' => {
+                let key = AbcKey::parse(value)?;
+                self.key_acc = key.accidentals();
+                self.tune.key = Some(key);
+            },
+            _ => {},
+        }
+        Some(())
+    }
+
+    fn duration(&mut self, len: f32) -> f32{
+        let mut dur = len * self.unit_quarters() * self.broken;
+        self.broken = 1.0;
+        if let Some((factor, left)) = self.tuplet{
+            dur *= factor;
+            self.tuplet = if left > 1 { Some((factor, left - 1)) } else { None };
+        }
+        dur
+    }
+
+    // A pitch with its accidental and octave marks, without the length.
+    fn pitch(&mut self, chars: &[char], i: &mut usize) -> Option<(Note, EnharmonicNote)>{
+        let rest = chars[*i..chars.len().min(*i + 2)].iter().collect::<String>();
+        let acc = abc_accidental(&rest).map(|(acc, len)| { *i += len; acc });
+        let c = *chars.get(*i)?;
+        let en = c.to_string().to_enharmonic_note()?;
+        let mut octave = if c.is_ascii_lowercase() { 5 } else { 4 };
+        *i += 1;
+        while *i < chars.len() && (chars[*i] == '\'' || chars[*i] == ','){
+            octave += if chars[*i] == '\'' { 1 } else { -1 };
+            *i += 1;
+        }
+        let letter = en.letter();
+        let accidental = match acc{
+            Some(acc) => { self.bar_acc.insert((letter, octave), acc); acc },
+            None => *self.bar_acc.get(&(letter, octave)).unwrap_or(&self.key_acc[letter as usize]),
+        };
+        let spelling = EnharmonicNote::new(letter, accidental);
+        Some((spelling.in_octave(octave), spelling))
+    }
+
+    fn push_notes(&mut self, pitches: Vec<(Note, EnharmonicNote)>, duration: f32){
+        let first = self.tune.notes.len();
+        for (note, spelling) in pitches{
+            self.tune.notes.push(AbcNote{ note, spelling, onset: self.pos, duration });
+        }
+        self.last = ((first..self.tune.notes.len()).collect(), duration);
+        self.pos += duration;
+    }
+
+    fn body_line(&mut self, line: &str) -> Option<()>{
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut i = 0;
+        let skip_to = |i: &mut usize, end: char|{
+            *i += 1;
+            while *i < chars.len() && chars[*i] != end { *i += 1; }
+            *i += 1;
+        };
+        while i < chars.len(){
+            let c = chars[i];
+            match c{
+                '"' => {
+                    let start = i + 1;
+                    skip_to(&mut i, '"');
+                    let text = chars[start..(i - 1).max(start)].iter().collect::<String>();
+                    // Annotations start with a placement, the rest are chord symbols.
+                    if !text.starts_with(['^', '_', '<', '>', '@']){
+                        if let Some(symbol) = ChordSymbol::parse(&text){
+                            self.tune.chords.push(AbcChord{ symbol, onset: self.pos });
+                        }
+                    }
+                },
+                '!' | '+' => skip_to(&mut i, c),
+                '{' => skip_to(&mut i, '}'),
+                '[' if i + 2 < chars.len() && chars[i + 1].is_ascii_alphabetic() && chars[i + 2] == ':' => {
+                    let (name, start) = (chars[i + 1], i + 3);
+                    skip_to(&mut i, ']');
+                    let value = chars[start..(i - 1).max(start)].iter().collect::<String>();
+                    self.field(name, &value)?;
+                },
+                '[' if i + 1 < chars.len() && chars[i + 1].is_ascii_digit() => i += 2,
+                '[' => {
+                    i += 1;
+                    let mut pitches = Vec::new();
+                    let mut inner = None;
+                    while i < chars.len() && chars[i] != ']'{
+                        if chars[i].is_whitespace() || chars[i] == '-' { i += 1; continue; }
+                        pitches.push(self.pitch(&chars, &mut i)?);
+                        let len = parse_length(&chars, &mut i)?;
+                        inner.get_or_insert(len);
+                    }
+                    i += 1;
+                    let len = inner.unwrap_or(1.0) * parse_length(&chars, &mut i)?;
+                    let dur = self.duration(len);
+                    self.push_notes(pitches, dur);
+                },
+                '|' | ':' | ']' => {
+                    self.bar_acc.clear();
+                    i += 1;
+                    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == ',') { i += 1; }
+                },
+                '(' if i + 1 < chars.len() && chars[i + 1].is_ascii_digit() => {
+                    let p = chars[i + 1].to_digit(10).unwrap_or(3);
+                    let q = match p{ 2 | 4 | 8 => 3, 3 | 6 => 2, _ => 2 };
+                    self.tuplet = Some((q as f32 / p as f32, p));
+                    i += 2;
+                },
+                '>' | '<' => {
+                    let (idx, dur) = self.last.clone();
+                    let factor = if c == '>' { 1.5 } else { 0.5 };
+                    for n in idx{
+                        self.tune.notes[n].duration = dur * factor;
+                    }
+                    self.pos += dur * (factor - 1.0);
+                    self.last.1 = dur * factor;
+                    self.broken = 2.0 - factor;
+                    i += 1;
+                },
+                'z' | 'x' => {
+                    i += 1;
+                    let len = parse_length(&chars, &mut i)?;
+                    let dur = self.duration(len);
+                    self.push_notes(Vec::new(), dur);
+                },
+                'Z' | 'X' => {
+                    i += 1;
+                    let bars = parse_length(&chars, &mut i)?;
+                    let (n, d) = self.tune.meter.unwrap_or((4, 4));
+                    self.pos += bars * 4.0 * n as f32 / d as f32;
+                },
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let pitch = self.pitch(&chars, &mut i)?;
+                    let len = parse_length(&chars, &mut i)?;
+                    let dur = self.duration(len);
+                    self.push_notes(vec![pitch], dur);
+                },
+                _ => i += 1,
+            }
+        }
+        Some(())
+    }
+}
+
+fn is_field(line: &str) -> Option<(char, &str)>{
+    let mut chars = line.chars();
+    let name = chars.next()?;
+    if name.is_ascii_alphabetic() && chars.next() == Some(':') { Some((name, &line[2..])) } else { None }
+}
+
+// All tunes of an ABC file. A tune that can't be read is left out, the others are still read.
+pub fn parse_abc(src: &str) -> Vec<AbcTune>{
+    let mut tunes = Vec::new();
+    let mut reader: Option<TuneReader> = None;
+    let mut in_body = false;
+    // Until the next X: after a tune failed.
+    let mut skipping = false;
+    for line in src.lines(){
+        let line = line.split('%').next().unwrap_or("").trim_end().trim_end_matches('\\');
+        if let Some((name, value)) = is_field(line){
+            if name == 'X'{
+                if let Some(r) = reader.take() { tunes.push(r.tune); }
+                reader = Some(TuneReader::new(value.trim().parse().unwrap_or(tunes.len() as u32 + 1)));
+                in_body = false;
+                skipping = false;
+                continue;
+            }
+            if skipping { continue; }
+            let r = reader.get_or_insert_with(|| TuneReader::new(1));
+            if r.field(name, value).is_none(){
+                reader = None;
+                skipping = true;
+            } else if name == 'K' { in_body = true; }
+        } else if in_body && !skipping && !line.trim().is_empty() && reader.as_mut().and_then(|r| r.body_line(line)).is_none(){
+            reader = None;
+            skipping = true;
+        }
+    }
+    if let Some(r) = reader { tunes.push(r.tune); }
+    tunes
+}
+
+// None when the file can't be read.
+pub fn read_abc(path: &str) -> Option<Vec<AbcTune>>{
+    Some(parse_abc(&fs::read_to_string(path).ok()?))
+}
+
+impl AbcTune{
+    // The pitchclasses, the ones sounding longest first.
+    pub fn pcs(&self) -> PCs{
+        let mut weights: Vec<(PC, f32)> = Vec::new();
+        for n in &self.notes{
+            match weights.iter_mut().find(|(pc, _)| *pc == n.note.to_pc()){
+                Some((_, w)) => *w += n.duration,
+                None => weights.push((n.note.to_pc(), n.duration)),
+            }
+        }
+        weights.sort_by(|a, b| b.1.total_cmp(&a.1));
+        weights.into_iter().map(|(pc, _)| pc).collect()
+    }
+
+    // The modes that hold every note of the tune, the one of the key first, then the smallest.
+    pub fn modes(&self) -> Vec<(PC, ModeObj)>{
+        let pcs = self.pcs();
+        if pcs.is_empty() { return Vec::new(); }
+        let key = self.key.as_ref().and_then(|k| k.mode.as_ref().map(|m| (k.tonic.to_pc(), m.steps.clone())));
+        let mut res = find_scale_superset(pcs, false);
+        res.sort_by_key(|(pc, mo)| (key.as_ref() != Some(&(*pc, mo.steps.clone())), mo.steps.len()));
+        res
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct AbcOptions{
+    pub index: u32,
+    pub title: String,
+    pub meter: (u32, u32),
+    pub unit: (u32, u32),
+}
+
+impl Default for AbcOptions{
+    fn default() -> Self{
+        Self{ index: 1, title: String::new(), meter: (4, 4), unit: (1, 4) }
+    }
+}
+
+// The K: value of a key, with a mode name for the modes of Ionian.
+pub fn abc_key(tonic: EnharmonicNote, steps: &Steps) -> String{
+    let sig = key_signature(tonic, steps);
+    let mode = match sig.mode{
+        "major" => "",
+        "minor" => "m",
+        "dorian" => "Dor",
+        "phrygian" => "Phr",
+        "lydian" => "Lyd",
+        "mixolydian" => "Mix",
+        "locrian" => "Loc",
+        _ => "",
+    };
+    format!("{}{}", ascii_name(sig.tonic()), mode)
+}
+
+struct AbcWriter{
+    key_acc: [i8; 7],
+    bar_acc: HashMap<(u8, Note), i8>,
+}
+
+impl AbcWriter{
+    fn pitch(&mut self, note: Note, en: EnharmonicNote) -> String{
+        let octave = en.octave_of(note);
+        let letter = en.letter();
+        let current = *self.bar_acc.get(&(letter, octave)).unwrap_or(&self.key_acc[letter as usize]);
+        let mut res = String::new();
+        if current != en.accidental(){
+            self.bar_acc.insert((letter, octave), en.accidental());
+            res.push_str(match en.accidental(){
+                2 => "^^",
+                1 => "^",
+                0 => "=",
+                -1 => "_",
+                _ => "__",
+            });
+        }
+        let name = ascii_name(EnharmonicNote::new(letter, 0));
+        if octave >= 5{
+            res.push_str(&name.to_lowercase());
+            for _ in 5..octave { res.push('\''); }
+        } else {
+            res.push_str(&name);
+            for _ in octave..4 { res.push(','); }
+        }
+        res
+    }
+}
+
+fn abc_length(units: u32) -> String{
+    if units == 1 { String::new() } else { units.to_string() }
+}
+
+fn abc_header(key: &str, options: &AbcOptions) -> String{
+    let mut res = format!("X:{}\n", options.index);
+    res.push_str(&format!("T:{}\n", if options.title.is_empty() { "Untitled" } else { &options.title }));
+    res.push_str(&format!("M:{}/{}\nL:{}/{}\nK:{}\n", options.meter.0, options.meter.1, options.unit.0, options.unit.1, key));
+    res
+}
+
+fn key_writer(key: &str) -> AbcWriter{
+    let key_acc = AbcKey::parse(key).map(|k| k.accidentals()).unwrap_or([0; 7]);
+    AbcWriter{ key_acc, bar_acc: HashMap::new() }
+}
+
+// Units of L: in a measure.
+fn measure_units(options: &AbcOptions) -> u32{
+    (options.meter.0 * options.unit.1) / (options.meter.1 * options.unit.0)
+}
+
+// The scale going up to the octave, a note per unit.
+pub fn scale_to_abc(scale: &Scale, start: Option<EnharmonicNote>, options: &AbcOptions) -> String{
+    if scale.is_empty() { return abc_header("none", options); }
+    let spelled = if scale.len() == 7 { scale.clone().into_enharmonic_notes_with_start(start) }
+        else { scale.0.iter().map(|n| n.to_enharmonic_note().expect("scale_to_abc: should not be None")).collect() };
+    let key = if scale.len() == 7 { abc_key(spelled[0], &scale.to_steps()) } else { String::from("none") };
+    let mut writer = key_writer(&key);
+    let per_bar = measure_units(options).max(1) as usize;
+    let mut notes = scale.0.iter().copied().zip(spelled.iter().copied()).collect::<Vec<_>>();
+    notes.push((scale.0[0] + OCTAVE, spelled[0]));
+    let bars = notes.chunks(per_bar).map(|bar|{
+        writer.bar_acc.clear();
+        bar.iter().map(|(note, en)| writer.pitch(*note, *en)).collect::<Vec<_>>().join(" ")
+    }).collect::<Vec<_>>();
+    format!("{}{} |]\n", abc_header(&key, options), bars.join(" | "))
+}
+
+// A measure per chord with its symbol. With a key (tonic and steps) the roots are spelled as in the key.
+pub fn chords_to_abc(chords: &[RootedChord], key: Option<(EnharmonicNote, &Steps)>, options: &AbcOptions) -> String{
+    let (key_str, key_notes, fifths) = match key{
+        Some((tonic, steps)) => {
+            let notes = steps.to_scale(tonic.to_note(0)).into_enharmonic_notes_with_start(Some(tonic));
+            (abc_key(tonic, steps), notes, key_signature(tonic, steps).fifths)
+        },
+        None => (String::from("C"), Vec::new(), 0),
+    };
+    let mut writer = key_writer(&key_str);
+    let len = abc_length(measure_units(options).max(1));
+    let mut bars = Vec::new();
+    for chord in chords{
+        writer.bar_acc.clear();
+        let root = spell_in_key(chord.root, &key_notes, fifths);
+        let symbol = ChordSymbol::from_rooted(chord, root);
+        let notes = chord.to_scale().0.into_iter().zip(chord.spelled(Some(root)))
+            .map(|(n, en)| writer.pitch(n, en)).collect::<String>();
+        bars.push(format!("\"{}\"[{}]{}", symbol.as_lead_sheet(), notes, len));
+    }
+    format!("{}{} |]\n", abc_header(&key_str, options), bars.join(" | "))
+}
+
+pub fn write_abc(path: &str, abc: &str) -> std::io::Result<()>{
+    fs::write(path, abc)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_parse_abc(){
+        let src = "X:1\nT:The Kesh\nM:6/8\nL:1/8\nK:Gmaj\n\"G\"GAG GAB|\"D\"ABA ABd|\"Em\"edd gdd|\"C\"edB dBA|\n\
+            X:2\nT:Modal\nM:4/4\nL:1/4\nK:D Dorian\n\"Dm\"D>E F_B | ^c2 [DFA]2 |]\n";
+        let tunes = parse_abc(src);
+        assert_eq!(AbcKey::parse("A mix").unwrap().accidentals(), [0, 0, 1, 0, 0, 1, 0]);
+        assert_eq!(AbcKey::parse("D exp ^f _b").unwrap().accidentals(), [0, -1, 0, 0, 0, 1, 0]);
+        assert_eq!(tunes.len(), 2);
+        let kesh = &tunes[0];
+        assert_eq!(kesh.title, "The Kesh");
+        assert_eq!(kesh.key.as_ref().unwrap().as_string(), "G Ionian");
+        assert_eq!(kesh.chords.iter().map(|c| c.symbol.as_lead_sheet()).collect::<Vec<_>>(), vec!["G", "D", "Em", "C"]);
+        assert_eq!(kesh.chords[1].onset, 3.0);
+        let (tonic, mode) = &kesh.modes()[0];
+        assert_eq!((*tonic, mode.mode_name.as_str()), (G, "Ionian"));
+        let modal = &tunes[1];
+        assert_eq!(modal.key.as_ref().unwrap().as_string(), "D Dorian");
+        let names = modal.notes.iter().map(|n| n.spelling.to_string_name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["D", "E", "F", "B♭", "C♯", "D", "F", "A"]);
+        assert_eq!(modal.notes[0].duration, 1.5);
+        assert_eq!(modal.notes[1].onset, 1.5);
+        assert_eq!(modal.notes[5].onset, modal.notes[7].onset);
+        assert_eq!(modal.notes[4].note, "C#".to_string().to_enharmonic_note().unwrap().in_octave(5));
+        assert!(AbcKey::parse("Hé").is_none());
+        // The tune with a key that isn't one is skipped.
+        let src = src.replace("K:Gmaj", "K:Ümaj");
+        let tunes = parse_abc(&src);
+        assert_eq!(tunes.len(), 1);
+        assert_eq!(tunes[0].title, "Modal");
+        // As is one with a zero length.
+        let src = src.replace("^c2", "^c/0");
+        assert!(parse_abc(&src).is_empty());
+    }
+    #[test]
+    fn test_write_abc(){
+        let d = "D".to_string().to_enharmonic_note().unwrap();
+        let dorian = ionian::steps().mode(1);
+        let abc = scale_to_abc(&dorian.to_scale(d.to_note(36)), Some(d), &AbcOptions::default());
+        assert!(abc.contains("K:DDor\nD E F G | A B c d |]"));
+        let chords = rooted_scale_chords(&ionian::steps(), "F".to_string().to_enharmonic_note().unwrap().to_note(36), 3);
+        let f = "F".to_string().to_enharmonic_note().unwrap();
+        let abc = chords_to_abc(&chords[..2], Some((f, &ionian::steps())), &AbcOptions::default());
+        assert!(abc.contains("K:F\n\"F\"[FAc]4 | \"Gm\"[GBd]4 |]"));
+        let tune = &parse_abc(&abc)[0];
+        assert_eq!(tune.notes[4].spelling.to_string_name(), "B♭");
+    }
+}
@@ -0,0 +1,338 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::*;
+use crate::theory::symbol::*;
+use std::fs;
+
+/// A chord marker as written: the suffix is kept as it is, only root and bass move when transposing.
+#[derive(Clone,Debug)]
+pub struct ChordProChord{
+    pub root: EnharmonicNote,
+    pub suffix: String,
+    pub bass: Option<EnharmonicNote>,
+}
+
+impl ChordProChord{
+    // Only when all of it is a chord, so [Bridge] is not a B.
+    pub fn parse(s: &str) -> Option<Self>{
+        let (root, rest) = parse_root(s.trim())?;
+        let res = match rest.rfind('/'){
+            Some(i) if rest[i + 1..].starts_with(|c: char| ('A'..='G').contains(&c)) => {
+                let bass = rest[i + 1..].to_string().to_enharmonic_note()?;
+                Self{ root, suffix: rest[..i].to_string(), bass: Some(bass) }
+            },
+            _ => Self{ root, suffix: rest.to_string(), bass: None },
+        };
+        parse_chord_suffix(&res.suffix)?;
+        Some(res)
+    }
+
+    // None when the suffix is not a chord we know.
+    pub fn symbol(&self) -> Option<ChordSymbol>{
+        Some(ChordSymbol{ root: self.root, chord: parse_chord_suffix(&self.suffix)?, bass: self.bass })
+    }
+
+    pub fn to_rooted_chord(&self, rank: Rank) -> Option<RootedChord>{
+        self.symbol().map(|s| s.to_rooted_chord(rank))
+    }
+
+    pub fn transposed(&self, from: &KeySignature, to: &KeySignature) -> Self{
+        Self{
+            root: transpose_spelled(self.root, from, to),
+            suffix: self.suffix.clone(),
+            bass: self.bass.map(|b| transpose_spelled(b, from, to)),
+        }
+    }
+}
+
+impl std::fmt::Display for ChordProChord{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{}{}", ascii_name(self.root), self.suffix)?;
+        if let Some(bass) = self.bass{
+            write!(f, "/{}", ascii_name(bass))?;
+        }
+        Ok(())
+    }
+}
+
+/// What is between the brackets: a chord or something else like N.C. or *Riff.
+#[derive(Clone,Debug)]
+pub enum ChordProMarker{
+    Chord(ChordProChord),
+    Other(String),
+}
+
+impl std::fmt::Display for ChordProMarker{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            ChordProMarker::Chord(c) => write!(f, "[{}]", c),
+            ChordProMarker::Other(s) => write!(f, "[{}]", s),
+        }
+    }
+}
+
+/// Lyrics with the chord that starts on them.
+#[derive(Clone,Debug)]
+pub struct ChordProSegment{
+    pub chord: Option<ChordProMarker>,
+    pub lyrics: String,
+}
+
+#[derive(Clone,Debug)]
+pub enum ChordProLine{
+    Directive{ name: String, value: Option<String> },
+    Lyrics(Vec<ChordProSegment>),
+    // Comments and the insides of tab and grid sections, kept as they are.
+    Text(String),
+}
+
+impl std::fmt::Display for ChordProLine{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            ChordProLine::Directive{ name, value: Some(v) } => write!(f, "{{{}: {}}}", name, v),
+            ChordProLine::Directive{ name, value: None } => write!(f, "{{{}}}", name),
+            ChordProLine::Lyrics(segs) => {
+                for seg in segs{
+                    if let Some(c) = &seg.chord { write!(f, "{}", c)?; }
+                    write!(f, "{}", seg.lyrics)?;
+                }
+                Ok(())
+            },
+            ChordProLine::Text(t) => write!(f, "{}", t),
+        }
+    }
+}
+
+#[derive(Clone,Debug,Default)]
+pub struct ChordProSong{
+    pub lines: Vec<ChordProLine>,
+}
+
+// A key as ChordPro writes it: C, Bb, F#m.
+pub fn parse_key(s: &str) -> Option<KeySignature>{
+    let (tonic, rest) = parse_root(s.trim())?;
+    match rest.trim().to_lowercase().as_str(){
+        "" | "maj" | "major" => Some(KeySignature{ fifths: tonic.fifths(), mode: "major" }),
+        "m" | "min" | "minor" => Some(KeySignature{ fifths: tonic.fifths() - 3, mode: "minor" }),
+        _ => None,
+    }
+}
+
+pub fn key_name(key: &KeySignature) -> String{
+    let mut res = ascii_name(key.tonic());
+    if key.mode == "minor" { res.push('m'); }
+    res
+}
+
+fn is_key(name: &str) -> bool{
+    name.eq_ignore_ascii_case("key")
+}
+
+fn is_capo(name: &str) -> bool{
+    name.eq_ignore_ascii_case("capo")
+}
+
+fn parse_lyrics(line: &str) -> Option<Vec<ChordProSegment>>{
+    let mut segs = Vec::new();
+    let mut rest = line;
+    let first = rest.find('[').unwrap_or(rest.len());
+    if first > 0 { segs.push(ChordProSegment{ chord: None, lyrics: rest[..first].to_string() }); }
+    rest = &rest[first..];
+    while !rest.is_empty(){
+        let close = rest.find(']')?;
+        let inner = &rest[1..close];
+        let chord = match ChordProChord::parse(inner){
+            Some(c) => ChordProMarker::Chord(c),
+            None => ChordProMarker::Other(inner.to_string()),
+        };
+        rest = &rest[close + 1..];
+        let len = rest.find('[').unwrap_or(rest.len());
+        segs.push(ChordProSegment{ chord: Some(chord), lyrics: rest[..len].to_string() });
+        rest = &rest[len..];
+    }
+    Some(segs)
+}
+
+// None when a chord or directive is not closed.
+pub fn parse_chordpro(src: &str) -> Option<ChordProSong>{
+    let mut lines = Vec::new();
+    let mut verbatim = false;
+    for line in src.lines(){
+        let trimmed = line.trim();
+        if trimmed.starts_with('{'){
+            let inner = trimmed.strip_prefix('{')?.strip_suffix('}')?;
+            let (name, value) = match inner.find(':'){
+                Some(i) => (inner[..i].trim().to_string(), Some(inner[i + 1..].trim().to_string())),
+                None => (inner.trim().to_string(), None),
+            };
+            match name.to_lowercase().as_str(){
+                "start_of_tab" | "sot" | "start_of_grid" | "sog" => verbatim = true,
+                "end_of_tab" | "eot" | "end_of_grid" | "eog" => verbatim = false,
+                _ => {},
+            }
+            lines.push(ChordProLine::Directive{ name, value });
+        } else if verbatim || trimmed.starts_with('#'){
+            lines.push(ChordProLine::Text(line.to_string()));
+        } else {
+            lines.push(ChordProLine::Lyrics(parse_lyrics(line)?));
+        }
+    }
+    Some(ChordProSong{ lines })
+}
+
+pub fn read_chordpro(path: &str) -> Option<ChordProSong>{
+    parse_chordpro(&fs::read_to_string(path).ok()?)
+}
+
+pub fn write_chordpro(path: &str, song: &ChordProSong) -> std::io::Result<()>{
+    fs::write(path, song.to_string())
+}
+
+impl std::fmt::Display for ChordProSong{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        for line in &self.lines{
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChordProSong{
+    pub fn directive(&self, names: &[&str]) -> Option<&str>{
+        self.lines.iter().find_map(|l| match l{
+            ChordProLine::Directive{ name, value: Some(v) } if names.iter().any(|n| n.eq_ignore_ascii_case(name)) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn title(&self) -> Option<&str>{
+        self.directive(&["title", "t"])
+    }
+
+    // The key the chords are written in.
+    pub fn key(&self) -> Option<KeySignature>{
+        self.directive(&["key"]).and_then(parse_key)
+    }
+
+    pub fn capo(&self) -> Note{
+        self.directive(&["capo"]).and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    // The key that sounds when played with the capo on.
+    pub fn sounding_key(&self) -> Option<KeySignature>{
+        self.home_key().map(|k| k.transposed(self.capo()))
+    }
+
+    pub fn chords(&self) -> Vec<&ChordProChord>{
+        self.lines.iter().flat_map(|l| match l{
+            ChordProLine::Lyrics(segs) => segs.iter().filter_map(|s| match &s.chord{
+                Some(ChordProMarker::Chord(c)) => Some(c),
+                _ => None,
+            }).collect(),
+            _ => Vec::new(),
+        }).collect()
+    }
+
+    // The key directive, or else the key of the first chord.
+    pub fn home_key(&self) -> Option<KeySignature>{
+        if let Some(key) = self.key() { return Some(key); }
+        let first = self.chords().into_iter().next()?;
+        let minor = first.symbol().map(|s| s.chord.0.contains(&MINOR_THIRD) && !s.chord.0.contains(&MAJOR_THIRD)).unwrap_or(false);
+        parse_key(&format!("{}{}", ascii_name(first.root), if minor { "m" } else { "" }))
+    }
+
+    // Every key is moved by the same interval, the first one to the given spelling if any.
+    fn transpose_with(&self, semis: Note, target: Option<KeySignature>) -> Self{
+        let mut from = match self.home_key(){
+            Some(k) => k,
+            None => return self.clone(),
+        };
+        let semis = semis.rem_euclid(OCTAVE);
+        let mut to = target.unwrap_or_else(|| from.transposed(semis));
+        let mut lines = Vec::new();
+        for line in &self.lines{
+            lines.push(match line{
+                ChordProLine::Directive{ name, value: Some(v) } if is_key(name) => {
+                    if let Some(key) = parse_key(v){
+                        if key != from{
+                            from = key;
+                            to = key.transposed(semis);
+                        }
+                    }
+                    ChordProLine::Directive{ name: name.clone(), value: Some(key_name(&to)) }
+                },
+                ChordProLine::Lyrics(segs) => ChordProLine::Lyrics(segs.iter().map(|s| ChordProSegment{
+                    chord: s.chord.as_ref().map(|c| match c{
+                        ChordProMarker::Chord(c) => ChordProMarker::Chord(c.transposed(&from, &to)),
+                        other => other.clone(),
+                    }),
+                    lyrics: s.lyrics.clone(),
+                }).collect()),
+                other => other.clone(),
+            });
+        }
+        Self{ lines }
+    }
+
+    pub fn transposed(&self, semis: Note) -> Self{
+        self.transpose_with(semis, None)
+    }
+
+    pub fn transposed_to(&self, key: &KeySignature) -> Self{
+        match self.home_key(){
+            Some(home) => self.transpose_with(key.tonic().to_note(0) - home.tonic().to_note(0), Some(*key)),
+            None => self.clone(),
+        }
+    }
+
+    // The chord shapes for another capo position, sounding the same.
+    pub fn with_capo(&self, capo: Note) -> Self{
+        let mut res = self.transposed(self.capo() - capo);
+        let pos = res.lines.iter().position(|l| matches!(l, ChordProLine::Directive{ name, .. } if is_capo(name)));
+        match pos{
+            Some(i) if capo == 0 => { res.lines.remove(i); },
+            Some(i) => res.lines[i] = ChordProLine::Directive{ name: String::from("capo"), value: Some(capo.to_string()) },
+            None if capo == 0 => {},
+            None => {
+                // Next to the key or the title, at the top otherwise.
+                let at = res.lines.iter().rposition(|l| matches!(l, ChordProLine::Directive{ name, .. }
+                    if is_key(name) || ["title", "t", "subtitle", "st", "artist"].iter().any(|n| n.eq_ignore_ascii_case(name))))
+                    .map(|i| i + 1).unwrap_or(0);
+                res.lines.insert(at, ChordProLine::Directive{ name: String::from("capo"), value: Some(capo.to_string()) });
+            },
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_chordpro(){
+        let src = "{title: Test}\n{key: F}\n[F]Hello [Bb]there [C7]my [Dm/A]friend [N.C.]\n{sot}\ne|--[0]--|\n{eot}\n";
+        let song = parse_chordpro(src).unwrap();
+        assert_eq!(song.to_string(), src);
+        assert_eq!(song.title(), Some("Test"));
+        assert_eq!(song.chords().len(), 4);
+        assert_eq!(song.chords()[3].to_rooted_chord(3).unwrap().to_scale().0, vec![41, 44, 48]);
+        let up = song.transposed(5);
+        assert_eq!(up.key().map(|k| key_name(&k)), Some(String::from("Bb")));
+        assert_eq!(up.lines[2].to_string(), "[Bb]Hello [Eb]there [F7]my [Gm/D]friend [N.C.]");
+        let a = song.transposed_to(&parse_key("A").unwrap());
+        assert_eq!(a.lines[2].to_string(), "[A]Hello [D]there [E7]my [F#m/C#]friend [N.C.]");
+        let capo = song.with_capo(3);
+        assert_eq!(capo.key().map(|k| key_name(&k)), Some(String::from("D")));
+        assert_eq!(capo.capo(), 3);
+        assert_eq!(capo.lines[2].to_string(), "{capo: 3}");
+        assert_eq!(capo.sounding_key(), song.key());
+        assert_eq!(capo.with_capo(0).to_string(), src);
+        let c = parse_chordpro("[C]a [Bb]b [Gm]c").unwrap().transposed(2);
+        assert_eq!(c.to_string(), "[D]a [C]b [Am]c\n");
+        assert!(parse_chordpro("[C a").is_none());
+        let c = parse_chordpro("[Bridge]a [C]b [Chorus]c [Dm/Fx]d").unwrap().transposed(2);
+        assert_eq!(c.to_string(), "[Bridge]a [D]b [Chorus]c [Dm/Fx]d\n");
+        assert_eq!(c.chords().len(), 1);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::*;
+use crate::libr::scales::{ get_all_scale_objs, HeptatonicScaleNamer };
+use std::fs;
+
+const LILY_VERSION: &str = "2.24.0";
+const LETTERS: [&str; 7] = ["a", "b", "c", "d", "e", "f", "g"];
+// Semitones above the root of the unaltered chord steps 1 to 7, 7 being the minor seventh as in LilyPond.
+const LILY_STEPS: [Note; 7] = [0, 2, 4, 5, 7, 9, 10];
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Clef{ Treble, Bass, Alto, Tenor }
+
+impl Clef{
+    pub fn as_str(&self) -> &'static str{
+        match self{
+            Clef::Treble => "treble",
+            Clef::Bass => "bass",
+            Clef::Alto => "alto",
+            Clef::Tenor => "tenor",
+        }
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct LilyOptions{
+    pub title: String,
+    // None picks treble or bass by the range of the notes.
+    pub clef: Option<Clef>,
+    // None takes the key of the scale when there is one.
+    pub key: Option<KeySignature>,
+    pub time: (u32, u32),
+}
+
+impl Default for LilyOptions{
+    fn default() -> Self{
+        Self{ title: String::new(), clef: None, key: None, time: (4, 4) }
+    }
+}
+
+// LilyPond's dutch names: ees, fis, beses.
+pub fn lily_pitch_name(en: EnharmonicNote) -> String{
+    let mut res = LETTERS[en.letter() as usize].to_string();
+    let acc = if en.accidental() > 0 { "is" } else { "es" };
+    for _ in 0..en.accidental().abs(){
+        res.push_str(acc);
+    }
+    res
+}
+
+// Absolute octave marks: c is C3, c' is C4 and c, is C2.
+pub fn lily_pitch(note: Note, en: EnharmonicNote) -> String{
+    let mut res = lily_pitch_name(en);
+    let octave = en.octave_of(note) - 3;
+    let mark = if octave > 0 { "'" } else { "," };
+    for _ in 0..octave.abs(){
+        res.push_str(mark);
+    }
+    res
+}
+
+// The modifiers after the colon in chordmode, like m7 or 7.9-.
+pub fn lily_chord_modifier(chord: &Chord) -> String{
+    const NAMED: &[(&[Note], &str)] = &[
+        (MAJOR, ""), (MINOR, "m"), (MAJOR_AUGMENTED, "aug"), (MINOR_DIMINISHED, "dim"),
+        (SUS2, "sus2"), (SUS4, "sus4"), (MAJOR_SIXTH_CHORD, "6"), (MINOR_SIXTH_CHORD, "m6"),
+        (MAJOR_SEVENTH_CHORD, "maj7"), (MINOR_SEVENTH_CHORD, "m7"), (DOMINANT_SEVENTH, "7"),
+        (MINOR_MAJOR_SEVENTH, "m7+"), (HALF_DIMINISHED_SEVENTH, "m7.5-"), (DIMINISHED_SEVENTH_CHORD, "dim7"),
+        (AUGMENTED_SEVENTH_CHORD, "aug7"), (&[PERFECT_FIFTH], "5"),
+    ];
+    if let Some((_, name)) = NAMED.iter().find(|(pattern, _)| chord.same_intervals(pattern)){
+        return name.to_string();
+    }
+    if chord.0.is_empty() { return "1".to_string(); }
+    // Spell every step out, removing the third or fifth of the default triad when missing.
+    let mut steps = Vec::new();
+    let (mut third, mut fifth) = (false, false);
+    for int in &chord.0{
+        let letters = interval_letters(*int) as Note + 7 * int.div_euclid(OCTAVE);
+        let step = letters + 1;
+        let natural = LILY_STEPS[(letters % 7) as usize] + OCTAVE * (letters / 7);
+        third |= step == 3;
+        fifth |= step == 5;
+        let alter = int - natural;
+        let mut s = step.to_string();
+        for _ in 0..alter.abs(){
+            s.push(if alter > 0 { '+' } else { '-' });
+        }
+        steps.push(s);
+    }
+    let mut res = steps.join(".");
+    let removed = [(third, "3"), (fifth, "5")].iter().filter(|(has, _)| !has).map(|(_, s)| *s).collect::<Vec<_>>();
+    if !removed.is_empty(){
+        res.push('^');
+        res.push_str(&removed.join("."));
+    }
+    res
+}
+
+// Tied durations filling a measure of the time signature, longest first. Measures that no
+// 64th notes add up to are scaled whole notes.
+fn lily_durations(time: (u32, u32)) -> Vec<String>{
+    const VALUES: [(u32, &str); 15] = [(192, "\\breve."), (128, "\\breve"), (96, "1."), (64, "1"), (48, "2."), (32, "2"), (24, "4."),
+        (16, "4"), (12, "8."), (8, "8"), (6, "16."), (4, "16"), (3, "32."), (2, "32"), (1, "64")];
+    let (beats, beat_type) = time;
+    if beats == 0 || beat_type == 0 || 64 % beat_type != 0 { return vec![format!("1*{}/{}", beats, beat_type)]; }
+    let mut left = beats * 64 / beat_type;
+    let mut res = Vec::new();
+    while let Some((value, dur)) = VALUES.iter().find(|(v, _)| *v <= left){
+        res.push(dur.to_string());
+        left -= value;
+    }
+    res
+}
+
+fn auto_clef(notes: &[Note], options: &LilyOptions) -> Clef{
+    if let Some(clef) = options.clef { return clef; }
+    let average = notes.iter().sum::<Note>() / notes.len().max(1) as Note;
+    if !notes.is_empty() && average < 39 { Clef::Bass } else { Clef::Treble }
+}
+
+fn staff_header(clef: Clef, key: Option<KeySignature>, options: &LilyOptions) -> String{
+    let mut res = format!("\\clef {} ", clef.as_str());
+    if let Some(key) = key{
+        res.push_str(&format!("\\key {} \\{} ", lily_pitch_name(key.tonic()), key.mode));
+    }
+    res.push_str(&format!("\\time {}/{}", options.time.0, options.time.1));
+    res
+}
+
+fn score_block(chordnames: Option<String>, staff: String, title: &str) -> String{
+    let mut res = String::from("\\score {\n  <<\n");
+    if let Some(names) = chordnames{
+        res.push_str(&format!("    \\new ChordNames \\chordmode {{ {} }}\n", names));
+    }
+    res.push_str(&format!("    \\new Staff {{ {} \\bar \"|.\" }}\n  >>\n", staff));
+    if !title.is_empty(){
+        res.push_str(&format!("  \\header {{ piece = \"{}\" }}\n", title.replace('"', "\\\"")));
+    }
+    res.push_str("  \\layout { }\n}\n");
+    res
+}
+
+fn document(title: &str, scores: &[String]) -> String{
+    let mut res = format!("\\version \"{}\"\n\\header {{\n", LILY_VERSION);
+    if !title.is_empty(){
+        res.push_str(&format!("  title = \"{}\"\n", title.replace('"', "\\\"")));
+    }
+    res.push_str("  tagline = ##f\n}\n\n");
+    res.push_str(&scores.join("\n"));
+    res
+}
+
+fn scale_spelling(scale: &Scale, start: Option<EnharmonicNote>) -> Vec<EnharmonicNote>{
+    if scale.len() == 7{
+        scale.clone().into_enharmonic_notes_with_start(start)
+    } else {
+        scale.0.iter().map(|n| n.to_enharmonic_note().expect("scale_spelling: should not be None")).collect::<Vec<_>>()
+    }
+}
+
+fn scale_score(scale: &Scale, start: Option<EnharmonicNote>, chord_names: Option<Vec<String>>, title: &str, options: &LilyOptions) -> String{
+    if scale.is_empty() { return score_block(None, String::new(), title); }
+    let spelled = scale_spelling(scale, start);
+    let key = options.key.or_else(|| if scale.len() == 7 { Some(key_signature(spelled[0], &scale.to_steps())) } else { None });
+    let mut notes = scale.0.iter().zip(spelled.iter()).map(|(n, en)| format!("{}4", lily_pitch(*n, *en))).collect::<Vec<_>>();
+    notes.push(format!("{}4", lily_pitch(scale.0[0] + OCTAVE, spelled[0])));
+    let staff = format!("{} {}", staff_header(auto_clef(&scale.0, options), key, options), notes.join(" "));
+    score_block(chord_names.map(|names| names.join(" ")), staff, title)
+}
+
+// The scale going up to the octave in quarter notes.
+pub fn scale_to_lilypond(scale: &Scale, start: Option<EnharmonicNote>, options: &LilyOptions) -> String{
+    document(&options.title, &[scale_score(scale, start, None, "", options)])
+}
+
+// A measure per chord, with the names above the staff. With a key (tonic and steps) the roots are spelled as in the key.
+pub fn chords_to_lilypond(chords: &[RootedChord], key: Option<(EnharmonicNote, &Steps)>, options: &LilyOptions) -> String{
+    let (signature, key_notes) = match key{
+        Some((tonic, steps)) => (Some(key_signature(tonic, steps)), scale_spelling(&steps.to_scale(tonic.to_note(0)), Some(tonic))),
+        None => (None, Vec::new()),
+    };
+    let signature = options.key.or(signature);
+    let fifths = signature.map(|s| s.fifths).unwrap_or(0);
+    let durs = lily_durations(options.time);
+    // Names aren't tied, they last the whole measure.
+    let name_dur = if durs.len() == 1 { durs[0].clone() } else { format!("1*{}/{}", options.time.0, options.time.1) };
+    let mut names = Vec::new();
+    let mut staff = Vec::new();
+    let mut all_notes = Vec::new();
+    for chord in chords{
+        let root = spell_in_key(chord.root, &key_notes, fifths);
+        let modifier = lily_chord_modifier(&chord.chord);
+        names.push(format!("{}{}{}{}", lily_pitch_name(root), name_dur, if modifier.is_empty() { "" } else { ":" }, modifier));
+        let notes = chord.to_scale().0;
+        let pitches = notes.iter().zip(chord.spelled(Some(root))).map(|(n, en)| lily_pitch(*n, en)).collect::<Vec<_>>();
+        staff.push(durs.iter().map(|d| format!("<{}>{}", pitches.join(" "), d)).collect::<Vec<_>>().join(" ~ "));
+        all_notes.extend(notes);
+    }
+    let staff = format!("{} {}", staff_header(auto_clef(&all_notes, options), signature, options), staff.join(" "));
+    document(&options.title, &[score_block(Some(names.join(" ")), staff, "")])
+}
+
+// The diatonic chords of a scale, like strs_scale_chords but engraved.
+pub fn scale_chords_to_lilypond(steps: &Steps, tonic: EnharmonicNote, rank: Rank, size: usize, options: &LilyOptions) -> String{
+    let chords = rooted_scale_chords(steps, tonic.to_note(0) + rank as Note * OCTAVE, size);
+    chords_to_lilypond(&chords, Some((tonic, steps)), options)
+}
+
+// Every mode of every scale on one tonic with the triad of each degree named above it, like print_scales.
+pub fn scale_catalogue_to_lilypond(tonic: EnharmonicNote, options: &LilyOptions) -> String{
+    let namer = HeptatonicScaleNamer::new();
+    let tonic_note = tonic.to_note(36);
+    let mut scores = Vec::new();
+    for sobj in get_all_scale_objs(){
+        for mode in sobj.get_modes(){
+            let mode_name = if mode.mode_name.is_empty() { namer.name(&mode.steps) } else { mode.mode_name.clone() };
+            let title = format!("{}, {}ᵉ mode of {}", mode_name, mode.mode_nr + 1, mode.fam_name);
+            let scale = mode.steps.to_scale(tonic_note);
+            let spelled = scale_spelling(&scale, Some(tonic));
+            let mut names = rooted_scale_chords(&mode.steps, tonic_note, 3).iter().zip(spelled.iter()).map(|(c, en)|{
+                let modifier = lily_chord_modifier(&c.chord);
+                format!("{}4{}{}", lily_pitch_name(*en), if modifier.is_empty() { "" } else { ":" }, modifier)
+            }).collect::<Vec<_>>();
+            names.push("r4".to_string());
+            scores.push(scale_score(&scale, Some(tonic), Some(names), &title, options));
+        }
+    }
+    document(&options.title, &scores)
+}
+
+pub fn write_lilypond(path: &str, ly: &str) -> std::io::Result<()>{
+    fs::write(path, ly)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_lilypond(){
+        let bb = "Bb".to_string().to_enharmonic_note().unwrap();
+        assert_eq!(lily_pitch(bb.to_note(48), bb), "bes'");
+        assert_eq!(lily_pitch_name("F##".to_string().to_enharmonic_note().unwrap()), "fisis");
+        assert_eq!(lily_chord_modifier(&Chord::new(&[MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, FLAT_NINETH])), "3.5.7.9-");
+        assert_eq!(lily_chord_modifier(&Chord::new(&[MAJOR_THIRD, MINOR_SEVENTH])), "3.7^5");
+        let ly = scale_chords_to_lilypond(&ionian::steps(), bb, 3, 4, &LilyOptions::default());
+        assert!(ly.contains("\\key bes \\major \\time 4/4"));
+        assert!(ly.contains("\\chordmode { bes1:maj7 c1:m7 d1:m7 ees1:maj7 f1:7 g1:m7 a1:m7.5- }"));
+        assert!(ly.contains("<ees' g' bes' d''>1"));
+        assert_eq!(lily_durations((3, 8)), ["4."]);
+        assert_eq!(lily_durations((6, 8)), ["2."]);
+        assert_eq!(lily_durations((5, 4)), ["1", "4"]);
+        let ly = scale_chords_to_lilypond(&ionian::steps(), bb, 3, 3, &LilyOptions{ time: (7, 8), ..Default::default() });
+        assert!(ly.contains("\\time 7/8"));
+        assert!(ly.contains("\\chordmode { bes1*7/8 c1*7/8:m"));
+        assert!(ly.contains("<bes d' f'>2. ~ <bes d' f'>8 "));
+        let ly = scale_to_lilypond(&ionian::steps().mode(5).to_scale(A4 - OCTAVE * 2), None, &LilyOptions::default());
+        assert!(ly.contains("\\clef bass \\key a \\minor"));
+    }
+}
@@ -0,0 +1,15 @@
+pub mod scala;
+pub mod xml;
+pub mod musicxml;
+pub mod lilypond;
+pub mod abc;
+pub mod chordpro;
+pub mod tab;
+
+pub use scala::*;
+pub use xml::*;
+pub use musicxml::*;
+pub use lilypond::*;
+pub use abc::*;
+pub use chordpro::*;
+pub use tab::*;
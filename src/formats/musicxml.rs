@@ -0,0 +1,663 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::*;
+use crate::libr::scales::ModeObj;
+use crate::query::find_scale_superset;
+use super::xml::*;
+use std::fs;
+
+// Divisions of a quarter note, so sixteenths are the shortest notes.
+const DIVISIONS: u32 = 4;
+const STEPS: [&str; 7] = ["A", "B", "C", "D", "E", "F", "G"];
+
+#[derive(Clone,PartialEq,Eq)]
+pub struct MusicXmlOptions{
+    pub title: String,
+    pub beats: u32,
+    pub beat_type: u32,
+    pub styling: ChordStyling,
+}
+
+impl Default for MusicXmlOptions{
+    fn default() -> Self{
+        Self{ title: String::new(), beats: 4, beat_type: 4, styling: ChordStyling::Std }
+    }
+}
+
+impl MusicXmlOptions{
+    // A time signature with a zero in it falls back to 4/4.
+    pub fn time(&self) -> (u32, u32){
+        if self.beats == 0 || self.beat_type == 0 { (4, 4) } else { (self.beats, self.beat_type) }
+    }
+
+    fn measure_len(&self) -> u32{
+        let (beats, beat_type) = self.time();
+        beats * DIVISIONS * 4 / beat_type
+    }
+}
+
+// Notes sounding together (none for a rest), optionally with a chord symbol.
+struct XmlEvent{
+    notes: Vec<(Note, EnharmonicNote)>,
+    duration: u32,
+    harmony: Option<(EnharmonicNote, Chord)>,
+}
+
+pub fn escape_xml(text: &str) -> String{
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const HARMONY_KINDS: &[(&[Note], &str)] = &[
+    (MAJOR, "major"), (MINOR, "minor"), (MAJOR_AUGMENTED, "augmented"), (MINOR_DIMINISHED, "diminished"),
+    (SUS2, "suspended-second"), (SUS4, "suspended-fourth"),
+    (MAJOR_SIXTH_CHORD, "major-sixth"), (MINOR_SIXTH_CHORD, "minor-sixth"),
+    (MAJOR_SEVENTH_CHORD, "major-seventh"), (MINOR_SEVENTH_CHORD, "minor-seventh"),
+    (DOMINANT_SEVENTH, "dominant"), (MINOR_MAJOR_SEVENTH, "major-minor"),
+    (HALF_DIMINISHED_SEVENTH, "half-diminished"), (DIMINISHED_SEVENTH_CHORD, "diminished-seventh"),
+    (AUGMENTED_SEVENTH_CHORD, "augmented-seventh"),
+    (&[MAJOR_THIRD, PERFECT_FIFTH, MAJOR_SEVENTH, NINETH], "major-ninth"),
+    (&[MINOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, NINETH], "minor-ninth"),
+    (&[MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, NINETH], "dominant-ninth"),
+    (&[PERFECT_FIFTH], "power"),
+];
+
+// The MusicXML kind of a chord, "other" with the symbol as text when there is none.
+pub fn harmony_kind(chord: &Chord) -> &'static str{
+    HARMONY_KINDS.iter().find(|(pattern, _)| chord.same_intervals(pattern)).map(|(_, kind)| *kind).unwrap_or("other")
+}
+
+// The chord of a MusicXML kind, None for kinds like "other" and "none".
+pub fn harmony_kind_chord(kind: &str) -> Option<Chord>{
+    HARMONY_KINDS.iter().find(|(_, k)| *k == kind).map(|(pattern, _)| Chord::new(pattern))
+}
+
+fn type_name(duration: u32) -> Option<(&'static str, bool)>{
+    Some(match duration{
+        1 => ("16th", false),
+        2 => ("eighth", false),
+        3 => ("eighth", true),
+        4 => ("quarter", false),
+        6 => ("quarter", true),
+        8 => ("half", false),
+        12 => ("half", true),
+        16 => ("whole", false),
+        24 => ("whole", true),
+        _ => return None,
+    })
+}
+
+fn pitch_xml(note: Note, en: EnharmonicNote) -> String{
+    let mut res = format!("<pitch><step>{}</step>", STEPS[en.letter() as usize]);
+    if en.accidental() != 0{
+        res.push_str(&format!("<alter>{}</alter>", en.accidental()));
+    }
+    res.push_str(&format!("<octave>{}</octave></pitch>", en.octave_of(note)));
+    res
+}
+
+fn harmony_xml(root: EnharmonicNote, chord: &Chord, styling: ChordStyling) -> String{
+    let mut res = format!("      <harmony>\n        <root><root-step>{}</root-step>", STEPS[root.letter() as usize]);
+    if root.accidental() != 0{
+        res.push_str(&format!("<root-alter>{}</root-alter>", root.accidental()));
+    }
+    let text = chord.quality(String::new(), false, styling);
+    res.push_str(&format!("</root>\n        <kind text=\"{}\">{}</kind>\n      </harmony>\n", escape_xml(&text), harmony_kind(chord)));
+    res
+}
+
+fn event_xml(event: &XmlEvent, styling: ChordStyling) -> String{
+    let mut res = String::new();
+    if let Some((root, chord)) = &event.harmony{
+        res.push_str(&harmony_xml(*root, chord, styling));
+    }
+    let typ = type_name(event.duration).map(|(name, dot)|
+        format!("<type>{}</type>{}", name, if dot { "<dot/>" } else { "" })).unwrap_or_default();
+    if event.notes.is_empty(){
+        res.push_str(&format!("      <note><rest/><duration>{}</duration>{}</note>\n", event.duration, typ));
+    }
+    for (i, (note, en)) in event.notes.iter().enumerate(){
+        res.push_str(&format!("      <note>{}{}<duration>{}</duration>{}</note>\n",
+            if i > 0 { "<chord/>" } else { "" }, pitch_xml(*note, *en), event.duration, typ));
+    }
+    res
+}
+
+fn write_score(events: Vec<XmlEvent>, key: Option<KeySignature>, options: &MusicXmlOptions) -> String{
+    let measure_len = options.measure_len();
+    let all_notes = events.iter().flat_map(|e| e.notes.iter().map(|(n, _)| *n)).collect::<Vec<_>>();
+    let average = all_notes.iter().sum::<Note>() / all_notes.len().max(1) as Note;
+    // Below middle C on average reads better in bass clef.
+    let clef = if !all_notes.is_empty() && average < 39 { "<sign>F</sign><line>4</line>" } else { "<sign>G</sign><line>2</line>" };
+    let mut res = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    res.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    res.push_str("<score-partwise version=\"4.0\">\n");
+    if !options.title.is_empty(){
+        res.push_str(&format!("  <work><work-title>{}</work-title></work>\n", escape_xml(&options.title)));
+    }
+    res.push_str("  <part-list><score-part id=\"P1\"><part-name>Music</part-name></score-part></part-list>\n  <part id=\"P1\">\n");
+    let mut measures: Vec<Vec<XmlEvent>> = vec![Vec::new()];
+    let mut filled = 0;
+    for event in events{
+        if filled + event.duration > measure_len && filled > 0{
+            measures.push(Vec::new());
+            filled = 0;
+        }
+        filled += event.duration;
+        measures.last_mut().expect("write_score: no measure").push(event);
+    }
+    if filled > 0 && filled < measure_len{
+        measures.last_mut().expect("write_score: no measure").push(XmlEvent{ notes: Vec::new(), duration: measure_len - filled, harmony: None });
+    }
+    for (i, measure) in measures.iter().enumerate(){
+        res.push_str(&format!("    <measure number=\"{}\">\n", i + 1));
+        if i == 0{
+            res.push_str(&format!("      <attributes><divisions>{}</divisions>", DIVISIONS));
+            if let Some(key) = key{
+                res.push_str(&format!("<key><fifths>{}</fifths><mode>{}</mode></key>", key.fifths, key.mode));
+            }
+            let (beats, beat_type) = options.time();
+            res.push_str(&format!("<time><beats>{}</beats><beat-type>{}</beat-type></time><clef>{}</clef></attributes>\n",
+                beats, beat_type, clef));
+        }
+        for event in measure{
+            res.push_str(&event_xml(event, options.styling));
+        }
+        if i + 1 == measures.len(){
+            res.push_str("      <barline location=\"right\"><bar-style>light-heavy</bar-style></barline>\n");
+        }
+        res.push_str("    </measure>\n");
+    }
+    res.push_str("  </part>\n</score-partwise>\n");
+    res
+}
+
+fn spelled_scale(scale: &Scale, start: Option<EnharmonicNote>) -> Vec<EnharmonicNote>{
+    if scale.len() == 7{
+        scale.clone().into_enharmonic_notes_with_start(start)
+    } else {
+        scale.0.iter().map(|n| n.to_enharmonic_note().expect("spelled_scale: should not be None")).collect::<Vec<_>>()
+    }
+}
+
+// A scale in quarter notes, going up to the octave.
+pub fn scale_to_musicxml(scale: &Scale, start: Option<EnharmonicNote>, options: &MusicXmlOptions) -> String{
+    if scale.is_empty() { return write_score(Vec::new(), None, options); }
+    let spelled = spelled_scale(scale, start);
+    let key = if scale.len() == 7 { Some(key_signature(spelled[0], &scale.to_steps())) } else { None };
+    let mut events = scale.0.iter().zip(spelled.iter())
+        .map(|(n, en)| XmlEvent{ notes: vec![(*n, *en)], duration: DIVISIONS, harmony: None })
+        .collect::<Vec<_>>();
+    events.push(XmlEvent{ notes: vec![(scale.0[0] + OCTAVE, spelled[0])], duration: DIVISIONS, harmony: None });
+    write_score(events, key, options)
+}
+
+// One chord per measure with its chord symbol. With a key (tonic and steps) the roots are spelled as in the key.
+pub fn chords_to_musicxml(chords: &[RootedChord], key: Option<(EnharmonicNote, &Steps)>, options: &MusicXmlOptions) -> String{
+    let (signature, key_notes) = match key{
+        Some((tonic, steps)) => {
+            let notes = spelled_scale(&steps.to_scale(tonic.to_note(0)), Some(tonic));
+            (Some(key_signature(tonic, steps)), notes)
+        },
+        None => (None, Vec::new()),
+    };
+    let fifths = signature.map(|s| s.fifths).unwrap_or(0);
+    let events = chords.iter().map(|chord|{
+        let root = spell_in_key(chord.root, &key_notes, fifths);
+        let notes = chord.to_scale().0.into_iter().zip(chord.spelled(Some(root))).collect::<Vec<_>>();
+        XmlEvent{ notes, duration: options.measure_len(), harmony: Some((root, chord.chord.clone())) }
+    }).collect::<Vec<_>>();
+    write_score(events, signature, options)
+}
+
+// The diatonic chords of a scale, like strs_scale_chords but as a score.
+pub fn scale_chords_to_musicxml(steps: &Steps, tonic: EnharmonicNote, rank: Rank, size: usize, options: &MusicXmlOptions) -> String{
+    let chords = rooted_scale_chords(steps, tonic.to_note(0) + rank as Note * OCTAVE, size);
+    chords_to_musicxml(&chords, Some((tonic, steps)), options)
+}
+
+pub fn write_musicxml(path: &str, xml: &str) -> std::io::Result<()>{
+    fs::write(path, xml)
+}
+
+/// A pitched note of a score, times in quarter notes from the start.
+#[derive(Clone,Copy,Debug)]
+pub struct ScoreNote{
+    pub note: Note,
+    pub spelling: EnharmonicNote,
+    pub part: usize,
+    pub measure: usize,
+    pub onset: f32,
+    pub duration: f32,
+}
+
+/// A chord symbol as written in the score.
+#[derive(Clone,Debug)]
+pub struct ScoreHarmony{
+    pub root: EnharmonicNote,
+    pub kind: String,
+    pub text: String,
+    pub bass: Option<EnharmonicNote>,
+    pub measure: usize,
+    pub onset: f32,
+}
+
+impl ScoreHarmony{
+    pub fn chord(&self) -> Option<RootedChord>{
+        harmony_kind_chord(&self.kind).map(|chord| RootedChord::from_chord(self.root.to_note(36), chord))
+    }
+
+    // The symbol as the score shows it, or built from the kind when it has no text.
+    pub fn as_string(&self, styling: ChordStyling) -> String{
+        let mut res = self.root.to_string_name();
+        match harmony_kind_chord(&self.kind){
+            Some(chord) if self.text.is_empty() => res = chord.quality(res, false, styling),
+            _ => res.push_str(&self.text),
+        }
+        if let Some(bass) = self.bass{
+            res.push('/');
+            res.push_str(&bass.to_string_name());
+        }
+        res
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ScoreKey{
+    pub fifths: Note,
+    pub mode: String,
+    pub measure: usize,
+}
+
+impl ScoreKey{
+    pub fn tonic(&self) -> PC{
+        let mode_offset = match self.mode.as_ref(){
+            "minor" | "aeolian" => MAJOR_SIXTH,
+            "dorian" => MAJOR_SECOND,
+            "phrygian" => MAJOR_THIRD,
+            "lydian" => PERFECT_FOURTH,
+            "mixolydian" => PERFECT_FIFTH,
+            "locrian" => MAJOR_SEVENTH,
+            _ => 0,
+        };
+        (C.0 + self.fifths * PERFECT_FIFTH + mode_offset).to_pc()
+    }
+
+    pub fn as_string(&self) -> String{
+        let tonic = spell_in_key(self.tonic().0, &[], self.fifths);
+        let tonic = if self.fifths > 0 && tonic.accidental() < 0 { tonic.next_enharmonic() } else { tonic };
+        format!("{} {}", tonic.to_string_name(), if self.mode.is_empty() { "major" } else { &self.mode })
+    }
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub struct ScoreMeasure{
+    pub number: String,
+    pub onset: f32,
+    pub length: f32,
+    pub beats: u32,
+    pub beat_type: u32,
+}
+
+#[derive(Clone,Debug,Default)]
+pub struct Score{
+    pub title: String,
+    pub parts: Vec<String>,
+    pub measures: Vec<ScoreMeasure>,
+    pub notes: Vec<ScoreNote>,
+    pub harmonies: Vec<ScoreHarmony>,
+    pub keys: Vec<ScoreKey>,
+}
+
+fn read_spelling(el: &XmlElement, step: &str, alter: &str) -> Option<EnharmonicNote>{
+    let step = el.child_text(step)?.to_uppercase();
+    let letter = STEPS.iter().position(|s| *s == step)? as u8;
+    // Microtonal alters are rounded.
+    let alter = el.child_text(alter).and_then(|a| a.parse::<f32>().ok()).unwrap_or(0.0).round() as i8;
+    Some(EnharmonicNote::new(letter, alter))
+}
+
+fn read_time(el: &XmlElement) -> Option<(u32, u32)>{
+    let time = el.child("time")?;
+    // Composite signatures like 3+2 are summed.
+    let beats = time.child_text("beats")?.split('+').map(|b| b.trim().parse::<u32>().ok()).sum::<Option<u32>>()?;
+    let beat_type = time.child_text("beat-type")?.parse::<u32>().ok()?;
+    if beats == 0 || beat_type == 0 { return None; }
+    Some((beats, beat_type))
+}
+
+// What one part has in one measure, onsets relative to the start of the measure.
+#[derive(Default)]
+struct PartMeasure{
+    notes: Vec<ScoreNote>,
+    harmonies: Vec<ScoreHarmony>,
+    key: Option<(Note, String)>,
+    time: Option<(u32, u32)>,
+    length: f32,
+}
+
+// Notes and harmonies that can't be read are left out, the rest of the measure still counts.
+fn read_measure(el: &XmlElement, part: usize, measure: usize, divisions: &mut f32) -> PartMeasure{
+    let mut res = PartMeasure::default();
+    let mut pos = 0.0f32;
+    let mut last_onset = 0.0;
+    let duration = |e: &XmlElement, divisions: f32| e.child_text("duration").and_then(|d| d.parse::<f32>().ok()).unwrap_or(0.0) / divisions;
+    for child in el.elements(){
+        match child.name.as_ref(){
+            "attributes" => {
+                if let Some(d) = child.child_text("divisions").and_then(|d| d.parse::<f32>().ok()){
+                    if d > 0.0 { *divisions = d; }
+                }
+                if let Some(key) = child.child("key"){
+                    if let Some(fifths) = key.child_text("fifths").and_then(|f| f.parse::<Note>().ok()){
+                        res.key = Some((fifths, key.child_text("mode").unwrap_or_else(|| String::from("major"))));
+                    }
+                }
+                if let Some(time) = read_time(child){
+                    res.time = Some(time);
+                }
+            },
+            "note" => {
+                if child.has_child("grace") { continue; }
+                let dur = duration(child, *divisions);
+                let onset = if child.has_child("chord") { last_onset } else {
+                    last_onset = pos;
+                    pos += dur;
+                    last_onset
+                };
+                let Some(pitch) = child.child("pitch") else { continue; };
+                let spelling = read_spelling(pitch, "step", "alter");
+                let octave = pitch.child_text("octave").and_then(|o| o.parse::<Note>().ok());
+                if let (Some(spelling), Some(octave)) = (spelling, octave){
+                    let note = spelling.in_octave(octave);
+                    res.notes.push(ScoreNote{ note, spelling, part, measure, onset, duration: dur });
+                }
+            },
+            "backup" => pos = (pos - duration(child, *divisions)).max(0.0),
+            "forward" => pos += duration(child, *divisions),
+            "harmony" => {
+                let Some(root) = child.child("root").and_then(|r| read_spelling(r, "root-step", "root-alter")) else { continue; };
+                let (kind, text) = match child.child("kind"){
+                    Some(k) => (k.text(), k.attr("text").unwrap_or("").to_string()),
+                    None => (String::from("none"), String::new()),
+                };
+                let bass = child.child("bass").and_then(|b| read_spelling(b, "bass-step", "bass-alter"));
+                let offset = child.child_text("offset").and_then(|o| o.parse::<f32>().ok()).unwrap_or(0.0) / *divisions;
+                res.harmonies.push(ScoreHarmony{ root, kind, text, bass, measure, onset: pos + offset });
+            },
+            _ => {},
+        }
+        res.length = res.length.max(pos);
+    }
+    res
+}
+
+// Partwise and timewise scores. Compressed .mxl files have to be unzipped first.
+pub fn parse_musicxml(src: &str) -> Option<Score>{
+    let root = parse_xml(src)?;
+    // Per part: its id and (measure number, the element holding the music) in order.
+    let mut parts: Vec<(String, Vec<(String, &XmlElement)>)> = Vec::new();
+    match root.name.as_ref(){
+        "score-partwise" => for part in root.children_named("part"){
+            let measures = part.children_named("measure").map(|m| (m.attr("number").unwrap_or("").to_string(), m)).collect();
+            parts.push((part.attr("id").unwrap_or("").to_string(), measures));
+        },
+        "score-timewise" => for measure in root.children_named("measure"){
+            let number = measure.attr("number").unwrap_or("").to_string();
+            for part in measure.children_named("part"){
+                let id = part.attr("id").unwrap_or("");
+                match parts.iter_mut().find(|(i, _)| i == id){
+                    Some((_, ms)) => ms.push((number.clone(), part)),
+                    None => parts.push((id.to_string(), vec![(number.clone(), part)])),
+                }
+            }
+        },
+        _ => return None,
+    }
+    let title = root.child("work").and_then(|w| w.child_text("work-title"))
+        .or_else(|| root.child_text("movement-title")).unwrap_or_default();
+    let mut score = Score{ title, ..Default::default() };
+    let part_list = root.child("part-list");
+    score.parts = parts.iter().map(|(id, _)| part_list
+        .and_then(|pl| pl.children_named("score-part").find(|sp| sp.attr("id") == Some(id.as_str())))
+        .and_then(|sp| sp.child_text("part-name")).unwrap_or_else(|| id.clone())).collect();
+    let mut read = Vec::new();
+    for (p, (_, measures)) in parts.iter().enumerate(){
+        let mut divisions = 1.0;
+        let mut res = Vec::new();
+        for (m, (_, el)) in measures.iter().enumerate(){
+            res.push(read_measure(el, p, m, &mut divisions));
+        }
+        read.push(res);
+    }
+    let count = parts.iter().map(|(_, ms)| ms.len()).max().unwrap_or(0);
+    let (mut beats, mut beat_type) = (4, 4);
+    let mut onset = 0.0;
+    for m in 0..count{
+        let in_measure = read.iter().filter_map(|pms| pms.get(m)).collect::<Vec<_>>();
+        if let Some((b, bt)) = in_measure.iter().find_map(|pm| pm.time){
+            beats = b;
+            beat_type = bt;
+        }
+        if let Some((fifths, mode)) = in_measure.iter().find_map(|pm| pm.key.clone()){
+            if score.keys.last().map(|k| k.fifths != fifths || k.mode != mode).unwrap_or(true){
+                score.keys.push(ScoreKey{ fifths, mode, measure: m });
+            }
+        }
+        let written = in_measure.iter().fold(0.0f32, |a, pm| a.max(pm.length));
+        // Pickups are as long as what is in them, empty measures as long as the signature says.
+        let length = if written > 0.0 { written } else { beats as f32 * 4.0 / beat_type as f32 };
+        let number = parts.iter().find_map(|(_, ms)| ms.get(m).map(|(n, _)| n.clone())).unwrap_or_default();
+        score.measures.push(ScoreMeasure{ number, onset, length, beats, beat_type });
+        for pm in in_measure{
+            score.notes.extend(pm.notes.iter().map(|n| ScoreNote{ onset: n.onset + onset, ..*n }));
+            score.harmonies.extend(pm.harmonies.iter().map(|h| ScoreHarmony{ onset: h.onset + onset, ..h.clone() }));
+        }
+        onset += length;
+    }
+    score.notes.sort_by(|a, b| a.onset.total_cmp(&b.onset).then(a.note.cmp(&b.note)));
+    score.harmonies.sort_by(|a, b| a.onset.total_cmp(&b.onset));
+    Some(score)
+}
+
+pub fn read_musicxml(path: &str) -> Option<Score>{
+    parse_musicxml(&fs::read_to_string(path).ok()?)
+}
+
+/// The notes sounding during one beat, named as a chord.
+#[derive(Clone)]
+pub struct Sonority{
+    pub measure: usize,
+    // Starting at 0.
+    pub beat: usize,
+    pub onset: f32,
+    pub chord: RootedChord,
+    pub bass: EnharmonicNote,
+    // One spelling per pitchclass, from the bass up.
+    pub spelled: Vec<EnharmonicNote>,
+}
+
+impl Sonority{
+    pub fn root_spelling(&self) -> EnharmonicNote{
+        let root = self.chord.root.to_pc();
+        *self.spelled.iter().find(|en| en.to_pc() == root).expect("Sonority::root_spelling: root should be spelled")
+    }
+
+    // Like a chord symbol, inversions get a slash and the bass.
+    pub fn as_string(&self, styling: ChordStyling) -> String{
+        let mut res = self.chord.chord.quality(self.root_spelling().to_string_name(), false, styling);
+        if self.bass.to_pc() != self.chord.root.to_pc(){
+            res.push('/');
+            res.push_str(&self.bass.to_string_name());
+        }
+        res
+    }
+}
+
+// Names pitchclasses, bass first, as a chord: the first inversion the chord book knows wins, root position first.
+pub fn name_sonority(pcs: &[PC], styling: ChordStyling) -> RootedChord{
+    let rchord = RootedChord::from_scale(pcs.to_vec().to_scale(0));
+    let mut cands = vec![rchord.clone()];
+    let mut inversions = rchord.all_inversions();
+    inversions.pop();
+    cands.extend(inversions);
+    // Straight from the book beats sus chords, which beat extended ones.
+    let rank = |c: &RootedChord|{
+        let name = c.chord.quality(String::new(), false, styling);
+        if STD_CHORD_BOOK.iter().any(|(p, _, _, ext)| c.chord.same_intervals(p) && !(*ext && styling == ChordStyling::Std)) { 0 }
+        else if name.contains('[') { 3 }
+        else if name.contains('(') { 2 }
+        else { 1 }
+    };
+    cands.into_iter().min_by_key(rank).unwrap_or(rchord)
+}
+
+/// Measures that share a key, with the scales that hold all their notes.
+pub struct Passage{
+    pub first_measure: usize,
+    pub last_measure: usize,
+    pub key: Option<ScoreKey>,
+    // Most sounding first.
+    pub pcs: PCs,
+    // Smallest scales first, then the ones on the tonic of the key.
+    pub scales: Vec<(PC, ModeObj)>,
+}
+
+impl Score{
+    pub fn key_at(&self, measure: usize) -> Option<&ScoreKey>{
+        self.keys.iter().rev().find(|k| k.measure <= measure)
+    }
+
+    fn sounding(&self, from: f32, to: f32) -> Vec<&ScoreNote>{
+        self.notes.iter().filter(|n| n.onset < to && n.onset + n.duration > from && n.duration > 0.0).collect()
+    }
+
+    // One sonority per beat that has notes, beats as the time signature counts them.
+    pub fn sonorities(&self, styling: ChordStyling) -> Vec<Sonority>{
+        let mut res = Vec::new();
+        for (m, measure) in self.measures.iter().enumerate(){
+            let beat_len = 4.0 / measure.beat_type as f32;
+            let beats = (measure.length / beat_len - 0.001).ceil().max(1.0) as usize;
+            for beat in 0..beats{
+                let onset = measure.onset + beat as f32 * beat_len;
+                let end = (onset + beat_len).min(measure.onset + measure.length);
+                let mut notes = self.sounding(onset, end);
+                if notes.is_empty() { continue; }
+                notes.sort_by_key(|n| n.note);
+                let mut pcs = Vec::new();
+                let mut spelled = Vec::new();
+                for n in notes{
+                    if !pcs.contains(&n.note.to_pc()){
+                        pcs.push(n.note.to_pc());
+                        spelled.push(n.spelling);
+                    }
+                }
+                let chord = name_sonority(&pcs, styling);
+                res.push(Sonority{ measure: m, beat, onset, chord, bass: spelled[0], spelled });
+            }
+        }
+        res
+    }
+
+    // Passages end at key changes and, when measures > 0, every that many measures.
+    pub fn passages(&self, measures: usize) -> Vec<Passage>{
+        let mut res = Vec::new();
+        let mut first = 0;
+        for m in 0..self.measures.len(){
+            let last = m + 1 == self.measures.len();
+            let key_change = self.keys.iter().any(|k| k.measure == m + 1);
+            let full = measures > 0 && m + 1 - first >= measures;
+            if !(last || key_change || full) { continue; }
+            let from = self.measures[first].onset;
+            let to = self.measures[m].onset + self.measures[m].length;
+            let mut weights: Vec<(PC, f32)> = Vec::new();
+            for n in self.sounding(from, to){
+                let dur = (n.onset + n.duration).min(to) - n.onset.max(from);
+                match weights.iter_mut().find(|(pc, _)| *pc == n.note.to_pc()){
+                    Some((_, w)) => *w += dur,
+                    None => weights.push((n.note.to_pc(), dur)),
+                }
+            }
+            weights.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let pcs = weights.into_iter().map(|(pc, _)| pc).collect::<Vec<_>>();
+            let key = self.key_at(first).cloned();
+            let mut scales = if pcs.is_empty() { Vec::new() } else { find_scale_superset(pcs.clone(), false) };
+            let tonic = key.as_ref().map(|k| k.tonic());
+            scales.sort_by_key(|(pc, mo)| (mo.steps.len(), Some(*pc) != tonic));
+            res.push(Passage{ first_measure: first, last_measure: m, key, pcs, scales });
+            first = m + 1;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    #[test]
+    fn test_scale_export(){
+        let f = "F".to_string().to_enharmonic_note().unwrap();
+        let xml = scale_to_musicxml(&ionian::steps().to_scale(f.to_note(36)), Some(f), &MusicXmlOptions::default());
+        assert!(xml.contains("<key><fifths>-1</fifths><mode>major</mode></key>"));
+        assert!(xml.contains("<step>B</step><alter>-1</alter><octave>4</octave>"));
+        assert!(xml.contains("<measure number=\"2\">"));
+        assert_eq!(xml.matches("<note>").count(), 8);
+    }
+    #[test]
+    fn test_chords_export(){
+        let bb = "Bb".to_string().to_enharmonic_note().unwrap();
+        let xml = scale_chords_to_musicxml(&ionian::steps(), bb, 3, 4, &MusicXmlOptions::default());
+        assert!(xml.contains("<root><root-step>B</root-step><root-alter>-1</root-alter></root>"));
+        assert!(xml.contains("<kind text=\"∆\">major-seventh</kind>"));
+        assert!(xml.contains("<root><root-step>E</root-step><root-alter>-1</root-alter></root>"));
+        assert_eq!(xml.matches("<harmony>").count(), 7);
+        let broken = MusicXmlOptions{ beat_type: 0, ..MusicXmlOptions::default() };
+        let xml = scale_chords_to_musicxml(&ionian::steps(), bb, 3, 4, &broken);
+        assert!(xml.contains("<beats>4</beats><beat-type>4</beat-type>"));
+        assert_eq!(xml.matches("<harmony>").count(), 7);
+    }
+    #[test]
+    fn test_import(){
+        let src = "<score-partwise><part-list><score-part id=\"P1\"><part-name>Piano</part-name></score-part></part-list>\
+            <part id=\"P1\"><measure number=\"1\"><attributes><divisions>2</divisions><key><fifths>-1</fifths></key>\
+            <time><beats>4</beats><beat-type>4</beat-type></time></attributes>\
+            <harmony><root><root-step>F</root-step></root><kind>major</kind></harmony>\
+            <note><pitch><step>F</step><octave>4</octave></pitch><duration>8</duration></note>\
+            <note><chord/><pitch><step>A</step><octave>4</octave></pitch><duration>8</duration></note>\
+            <backup><duration>8</duration></backup>\
+            <note><pitch><step>C</step><octave>3</octave></pitch><duration>8</duration></note></measure>\
+            <measure number=\"2\"><note><pitch><step>B</step><alter>-1</alter><octave>3</octave></pitch><duration>8</duration></note>\
+            <note><chord/><pitch><step>D</step><octave>4</octave></pitch><duration>8</duration></note>\
+            <note><chord/><pitch><step>G</step><octave>4</octave></pitch><duration>8</duration></note></measure></part></score-partwise>";
+        let score = parse_musicxml(src).unwrap();
+        assert_eq!(score.parts, vec!["Piano"]);
+        assert_eq!(score.measures.len(), 2);
+        assert_eq!(score.measures[1].onset, 4.0);
+        assert_eq!(score.keys[0].as_string(), "F major");
+        assert_eq!(score.harmonies[0].as_string(ChordStyling::Std), "F");
+        let names = score.sonorities(ChordStyling::Std).iter().map(|s| s.as_string(ChordStyling::Std)).collect::<Vec<_>>();
+        assert_eq!(names, vec!["F/C", "F/C", "F/C", "F/C", "Gm/B♭", "Gm/B♭", "Gm/B♭", "Gm/B♭"]);
+        let passages = score.passages(0);
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].pcs.len(), 6);
+        assert_eq!(passages[0].scales[0].0, F);
+        // A note and a harmony with a step that is no letter are skipped.
+        let bad = src.replace("<step>A</step>", "<step>H</step>").replace("<root-step>F</root-step>", "<root-step>X</root-step>");
+        let score = parse_musicxml(&bad).unwrap();
+        assert_eq!(score.notes.len(), 5);
+        assert!(score.harmonies.is_empty());
+        assert_eq!(score.measures[1].onset, 4.0);
+    }
+    #[test]
+    fn test_export_import(){
+        let chords = vec![RootedChord::from_intervals(D.0 + 36, MINOR_SEVENTH_CHORD), RootedChord::from_intervals(G.0 + 36, DOMINANT_SEVENTH)];
+        let c = "C".to_string().to_enharmonic_note().unwrap();
+        let xml = chords_to_musicxml(&chords, Some((c, &ionian::steps())), &MusicXmlOptions::default());
+        let score = parse_musicxml(&xml).unwrap();
+        let symbols = score.harmonies.iter().map(|h| h.as_string(ChordStyling::Std)).collect::<Vec<_>>();
+        assert_eq!(symbols, vec!["Dm-", "G⁷"]);
+        assert_eq!(score.notes.len(), 8);
+        assert_eq!(score.sonorities(ChordStyling::Std).len(), 8);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::theory::note::*;
+use crate::theory::tuning::{ Tuning, Edo };
+use crate::theory::just::{ Ratio, Limit, just_ratio };
+use std::fs;
+
+// Our notes start at A0, which is midi note 21.
+const MIDI_A0: i32 = 21;
+const MIDI_C4: i32 = 60;
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum ScalaPitch{ Cents(f32), Ratio(Ratio) }
+
+impl ScalaPitch{
+    pub fn cents(&self) -> f32{
+        match self{
+            ScalaPitch::Cents(c) => *c,
+            ScalaPitch::Ratio(r) => r.cents(),
+        }
+    }
+}
+
+impl std::fmt::Display for ScalaPitch{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            ScalaPitch::Cents(c) => write!(f, "{:.5}", c),
+            ScalaPitch::Ratio(r) => write!(f, "{}", r),
+        }
+    }
+}
+
+/// The contents of a .scl file.
+/// The 1/1 is implicit, the last pitch is the period (usually 2/1).
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct ScalaScale{
+    pub description: String,
+    pub pitches: Vec<ScalaPitch>,
+}
+
+// Skips comments, keeps empty lines as the description may be empty.
+fn scala_lines(text: &str) -> impl Iterator<Item = &str>{
+    text.lines().map(|l| l.trim_end_matches('\r')).filter(|l| !l.starts_with('!'))
+}
+
+fn parse_pitch(line: &str) -> Option<ScalaPitch>{
+    let token = line.split_whitespace().next()?;
+    if token.contains('.'){
+        return token.parse::<f32>().ok().map(ScalaPitch::Cents);
+    }
+    let mut parts = token.split('/');
+    let num = parts.next()?.parse::<u32>().ok()?;
+    let den = match parts.next(){
+        Some(d) => d.parse::<u32>().ok()?,
+        None => 1,
+    };
+    if den == 0 { return None; }
+    Some(ScalaPitch::Ratio(Ratio(num, den)))
+}
+
+impl ScalaScale{
+    pub fn parse(text: &str) -> Option<Self>{
+        let mut lines = scala_lines(text);
+        let description = lines.next()?.trim().to_string();
+        let count = lines.next()?.trim().parse::<usize>().ok()?;
+        let mut pitches = Vec::new();
+        for line in lines.filter(|l| !l.trim().is_empty()).take(count){
+            pitches.push(parse_pitch(line.trim())?);
+        }
+        if pitches.len() != count { return None; }
+        Some(Self{ description, pitches })
+    }
+
+    pub fn to_scl_string(&self) -> String{
+        let mut res = String::from("! Written by music-theory-query\n!\n");
+        res.push_str(&format!("{}\n {}\n!\n", self.description, self.pitches.len()));
+        for p in &self.pitches{
+            res.push_str(&format!(" {}\n", p));
+        }
+        res
+    }
+
+    pub fn len(&self) -> usize{
+        self.pitches.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.pitches.is_empty()
+    }
+
+    pub fn period_cents(&self) -> f32{
+        self.pitches.last().map(|p| p.cents()).unwrap_or(1200.0)
+    }
+
+    // Cents above the 1/1 for any degree, wrapping around the period.
+    pub fn degree_cents(&self, degree: i32) -> f32{
+        if self.pitches.is_empty() { return 0.0; }
+        let len = self.pitches.len() as i32;
+        let periods = degree.div_euclid(len);
+        let rest = degree.rem_euclid(len);
+        let within = if rest == 0 { 0.0 } else { self.pitches[rest as usize - 1].cents() };
+        periods as f32 * self.period_cents() + within
+    }
+
+    pub fn from_steps(steps: &Steps, edo: Edo, description: &str) -> Self{
+        let step_cents = 1200.0 / edo.divisions() as f32;
+        let mut acc = 0;
+        let pitches = steps.0.iter().map(|s| { acc += s; ScalaPitch::Cents(acc as f32 * step_cents) }).collect::<Vec<_>>();
+        Self{ description: description.to_string(), pitches }
+    }
+
+    pub fn from_steps_just(steps: &Steps, limit: Limit, description: &str) -> Self{
+        let mut acc = 0;
+        let pitches = steps.0.iter().map(|s| { acc += s; just_ratio(acc, limit).map(ScalaPitch::Ratio).unwrap_or(ScalaPitch::Cents(acc as f32 * 100.0)) }).collect::<Vec<_>>();
+        Self{ description: description.to_string(), pitches }
+    }
+
+    // Samples one octave of any tuning, starting from A4. Notes without a pitch are left out.
+    pub fn from_tuning(tuning: &dyn Tuning, description: &str) -> Self{
+        let base = tuning.reference_note();
+        let base_pitch = tuning.to_pitch(base);
+        let pitches = (1..=tuning.octave())
+            .map(|i| tuning.to_pitch(base + i) / base_pitch)
+            .filter(|ratio| ratio.is_finite())
+            .map(|ratio| ScalaPitch::Cents(crate::theory::just::cents(ratio)))
+            .collect::<Vec<_>>();
+        Self{ description: description.to_string(), pitches }
+    }
+}
+
+/// The contents of a .kbm file: which midi key plays which scale degree.
+/// An empty mapping maps every key to the next degree.
+#[derive(Clone,PartialEq,Debug)]
+pub struct KeyboardMapping{
+    pub first: i32,
+    pub last: i32,
+    pub middle: i32,
+    pub reference_note: i32,
+    pub reference_freq: f32,
+    pub octave_degree: i32,
+    pub mapping: Vec<Option<i32>>,
+}
+
+impl Default for KeyboardMapping{
+    fn default() -> Self{
+        Self{
+            first: 0,
+            last: 127,
+            middle: MIDI_C4,
+            reference_note: MIDI_C4,
+            reference_freq: to_pitch(MIDI_C4 - MIDI_A0),
+            octave_degree: 0,
+            mapping: Vec::new(),
+        }
+    }
+}
+
+impl KeyboardMapping{
+    pub fn parse(text: &str) -> Option<Self>{
+        let mut lines = scala_lines(text).map(|l| l.trim()).filter(|l| !l.is_empty());
+        let mut int = || lines.next().and_then(|l| l.split_whitespace().next()).map(|t| t.to_string());
+        let size = int()?.parse::<usize>().ok()?;
+        let first = int()?.parse::<i32>().ok()?;
+        let last = int()?.parse::<i32>().ok()?;
+        let middle = int()?.parse::<i32>().ok()?;
+        let reference_note = int()?.parse::<i32>().ok()?;
+        let reference_freq = int()?.parse::<f32>().ok()?;
+        let octave_degree = int()?.parse::<i32>().ok()?;
+        let mut mapping = Vec::new();
+        for _ in 0..size{
+            let token = int()?;
+            if token == "x" || token == "X"{
+                mapping.push(None);
+            } else {
+                mapping.push(Some(token.parse::<i32>().ok()?));
+            }
+        }
+        Some(Self{ first, last, middle, reference_note, reference_freq, octave_degree, mapping })
+    }
+
+    pub fn to_kbm_string(&self) -> String{
+        let mut res = String::from("! Written by music-theory-query\n");
+        res.push_str(&format!("{}\n{}\n{}\n{}\n{}\n{:.6}\n{}\n", self.mapping.len(), self.first, self.last,
+            self.middle, self.reference_note, self.reference_freq, self.octave_degree));
+        for m in &self.mapping{
+            match m{
+                Some(d) => res.push_str(&format!("{}\n", d)),
+                None => res.push_str("x\n"),
+            }
+        }
+        res
+    }
+
+    // The scale degree a midi key plays, None when unmapped.
+    pub fn degree(&self, midi: i32, scale_len: usize) -> Option<i32>{
+        if midi < self.first || midi > self.last { return None; }
+        let offset = midi - self.middle;
+        if self.mapping.is_empty() { return Some(offset); }
+        let size = self.mapping.len() as i32;
+        let octave = if self.octave_degree == 0 { scale_len as i32 } else { self.octave_degree };
+        let degree = self.mapping[offset.rem_euclid(size) as usize]?;
+        Some(degree + offset.div_euclid(size) * octave)
+    }
+}
+
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct ScalaTuning{
+    pub scale: ScalaScale,
+    pub mapping: KeyboardMapping,
+}
+
+impl ScalaTuning{
+    pub fn new(scale: ScalaScale, mapping: KeyboardMapping) -> Self{
+        Self{ scale, mapping }
+    }
+
+    pub fn from_scale(scale: ScalaScale) -> Self{
+        Self{ scale, mapping: KeyboardMapping::default() }
+    }
+
+    pub fn pitch(&self, note: Note) -> Option<f32>{
+        let len = self.scale.len();
+        let degree = self.mapping.degree(note + MIDI_A0, len)?;
+        let reference = self.mapping.degree(self.mapping.reference_note, len)?;
+        let cents = self.scale.degree_cents(degree) - self.scale.degree_cents(reference);
+        Some(self.mapping.reference_freq * (2.0f32).powf(cents / 1200.0))
+    }
+}
+
+impl Tuning for ScalaTuning{
+    fn octave(&self) -> Note{
+        if self.mapping.mapping.is_empty() { self.scale.len() as Note } else { self.mapping.mapping.len() as Note }
+    }
+
+    // Unmapped keys have no pitch and give NaN.
+    fn to_pitch(&self, note: Note) -> f32{
+        self.pitch(note).unwrap_or(f32::NAN)
+    }
+
+    // Notes are keys, whatever the size of the mapping.
+    fn reference_note(&self) -> Note{
+        A4
+    }
+}
+
+pub fn read_scl(path: &str) -> Option<ScalaScale>{
+    ScalaScale::parse(&fs::read_to_string(path).ok()?)
+}
+
+pub fn write_scl(path: &str, scale: &ScalaScale) -> std::io::Result<()>{
+    fs::write(path, scale.to_scl_string())
+}
+
+pub fn read_kbm(path: &str) -> Option<KeyboardMapping>{
+    KeyboardMapping::parse(&fs::read_to_string(path).ok()?)
+}
+
+pub fn write_kbm(path: &str, mapping: &KeyboardMapping) -> std::io::Result<()>{
+    fs::write(path, mapping.to_kbm_string())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::theory::tuning::EDO12;
+    use crate::libr::scales::ionian;
+    const PYTHAGOREAN: &str = "! pyth.scl\n!\nPythagorean pentatonic\n 5\n!\n 9/8\n 81/64\n 3/2\n 27/16\n 2/1\n";
+    #[test]
+    fn test_scl_parse(){
+        let scl = ScalaScale::parse(PYTHAGOREAN).unwrap();
+        assert_eq!(scl.description, "Pythagorean pentatonic");
+        assert_eq!(scl.pitches[1], ScalaPitch::Ratio(Ratio(81, 64)));
+        assert_eq!(scl.period_cents().round() as i32, 1200);
+        assert_eq!(ScalaScale::parse(&scl.to_scl_string()), Some(scl));
+    }
+    #[test]
+    fn test_scala_tuning(){
+        let scl = ScalaScale::from_steps(&ionian::steps(), EDO12, "Ionian");
+        let kbm = KeyboardMapping::parse("12\n0\n127\n60\n69\n440.0\n7\n0\nx\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n").unwrap();
+        assert_eq!(kbm.mapping.len(), 12);
+        let tuning = ScalaTuning::new(scl, kbm);
+        assert_eq!(tuning.to_pitch(A4).round() as i32, 440);
+        assert_eq!(tuning.to_pitch(A4 + 12).round() as i32, 880);
+        assert!(tuning.pitch(A4 + 1).is_none());
+        // The seven mapped keys from A4 up, the unmapped ones left out.
+        let sampled = ScalaScale::from_tuning(&tuning, "");
+        assert_eq!(sampled.pitches.iter().map(|p| p.cents().round() as i32).collect::<Vec<_>>(), vec![200, 300, 500, 700, 800, 1000, 1200]);
+        let five = ScalaTuning::from_scale(ScalaScale::parse(PYTHAGOREAN).unwrap());
+        assert_eq!(ScalaScale::from_tuning(&five, "").pitches.len(), 5);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::OCTAVE;
+use crate::theory::symbol::ascii_name;
+use crate::fretted::{ Instrument, ChordShape };
+use std::fs;
+
+/// A note read from tab, timed in columns of text from the start of the tab.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct TabNote{
+    pub note: Note,
+    // Lowest string is 0.
+    pub string: usize,
+    pub fret: Note,
+    pub onset: usize,
+    // Until the next note on the same string or the end of the line.
+    pub duration: usize,
+}
+
+#[derive(Clone,Debug)]
+pub struct Tab{
+    pub instrument: Instrument,
+    pub notes: Vec<TabNote>,
+}
+
+// The name and what follows the first barline of a tab line like e|--3--|, None for other lines.
+fn tab_line(line: &str) -> Option<(&str, &str)>{
+    let line = line.trim();
+    let bar = line.find(['|', ':'])?;
+    let (label, body) = (line[..bar].trim(), &line[bar + 1..]);
+    if label.len() > 3 || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '#') { return None; }
+    if body.matches('-').count() < 3 { return None; }
+    Some((label, body))
+}
+
+fn tuning_header(line: &str) -> Option<&str>{
+    let line = line.trim();
+    if !line.get(..6).is_some_and(|h| h.eq_ignore_ascii_case("tuning")) { return None; }
+    Some(line[6..].trim_start_matches([':', ' ', '\t']).trim())
+}
+
+// A tuning written as note names, or by name like Drop D. E A D G is a bass.
+fn parse_tuning(s: &str) -> Option<Instrument>{
+    if let Some(i) = Instrument::parse(s) { return Some(as_bass(i)); }
+    let names = if s.contains([',', ' ']){
+        s.split([',', ' ']).filter(|n| !n.is_empty()).collect::<Vec<_>>().join(",")
+    } else {
+        // EADGBE: a letter with the accidentals after it. A b before a lowercase name is
+        // the B string, like in EADGbe.
+        let chars = s.chars().collect::<Vec<_>>();
+        let mut names: Vec<String> = Vec::new();
+        for (i, c) in chars.iter().enumerate(){
+            let string_next = chars.get(i + 1).is_some_and(|n| ('a'..='g').contains(n));
+            match names.last_mut(){
+                Some(n) if *c == '#' || *c == 'b' && !string_next => n.push(*c),
+                _ => names.push(c.to_string()),
+            }
+        }
+        names.join(",")
+    };
+    Instrument::parse(&names).map(as_bass)
+}
+
+// Four strings tuned like the lowest of a guitar are the octave below on a bass.
+fn as_bass(instrument: Instrument) -> Instrument{
+    let bass = Instrument::bass();
+    if instrument.strings.iter().map(|n| n - OCTAVE).eq(bass.strings.iter().copied()) { bass } else { instrument }
+}
+
+// Reads the frets on one line of a block, with the column they start in.
+fn line_frets(body: &[char]) -> Vec<(usize, Note)>{
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < body.len(){
+        if body[i].is_ascii_digit(){
+            let start = i;
+            while i < body.len() && body[i].is_ascii_digit() { i += 1; }
+            let fret = body[start..i].iter().collect::<String>().parse().unwrap_or(0);
+            // After a b or r the number is the pitch bent to, not a new note.
+            let bent = start > 0 && (body[start - 1] == 'b' || body[start - 1] == 'r');
+            if !bent { res.push((start, fret)); }
+        } else {
+            i += 1;
+        }
+    }
+    res
+}
+
+// The tab blocks of a text, guitar with six lines unless a tuning header or the line names say otherwise.
+// None when there is no tab or the tuning does not match the lines.
+pub fn parse_tab(src: &str, instrument: Option<&Instrument>) -> Option<Tab>{
+    let mut header = None;
+    let mut blocks: Vec<Vec<(&str, &str)>> = Vec::new();
+    let mut block = Vec::new();
+    for line in src.lines(){
+        if let Some(t) = tuning_header(line) { header = parse_tuning(t); }
+        match tab_line(line){
+            Some(l) => block.push(l),
+            None if !block.is_empty() => blocks.push(std::mem::take(&mut block)),
+            None => {},
+        }
+    }
+    if !block.is_empty() { blocks.push(block); }
+    let blocks = blocks.into_iter().filter(|b| b.len() > 1).collect::<Vec<_>>();
+    let first = blocks.first()?;
+    let instrument = match (instrument, header){
+        (Some(i), _) => i.clone(),
+        (None, Some(i)) => i,
+        (None, None) => {
+            let labels = first.iter().rev().map(|(l, _)| *l).collect::<Vec<_>>();
+            match parse_tuning(&labels.join(",")){
+                Some(i) if i.strings.len() == labels.len() && labels.len() == 6 && labels[0].eq_ignore_ascii_case("e") => Instrument::guitar(),
+                Some(i) if i.strings.len() == labels.len() => i,
+                _ if labels.len() == 6 => Instrument::guitar(),
+                _ => return None,
+            }
+        },
+    };
+    let mut notes = Vec::new();
+    let mut offset = 0;
+    for block in blocks{
+        if block.len() != instrument.strings.len() { return None; }
+        let width = block.iter().map(|(_, b)| b.chars().count()).max().unwrap_or(0);
+        for (line, (_, body)) in block.iter().enumerate(){
+            let string = instrument.strings.len() - 1 - line;
+            let frets = line_frets(&body.chars().collect::<Vec<_>>());
+            for (k, (col, fret)) in frets.iter().enumerate(){
+                let end = frets.get(k + 1).map(|(c, _)| *c).unwrap_or(width);
+                notes.push(TabNote{ note: instrument.note_at(string, *fret), string, fret: *fret, onset: offset + col, duration: end - col });
+            }
+        }
+        offset += width;
+    }
+    notes.sort_by_key(|n| (n.onset, n.note));
+    Some(Tab{ instrument, notes })
+}
+
+pub fn read_tab(path: &str, instrument: Option<&Instrument>) -> Option<Tab>{
+    parse_tab(&fs::read_to_string(path).ok()?, instrument)
+}
+
+impl Tab{
+    // The pitchclasses, the ones sounding longest first, ready for pcs_analysis or the scale queries.
+    pub fn pcs(&self) -> PCs{
+        let mut weights: Vec<(PC, usize)> = Vec::new();
+        for n in &self.notes{
+            match weights.iter_mut().find(|(pc, _)| *pc == n.note.to_pc()){
+                Some((_, w)) => *w += n.duration,
+                None => weights.push((n.note.to_pc(), n.duration)),
+            }
+        }
+        weights.sort_by_key(|(_, w)| std::cmp::Reverse(*w));
+        weights.into_iter().map(|(pc, _)| pc).collect()
+    }
+
+    // Notes struck together, lowest first, with their onset.
+    pub fn chords(&self) -> Vec<(usize, Scale)>{
+        let mut res: Vec<(usize, Scale)> = Vec::new();
+        for n in &self.notes{
+            match res.last_mut(){
+                Some((onset, scale)) if *onset == n.onset => scale.0.push(n.note),
+                _ => res.push((n.onset, Scale(vec![n.note]))),
+            }
+        }
+        res.retain(|(_, s)| s.0.len() > 1);
+        res
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TabOptions{
+    pub instrument: Instrument,
+    // Dashes before every note or chord.
+    pub spacing: usize,
+    // Notes or chords per bar, all on one bar when None.
+    pub per_bar: Option<usize>,
+    // The fret the hand stays around when placing the notes of a scale.
+    pub position: Option<Note>,
+}
+
+impl Default for TabOptions{
+    fn default() -> Self{
+        Self{ instrument: Instrument::guitar(), spacing: 2, per_bar: Some(8), position: None }
+    }
+}
+
+// Writes columns of frets, lowest string first in every column, as tab with the highest string on top.
+pub fn frets_to_tab(columns: &[Vec<Option<Note>>], options: &TabOptions) -> String{
+    let strings = &options.instrument.strings;
+    let mut names = strings.iter().map(|n| ascii_name(n.to_enharmonic_note().expect("frets_to_tab: should not be None"))).collect::<Vec<_>>();
+    let last = names.len() - 1;
+    if last > 0 && names[last] == names[0] { names[last] = names[last].to_lowercase(); }
+    let label_width = names.iter().map(|n| n.len()).max().unwrap_or(1);
+    let mut res = format!("Tuning: {}\n", names.iter().map(|n| n.to_uppercase()).collect::<Vec<_>>().join(" "));
+    for s in (0..strings.len()).rev(){
+        let mut line = format!("{:<w$}|", names[s], w = label_width);
+        for (i, col) in columns.iter().enumerate(){
+            let width = col.iter().map(|f| f.map(|f| f.to_string().len()).unwrap_or(1)).max().unwrap_or(1);
+            line.push_str(&"-".repeat(options.spacing));
+            let fret = col.get(s).copied().flatten().map(|f| f.to_string()).unwrap_or_default();
+            line.push_str(&fret);
+            line.push_str(&"-".repeat(width - fret.len()));
+            if options.per_bar.map(|n| (i + 1) % n == 0 && i + 1 < columns.len()).unwrap_or(false){
+                line.push_str(&"-".repeat(options.spacing));
+                line.push('|');
+            }
+        }
+        line.push_str(&"-".repeat(options.spacing));
+        line.push('|');
+        res.push_str(&line);
+        res.push('\n');
+    }
+    res
+}
+
+// Places the notes one by one, moving up the strings and staying around one position.
+pub fn scale_to_tab(scale: &Scale, options: &TabOptions) -> Option<String>{
+    let instrument = &options.instrument;
+    let first = *scale.0.first()?;
+    let fret_on = |s: usize, n: Note| Some(n - instrument.strings[s]).filter(|f| *f >= 0 && *f <= instrument.frets);
+    let (mut string, position) = match options.position{
+        Some(p) => ((0..instrument.strings.len()).find(|s| fret_on(*s, first).map(|f| f >= p - 1).unwrap_or(false))?, p),
+        None => {
+            let s = (0..instrument.strings.len()).rev().find(|s| fret_on(*s, first).map(|f| f <= 5).unwrap_or(false))
+                .or_else(|| (0..instrument.strings.len()).find(|s| fret_on(*s, first).is_some()))?;
+            (s, fret_on(s, first)?.max(1))
+        },
+    };
+    let mut columns = Vec::new();
+    for n in &scale.0{
+        // The first string from here in reach of the hand, else the closest fret on any string.
+        let in_box = (string..instrument.strings.len()).find(|s| fret_on(*s, *n).map(|f| f == 0 || (f >= position - 1 && f <= position + 3)).unwrap_or(false));
+        string = match in_box{
+            Some(s) => s,
+            None => (0..instrument.strings.len()).filter(|s| fret_on(*s, *n).is_some())
+                .min_by_key(|s| (fret_on(*s, *n).unwrap_or(0) - position).abs())?,
+        };
+        let mut col = vec![None; instrument.strings.len()];
+        col[string] = fret_on(string, *n);
+        columns.push(col);
+    }
+    Some(frets_to_tab(&columns, options))
+}
+
+pub fn arpeggio_to_tab(chord: &RootedChord, options: &TabOptions) -> Option<String>{
+    scale_to_tab(&chord.to_scale(), options)
+}
+
+// Chord voicings struck one after the other.
+pub fn shapes_to_tab(shapes: &[ChordShape], options: &TabOptions) -> String{
+    let columns = shapes.iter().map(|s| s.frets.iter().map(|f| f.map(|f| f + s.capo)).collect()).collect::<Vec<_>>();
+    frets_to_tab(&columns, options)
+}
+
+pub fn write_tab(path: &str, tab: &str) -> std::io::Result<()>{
+    fs::write(path, tab)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::fretted::{ find_chord_shapes, ShapeOptions };
+    #[test]
+    fn test_parse_tab(){
+        let src = "Some riff\n\
+            e|-----0-----|---------3--|\n\
+            B|---1---1---|-------0----|\n\
+            G|-0-------0-|-----0------|\n\
+            D|-2---------|---0--------|\n\
+            A|-3---------|-2----------|\n\
+            E|-----------|------------|\n\
+            \n\
+            e|--12--15b17--|\n\
+            B|-------------|\n\
+            G|-------------|\n\
+            D|-------------|\n\
+            A|-------------|\n\
+            E|-------------|\n";
+        let tab = parse_tab(src, None).unwrap();
+        assert_eq!(tab.instrument, Instrument::guitar());
+        let firsts = tab.notes.iter().take(3).map(|n| n.note.to_pc()).collect::<Vec<_>>();
+        assert_eq!(firsts, vec![C, E, G]);
+        assert_eq!(tab.chords()[0].1.0.len(), 3);
+        assert_eq!(tab.notes.len(), 14);
+        assert_eq!(tab.notes.last().map(|n| n.fret), Some(15));
+        let mut pcs = tab.pcs();
+        pcs.sort_by_key(|pc| pc.0);
+        assert_eq!(pcs, vec![B, C, D, E, G]);
+        let drop = parse_tab("Tuning: Drop D\nE|--0--|\nB|--0--|\nG|--0--|\nD|--0--|\nA|--0--|\nD|--0--|\n", None).unwrap();
+        assert_eq!(drop.notes[0].note.to_pc(), D);
+        let bass = parse_tab("G|--2--|\nD|--0--|\nA|--3--|\nE|--0--|\n", None).unwrap();
+        assert_eq!(bass.notes.iter().map(|n| n.note.to_pc()).collect::<Vec<_>>(), vec![E, C, D, A]);
+        assert_eq!(bass.instrument, Instrument::bass());
+        assert!(tuning_header("Song—Intro").is_none());
+        assert_eq!(parse_tuning("EADGbe").unwrap().strings, Instrument::guitar().strings);
+        assert_eq!(parse_tuning("EbAbDbGbBbEb").unwrap().strings, Instrument::guitar().strings.iter().map(|n| n - 1).collect::<Vec<_>>());
+        assert!(parse_tab("no tab here", None).is_none());
+    }
+
+    #[test]
+    fn test_write_tab(){
+        let scale = ionian::steps().to_scale(C.0 + 24);
+        let options = TabOptions::default();
+        let tab = scale_to_tab(&scale, &options).unwrap();
+        assert_eq!(tab.lines().nth(5), Some("A|--3--5-----------------|"));
+        let parsed = parse_tab(&tab, None).unwrap();
+        assert_eq!(parsed.notes.iter().map(|n| n.note).collect::<Vec<_>>(), scale.0);
+        let c = RootedChord::from_intervals(C.0 + 36, MAJOR);
+        let shapes = find_chord_shapes(&c, &Instrument::guitar(), &ShapeOptions::default());
+        let tab = shapes_to_tab(&shapes[..2], &options);
+        assert!(tab.lines().nth(5).unwrap().starts_with("A|--3--"));
+        assert_eq!(parse_tab(&tab, None).unwrap().chords().len(), 2);
+    }
+}
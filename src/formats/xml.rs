@@ -0,0 +1,211 @@
+/// A small xml reader, enough for documents like MusicXML: elements, attributes,
+/// text, comments, cdata and the five predefined entities plus character references.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub enum XmlNode{
+    Element(XmlElement),
+    Text(String),
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct XmlElement{
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlElement{
+    pub fn attr(&self, name: &str) -> Option<&str>{
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement>{
+        self.children.iter().filter_map(|c| match c{
+            XmlNode::Element(e) => Some(e),
+            XmlNode::Text(_) => None,
+        })
+    }
+
+    pub fn child(&self, name: &str) -> Option<&XmlElement>{
+        self.elements().find(|e| e.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement>{
+        self.elements().filter(move |e| e.name == name)
+    }
+
+    pub fn has_child(&self, name: &str) -> bool{
+        self.child(name).is_some()
+    }
+
+    // All text inside, trimmed.
+    pub fn text(&self) -> String{
+        let mut res = String::new();
+        for c in &self.children{
+            match c{
+                XmlNode::Text(t) => res.push_str(t),
+                XmlNode::Element(e) => res.push_str(&e.text()),
+            }
+        }
+        res.trim().to_string()
+    }
+
+    // Text of a direct child.
+    pub fn child_text(&self, name: &str) -> Option<String>{
+        self.child(name).map(|e| e.text())
+    }
+}
+
+fn unescape(text: &str) -> Option<String>{
+    let mut res = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('&'){
+        res.push_str(&rest[..i]);
+        let end = rest[i..].find(';')? + i;
+        let entity = &rest[i + 1..end];
+        let c = match entity{
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ if entity.starts_with("#x") => char::from_u32(u32::from_str_radix(&entity[2..], 16).ok()?)?,
+            _ if entity.starts_with('#') => char::from_u32(entity[1..].parse().ok()?)?,
+            _ => return None,
+        };
+        res.push(c);
+        rest = &rest[end + 1..];
+    }
+    res.push_str(rest);
+    Some(res)
+}
+
+struct Reader<'a>{
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a>{
+    fn rest(&self) -> &'a str{
+        &self.src[self.pos..]
+    }
+
+    fn skip_past(&mut self, pat: &str) -> Option<()>{
+        let i = self.rest().find(pat)?;
+        self.pos += i + pat.len();
+        Some(())
+    }
+
+    fn skip_ws(&mut self){
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> Option<String>{
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=').unwrap_or(rest.len());
+        if len == 0 { return None; }
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+
+    // Skips the prolog, comments, processing instructions and the doctype.
+    fn skip_misc(&mut self) -> Option<()>{
+        loop{
+            self.skip_ws();
+            let rest = self.rest();
+            if rest.starts_with("<?") { self.skip_past("?>")?; }
+            else if rest.starts_with("<!--") { self.skip_past("-->")?; }
+            else if rest.starts_with("<!DOCTYPE"){
+                // The internal subset can hold '>' inside brackets.
+                let close = rest.find('>')?;
+                match rest.find('['){
+                    Some(open) if open < close => self.skip_past("]>")?,
+                    _ => self.skip_past(">")?,
+                }
+            }
+            else { return Some(()); }
+        }
+    }
+
+    fn element(&mut self) -> Option<XmlElement>{
+        if !self.rest().starts_with('<') { return None; }
+        self.pos += 1;
+        let mut el = XmlElement{ name: self.name()?, ..Default::default() };
+        loop{
+            self.skip_ws();
+            let rest = self.rest();
+            if rest.starts_with("/>"){
+                self.pos += 2;
+                return Some(el);
+            }
+            if rest.starts_with('>'){
+                self.pos += 1;
+                break;
+            }
+            let name = self.name()?;
+            self.skip_ws();
+            if !self.rest().starts_with('=') { return None; }
+            self.pos += 1;
+            self.skip_ws();
+            let quote = self.rest().chars().next()?;
+            if quote != '"' && quote != '\'' { return None; }
+            self.pos += 1;
+            let len = self.rest().find(quote)?;
+            let value = unescape(&self.rest()[..len])?;
+            self.pos += len + 1;
+            el.attributes.push((name, value));
+        }
+        loop{
+            let rest = self.rest();
+            if rest.is_empty() { return None; }
+            if rest.starts_with("</"){
+                self.pos += 2;
+                if self.name()? != el.name { return None; }
+                self.skip_ws();
+                if !self.rest().starts_with('>') { return None; }
+                self.pos += 1;
+                return Some(el);
+            } else if rest.starts_with("<!--"){
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA["){
+                let end = rest.find("]]>")?;
+                el.children.push(XmlNode::Text(rest[9..end].to_string()));
+                self.pos += end + 3;
+            } else if rest.starts_with("<?"){
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<'){
+                el.children.push(XmlNode::Element(self.element()?));
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                el.children.push(XmlNode::Text(unescape(&rest[..len])?));
+                self.pos += len;
+            }
+        }
+    }
+}
+
+// The root element, None when the document is not well formed.
+pub fn parse_xml(src: &str) -> Option<XmlElement>{
+    let mut reader = Reader{ src: src.trim_start_matches('\u{feff}'), pos: 0 };
+    reader.skip_misc()?;
+    let root = reader.element()?;
+    reader.skip_misc()?;
+    if reader.rest().is_empty() { Some(root) } else { None }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_parse_xml(){
+        let src = "<?xml version=\"1.0\"?>\n<!DOCTYPE a PUBLIC \"x\" \"y\">\n<!-- c -->\n\
+            <a x='1' y=\"a &amp; b\"><b>t&lt;1&#65;</b><c/><![CDATA[<raw>]]></a>";
+        let root = parse_xml(src).unwrap();
+        assert_eq!(root.name, "a");
+        assert_eq!(root.attr("y"), Some("a & b"));
+        assert_eq!(root.child_text("b"), Some("t<1A".to_string()));
+        assert!(root.has_child("c"));
+        assert_eq!(root.text(), "t<1A<raw>");
+        assert_eq!(parse_xml("<a><b></a>"), None);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::symbol::ascii_name;
+use super::instrument::*;
+use crate::utils::centered;
+
+// Semitones above the root of the unaltered degrees, as in a major scale.
+const MAJOR_DEGREES: [Note; 7] = [0, 2, 4, 5, 7, 9, 11];
+// Frets with an inlay, the double ones with two dots.
+pub const INLAYS: [Note; 10] = [3, 5, 7, 9, 12, 15, 17, 19, 21, 24];
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum FretLabels{
+    // The root stands out, the other tones get a dot.
+    Dots,
+    Degrees,
+    Names,
+}
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct FretboardOptions{
+    pub first: Note,
+    pub last: Note,
+    pub labels: FretLabels,
+    // Box drawing and ♭♯ or plain ascii.
+    pub unicode: bool,
+}
+
+impl Default for FretboardOptions{
+    fn default() -> Self{
+        Self{ first: 0, last: 12, labels: FretLabels::Degrees, unicode: true }
+    }
+}
+
+/// A tone to show on the neck with its spelling and its degree counted from the root.
+#[derive(Clone,Copy,Debug)]
+pub struct FretMark{
+    pub pc: PC,
+    pub name: EnharmonicNote,
+    pub degree: u8,
+    pub accidental: i8,
+}
+
+impl FretMark{
+    fn new(note: Note, name: EnharmonicNote, root: EnharmonicNote, above_octave: bool) -> Self{
+        let number = (name.letter() + 7 - root.letter()) % 7;
+        let semis = (note - root.to_note(0)).rem_euclid(OCTAVE);
+        let accidental = (semis - MAJOR_DEGREES[number as usize] + 6).rem_euclid(OCTAVE) - 6;
+        let degree = number + 1 + if above_octave && number > 0 { 7 } else { 0 };
+        Self{ pc: note.to_pc(), name, degree, accidental: accidental as i8 }
+    }
+
+    pub fn is_root(&self) -> bool{
+        self.degree == 1 && self.accidental == 0
+    }
+
+    pub fn label(&self, labels: FretLabels, unicode: bool) -> String{
+        match labels{
+            FretLabels::Dots if self.is_root() => String::from(if unicode { "◆" } else { "R" }),
+            FretLabels::Dots => String::from(if unicode { "●" } else { "o" }),
+            FretLabels::Degrees if self.is_root() => String::from("R"),
+            FretLabels::Degrees => {
+                let (flat, sharp) = if unicode { ('♭', '♯') } else { ('b', '#') };
+                let acc = if self.accidental < 0 { flat } else { sharp };
+                let mut res = (0..self.accidental.abs()).map(|_| acc).collect::<String>();
+                res.push_str(&self.degree.to_string());
+                res
+            },
+            FretLabels::Names if unicode => self.name.to_string_name(),
+            FretLabels::Names => ascii_name(self.name),
+        }
+    }
+}
+
+// The tones of a scale spelled from its first note, or from the given spelling of it.
+pub fn scale_marks(scale: &Scale, tonic: Option<EnharmonicNote>) -> Vec<FretMark>{
+    if scale.0.is_empty() { return Vec::new(); }
+    let mut names = scale.clone().into_enharmonic_notes_with_start(tonic);
+    if names.len() != scale.0.len(){
+        names = scale.0.iter().map(|n| n.to_enharmonic_note().expect("scale_marks: should not be None")).collect();
+        if let Some(t) = tonic { names[0] = t; }
+    }
+    let root = names[0];
+    scale.0.iter().zip(names).map(|(n, en)| FretMark::new(*n, en, root, false)).collect()
+}
+
+pub fn chord_marks(chord: &RootedChord, root: Option<EnharmonicNote>) -> Vec<FretMark>{
+    let names = chord.spelled(root);
+    let root = names[0];
+    let mut res = vec![FretMark::new(chord.root, root, root, false)];
+    for (int, en) in chord.chord.0.iter().zip(names.into_iter().skip(1)){
+        res.push(FretMark::new(chord.root + int, en, root, *int >= OCTAVE));
+    }
+    res
+}
+
+// The neck with the highest string on top as in tablature, frets numbered above and inlays below.
+pub fn fretboard_string(instrument: &Instrument, marks: &[FretMark], options: &FretboardOptions) -> String{
+    let (fill, sep, nut) = if options.unicode { ('─', "│", "‖") } else { ('-', "|", "||") };
+    let labels = |pc: PC| marks.iter().find(|m| m.pc == pc).map(|m| m.label(options.labels, options.unicode));
+    let width = marks.iter().map(|m| m.label(options.labels, options.unicode).chars().count()).max().unwrap_or(1).max(1) + 2;
+    let name = |n: Note| if options.unicode { n.to_pc().to_string_name() } else { ascii_name(n.to_enharmonic_note().expect("fretboard_string: should not be None")) };
+    let name_width = instrument.strings.iter().map(|n| name(*n).chars().count()).max().unwrap_or(1);
+    let first = options.first.max(0);
+    let last = options.last.max(first);
+    let frets = if first == 0 { 1..=last } else { first..=last };
+    let mut res = String::new();
+    // Fret numbers, the open strings at the nut.
+    res.push_str(&" ".repeat(name_width + 1));
+    if first == 0 { res.push_str(&centered("0", width, ' ')); res.push_str(&" ".repeat(nut.chars().count())); }
+    else { res.push(' '); }
+    for fret in frets.clone(){
+        res.push_str(&centered(&fret.to_string(), width, ' '));
+        res.push(' ');
+    }
+    res.push('\n');
+    for (i, open) in instrument.strings.iter().enumerate().rev(){
+        let n = name(*open);
+        res.push_str(&n);
+        res.push_str(&" ".repeat(name_width - n.chars().count() + 1));
+        if first == 0{
+            res.push_str(&centered(&labels(open.to_pc()).unwrap_or_default(), width, ' '));
+            res.push_str(nut);
+        } else {
+            res.push_str(sep);
+        }
+        for fret in frets.clone(){
+            let label = labels(instrument.note_at(i, fret).to_pc()).unwrap_or_default();
+            res.push_str(&centered(&label, width, fill));
+            res.push_str(sep);
+        }
+        res.push('\n');
+    }
+    res.push_str(&" ".repeat(name_width + 1));
+    res.push_str(&" ".repeat(if first == 0 { width + nut.chars().count() } else { 1 }));
+    let dot = if options.unicode { "•" } else { "*" };
+    for fret in frets{
+        let inlay = match fret{
+            12 | 24 => dot.repeat(2),
+            _ if INLAYS.contains(&fret) => dot.to_string(),
+            _ => String::new(),
+        };
+        res.push_str(&centered(&inlay, width, ' '));
+        res.push(' ');
+    }
+    res.lines().map(|l| format!("{}\n", l.trim_end())).collect()
+}
+
+pub fn print_fretboard(instrument: &Instrument, marks: &[FretMark], options: &FretboardOptions){
+    print!("{}", fretboard_string(instrument, marks, options));
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::melodic_minor;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_fretboard(){
+        let c7 = RootedChord::from_intervals(C.0 + 36, DOMINANT_SEVENTH);
+        let marks = chord_marks(&c7, None);
+        let labels = marks.iter().map(|m| m.label(FretLabels::Degrees, false)).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["R", "3", "5", "b7"]);
+        let options = FretboardOptions{ last: 3, labels: FretLabels::Names, unicode: false, ..Default::default() };
+        let diagram = fretboard_string(&Instrument::guitar(), &marks, &options);
+        let lines = diagram.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "   0     1    2    3");
+        assert_eq!(lines[1], "E  E  ||----|----|-G--|");
+        assert_eq!(lines[5], "A     ||-Bb-|----|-C--|");
+        assert_eq!(lines[7], "                   *");
+        // Lydian dominant from C: the fourth is sharp and the seventh flat.
+        let lydian_dominant = melodic_minor::steps().mode(3).to_scale(C.0 + 36);
+        let marks = scale_marks(&lydian_dominant, None);
+        let labels = marks.iter().map(|m| m.label(FretLabels::Degrees, true)).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["R", "2", "3", "♯4", "5", "6", "♭7"]);
+        let options = FretboardOptions{ first: 5, last: 8, labels: FretLabels::Dots, ..Default::default() };
+        let diagram = fretboard_string(&Instrument::ukulele(), &marks, &options);
+        assert_eq!(diagram.lines().nth(1), Some("A │─●─│───│─●─│───│"));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::theory::note::*;
+
+/// A fretted instrument: its open strings from the lowest up and how many frets it has.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Instrument{
+    pub name: String,
+    pub strings: Vec<Note>,
+    pub frets: Note,
+}
+
+impl Instrument{
+    // Open strings given as midi numbers, lowest first.
+    pub fn from_midi(name: &str, midi: &[Note], frets: Note) -> Self{
+        Self{ name: name.to_string(), strings: midi.iter().map(|m| m - 21).collect(), frets }
+    }
+
+    pub fn guitar() -> Self{
+        Self::from_midi("Guitar", &[40, 45, 50, 55, 59, 64], 22)
+    }
+
+    pub fn drop_d() -> Self{
+        Self::from_midi("Guitar, drop D", &[38, 45, 50, 55, 59, 64], 22)
+    }
+
+    pub fn dadgad() -> Self{
+        Self::from_midi("Guitar, DADGAD", &[38, 45, 50, 55, 57, 62], 22)
+    }
+
+    pub fn bass() -> Self{
+        Self::from_midi("Bass", &[28, 33, 38, 43], 20)
+    }
+
+    // Reentrant: the G string is above the C string.
+    pub fn ukulele() -> Self{
+        Self::from_midi("Ukulele", &[67, 60, 64, 69], 15)
+    }
+
+    pub fn mandolin() -> Self{
+        Self::from_midi("Mandolin", &[55, 62, 69, 76], 17)
+    }
+
+    pub fn all() -> Vec<Self>{
+        vec![Self::guitar(), Self::drop_d(), Self::dadgad(), Self::bass(), Self::ukulele(), Self::mandolin()]
+    }
+
+    // By name as on the command line: guitar, dropd, dadgad, bass, ukulele or mandolin.
+    // Other tunings can be given as notes from the lowest string up, like D,A,D,G,B,E,
+    // or with octaves for strings that aren't above the previous one, like G4,C4,E4,A4.
+    pub fn parse(s: &str) -> Option<Self>{
+        Some(match s.to_lowercase().replace([' ', '-', '_'], "").as_str(){
+            "guitar" | "standard" => Self::guitar(),
+            "dropd" => Self::drop_d(),
+            "dadgad" => Self::dadgad(),
+            "bass" => Self::bass(),
+            "ukulele" | "uke" => Self::ukulele(),
+            "mandolin" => Self::mandolin(),
+            _ => {
+                let mut strings: Vec<Note> = Vec::new();
+                for name in s.split(','){
+                    let name = name.trim();
+                    let digits = name.chars().rev().take_while(|c| c.is_ascii_digit()).count();
+                    let (name, octave) = name.split_at(name.len() - digits);
+                    let en = name.to_string().to_enharmonic_note()?;
+                    // Without an octave: above the previous string, the lowest one nearest to low E.
+                    let low = strings.last().map(|n| n + 1).unwrap_or(Self::guitar().strings[0] - 6);
+                    strings.push(match octave.parse::<Note>(){
+                        Ok(octave) => en.in_octave(octave),
+                        Err(_) => low + (en.to_note(0) - low).rem_euclid(12),
+                    });
+                }
+                Self{ name: s.to_string(), strings, frets: 22 }
+            },
+        })
+    }
+
+    // The note sounding on a string at a fret.
+    pub fn note_at(&self, string: usize, fret: Note) -> Note{
+        self.strings[string] + fret
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_instrument(){
+        let guitar = Instrument::guitar();
+        assert_eq!(guitar.strings[0].to_pc(), E);
+        assert_eq!(guitar.note_at(5, 5), A4);
+        assert_eq!(Instrument::parse("drop-d"), Some(Instrument::drop_d()));
+        assert_eq!(Instrument::parse("D,A,D,G,A,D").unwrap().strings, Instrument::dadgad().strings);
+        assert_eq!(Instrument::parse("banjo"), None);
+        assert_eq!(Instrument::parse("C,G,C,F,A,D").unwrap().strings, Instrument::from_midi("", &[36, 43, 48, 53, 57, 62], 22).strings);
+        assert_eq!(Instrument::parse("B,E,A,D,G,B,E").unwrap().strings[..2], [Instrument::guitar().strings[0] - 5, Instrument::guitar().strings[0]]);
+        assert_eq!(Instrument::parse("B1,E2").unwrap().strings, Instrument::parse("B,E").unwrap().strings);
+        assert_eq!(Instrument::parse("G4,C4,E4,A4").unwrap().strings, Instrument::ukulele().strings);
+        assert_eq!(Instrument::parse("E,A9x"), None);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod instrument;
+pub mod fretboard;
+pub mod shapes;
+
+pub use instrument::*;
+pub use fretboard::*;
+pub use shapes::*;
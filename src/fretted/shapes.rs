@@ -0,0 +1,183 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use super::instrument::*;
+use std::collections::HashSet;
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ShapeOptions{
+    // How many frets the fingers may cover, open strings not counted.
+    pub span: Note,
+    pub max_fret: Note,
+    pub capo: Note,
+    pub min_strings: usize,
+    // Chord tones, as intervals from the root, that may be left out.
+    pub omittable: Vec<Note>,
+    pub root_in_bass: bool,
+}
+
+impl Default for ShapeOptions{
+    fn default() -> Self{
+        Self{ span: 4, max_fret: 12, capo: 0, min_strings: 3, omittable: vec![PERFECT_FIFTH], root_in_bass: false }
+    }
+}
+
+/// What makes a shape hard or less complete, the lower the cost the better.
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct ShapeScore{
+    pub stretch: Note,
+    pub barre: bool,
+    pub fingers: usize,
+    pub muted: usize,
+    // Muted strings between sounding ones, hard to damp.
+    pub inner_muted: usize,
+    pub omitted: Vec<Note>,
+    // Interval of the bass note above the root.
+    pub bass: Note,
+    pub position: Note,
+}
+
+impl ShapeScore{
+    pub fn cost(&self) -> u32{
+        let stretch = if self.stretch > 2 { 4 * self.stretch - 6 } else { 2 * self.stretch };
+        stretch as u32
+            + if self.barre { 3 } else { 0 }
+            + self.fingers as u32
+            + 2 * self.muted as u32
+            + 3 * self.inner_muted as u32
+            + 2 * self.omitted.len() as u32
+            + if self.bass != 0 { 4 } else { 0 }
+            + self.position as u32 / 2
+    }
+}
+
+/// A fingering: a fret or None for a muted string, lowest string first, counted from the capo.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ChordShape{
+    pub frets: Vec<Option<Note>>,
+    pub capo: Note,
+    pub score: ShapeScore,
+}
+
+impl ChordShape{
+    // As chord charts write it: x32010, or x-10-12-12-11-x from fret 10 up.
+    pub fn notation(&self) -> String{
+        let strs = self.frets.iter().map(|f| f.map(|f| f.to_string()).unwrap_or_else(|| String::from("x"))).collect::<Vec<_>>();
+        if self.frets.iter().any(|f| f.unwrap_or(0) > 9) { strs.join("-") } else { strs.concat() }
+    }
+
+    // The sounding notes from low to high.
+    pub fn notes(&self, instrument: &Instrument) -> Vec<Note>{
+        self.frets.iter().enumerate().filter_map(|(i, f)| f.map(|f| instrument.note_at(i, f + self.capo))).collect()
+    }
+}
+
+impl std::fmt::Display for ChordShape{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{}", self.notation())?;
+        if self.capo > 0 { write!(f, " (capo {})", self.capo)?; }
+        Ok(())
+    }
+}
+
+// Scores a shape, None when it can not be played or misses chord tones.
+fn score_shape(frets: &[Option<Note>], chord: &RootedChord, instrument: &Instrument, options: &ShapeOptions) -> Option<ShapeScore>{
+    let sounding = frets.iter().enumerate().filter_map(|(i, f)| f.map(|f| (i, f))).collect::<Vec<_>>();
+    if sounding.len() < options.min_strings.min(frets.len()) { return None; }
+    let root = chord.root.to_pc();
+    let interval = |i: usize, f: Note| (instrument.note_at(i, f + options.capo).to_pc().0 - root.0).rem_euclid(OCTAVE);
+    let intervals = sounding.iter().map(|(i, f)| interval(*i, *f)).collect::<HashSet<_>>();
+    let mut omitted = Vec::new();
+    for int in std::iter::once(0).chain(chord.chord.0.iter().copied()){
+        let int = int.rem_euclid(OCTAVE);
+        if intervals.contains(&int) { continue; }
+        if !options.omittable.contains(&int) { return None; }
+        omitted.push(int);
+    }
+    let bass = sounding.first().map(|(i, f)| interval(*i, *f)).unwrap_or(0);
+    if options.root_in_bass && bass != 0 { return None; }
+    let fretted = sounding.iter().filter(|(_, f)| *f > 0).map(|(i, f)| (*i, *f)).collect::<Vec<_>>();
+    let low = fretted.iter().map(|(_, f)| *f).min().unwrap_or(0);
+    let high = fretted.iter().map(|(_, f)| *f).max().unwrap_or(0);
+    let (mut fingers, mut barre) = (fretted.len(), false);
+    if fingers > 4{
+        // The index finger lies across every string from the lowest one on that fret up.
+        let from = fretted.iter().find(|(_, f)| *f == low).map(|(i, _)| *i)?;
+        if sounding.iter().any(|(i, f)| *i > from && *f < low) { return None; }
+        fingers = 1 + fretted.iter().filter(|(_, f)| *f > low).count();
+        barre = true;
+        if fingers > 4 { return None; }
+    }
+    let first = sounding.first().map(|(i, _)| *i).unwrap_or(0);
+    let last = sounding.last().map(|(i, _)| *i).unwrap_or(0);
+    let muted = frets.len() - sounding.len();
+    let inner_muted = (first..=last).filter(|i| frets[*i].is_none()).count();
+    Some(ShapeScore{ stretch: high - low, barre, fingers, muted, inner_muted, omitted, bass, position: low })
+}
+
+// Every playable shape, the easiest and most complete first.
+pub fn find_chord_shapes(chord: &RootedChord, instrument: &Instrument, options: &ShapeOptions) -> Vec<ChordShape>{
+    let pcs = chord.to_scale().into_pcs();
+    let in_chord = |i: usize, f: Note| pcs.contains(&instrument.note_at(i, f + options.capo).to_pc());
+    let max_fret = (options.max_fret.min(instrument.frets) - options.capo).max(0);
+    let mut seen = HashSet::new();
+    let mut res = Vec::new();
+    for start in 1..=max_fret.max(1){
+        let window = start..(start + options.span).min(max_fret + 1);
+        // The options per string: muted, open or a fret in the window.
+        let choices = (0..instrument.strings.len()).map(|i|{
+            let mut c = vec![None];
+            if in_chord(i, 0) { c.push(Some(0)); }
+            c.extend(window.clone().filter(|f| in_chord(i, *f)).map(Some));
+            c
+        }).collect::<Vec<_>>();
+        let mut idx = vec![0; choices.len()];
+        loop{
+            let frets = idx.iter().zip(&choices).map(|(i, c)| c[*i]).collect::<Vec<_>>();
+            if !seen.contains(&frets){
+                if let Some(score) = score_shape(&frets, chord, instrument, options){
+                    res.push(ChordShape{ frets: frets.clone(), capo: options.capo, score });
+                }
+                seen.insert(frets);
+            }
+            // Next combination, like counting with a different base per string.
+            let mut s = 0;
+            while s < idx.len(){
+                idx[s] += 1;
+                if idx[s] < choices[s].len() { break; }
+                idx[s] = 0;
+                s += 1;
+            }
+            if s == idx.len() { break; }
+        }
+    }
+    res.sort_by_key(|s| (s.score.cost(), s.score.position));
+    res
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_chord_shapes(){
+        let guitar = Instrument::guitar();
+        let c = RootedChord::from_intervals(C.0 + 36, MAJOR);
+        let shapes = find_chord_shapes(&c, &guitar, &ShapeOptions::default());
+        assert_eq!(shapes[0].notation(), "x32010");
+        assert!(shapes.iter().any(|s| s.notation() == "x35553" && s.score.barre));
+        assert!(shapes.iter().all(|s| s.score.fingers <= 4));
+        let f = RootedChord::from_intervals(F.0 + 36, MAJOR);
+        let shapes = find_chord_shapes(&f, &guitar, &ShapeOptions{ root_in_bass: true, ..Default::default() });
+        assert!(shapes.iter().any(|s| s.notation() == "133211"));
+        assert!(shapes.iter().all(|s| s.score.bass == 0));
+        // With the capo on 5 an F is played as a C shape.
+        let shapes = find_chord_shapes(&f, &guitar, &ShapeOptions{ capo: 5, ..Default::default() });
+        assert_eq!(shapes[0].to_string(), "x32010 (capo 5)");
+        assert_eq!(shapes[0].notes(&guitar)[0].to_pc(), F);
+        let high = ChordShape{ frets: vec![None, Some(10), Some(12), Some(12), Some(11), None], capo: 0, score: ShapeScore::default() };
+        assert_eq!(high.notation(), "x-10-12-12-11-x");
+        let g7 = RootedChord::from_intervals(G.0 + 36, DOMINANT_SEVENTH);
+        let shapes = find_chord_shapes(&g7, &Instrument::ukulele(), &ShapeOptions::default());
+        assert_eq!(shapes[0].notation(), "0212");
+    }
+}
@@ -3,20 +3,19 @@ pub mod theory;
 pub mod utils;
 pub mod libr;
 pub mod query;
+pub mod formats;
+pub mod audio;
+pub mod fretted;
+pub mod render;
+pub mod analysis;
 
 use std::collections::{ HashSet, HashMap };
 use std::mem;
 use theory::*;
 use libr::*;
 use query::*;
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+use formats::{ Score, AbcTune };
+use render::{ StaffOptions, staff_string, scale_staff };
 
 pub fn step_chords_string(steps: &Steps, root: Note, styling: ChordStyling) -> String{
     let mut string = String::new();
@@ -27,8 +26,24 @@ pub fn step_chords_string(steps: &Steps, root: Note, styling: ChordStyling) -> S
     string
 }
 
+// Same as notes_analysis, but from pitchclasses
+pub fn pcs_analysis(pcs: &[PC], styling: ChordStyling) -> Vec<(String, String)>{
+    let input_string = pcs.iter().map(|pc| pc.to_string_name()).collect::<Vec<_>>().join(",");
+    notes_analysis(input_string, styling)
+}
+
+// Same as notes_analysis, but from frequencies in Hz
+pub fn frequencies_analysis(frequencies: &[f32], concert: ConcertPitch, styling: ChordStyling) -> Vec<(String, String)>{
+    pcs_analysis(&concert.pcs_from_frequencies(frequencies), styling)
+}
+
 // return (header,content)
 pub fn notes_analysis(input_string: String, styling: ChordStyling) -> Vec<(String, String)>{
+    notes_analysis_with(input_string, styling, None)
+}
+
+// Same as notes_analysis, with the input and every scale drawn on a staff as well
+pub fn notes_analysis_with(input_string: String, styling: ChordStyling, staff: Option<StaffOptions>) -> Vec<(String, String)>{
     // Remove duplicate notes
     let (ens, pcs, pcs_to_ens) = {
         let ens = input_string.into_enharmonic_notes();
@@ -51,14 +66,18 @@ pub fn notes_analysis(input_string: String, styling: ChordStyling) -> Vec<(Strin
     let spell_out = |scale: Scale| {
         if scale.is_empty() { return String::new(); }
         let tonic = scale.0[0];
-        let start = if let Some(x) = pcs_to_ens.get(&tonic.to_pc()){
-            Some(*x)
-        } else {
-            None
-        };
+        let start = pcs_to_ens.get(&tonic.to_pc()).copied();
         scale.into_enharmonic_notes_with_start(start).into_iter().map(|e| e.to_string_name()).collect::<Vec<String>>().intercalate(", ".to_string())
     };
 
+    // Scales are drawn from the octave of middle C
+    let staff_out = |scale: Scale| {
+        let Some(options) = staff else { return String::new(); };
+        if scale.is_empty() { return String::new(); }
+        let start = pcs_to_ens.get(&scale.0[0].to_pc()).copied();
+        scale_staff(&Scale(scale.0.iter().map(|n| n + 36).collect()), start, &options)
+    };
+
     let map_pc_to_en = |pc: PC| {
         if let Some(x) = pcs_to_ens.get(&pc){
             *x
@@ -87,11 +106,15 @@ pub fn notes_analysis(input_string: String, styling: ChordStyling) -> Vec<(Strin
     let ctonic = pcs[0];
     let rchord = RootedChord::from_scale(scale.clone());
     let mut included = HashSet::new();
-    let ens_string = ens.into_iter().map(|en| { let mut string = en.to_string_name(); string.push_str(", "); string }).collect::<String>();
+    let ens_string = ens.iter().map(|en| { let mut string = en.to_string_name(); string.push_str(", "); string }).collect::<String>();
     let mut string = format!("Your input: {}\n", ens_string);
     string.push_str(&format!("Numbered pitchclasses: {:?}\n", pcs.iter().map(|pc| pc.0).collect::<Vec<_>>()));
     string.push_str(&format!("Named pitchclasses: {:?}\n", pcs));
-    res.push(("Input".to_string(), mem::replace(&mut string, String::new())));
+    if let Some(options) = staff{
+        let stack = scale.0.iter().zip(&ens).map(|(n, en)| (n + 36, *en)).collect::<Vec<_>>();
+        string.push_str(&staff_string(&[stack], &options));
+    }
+    res.push(("Input".to_string(), mem::take(&mut string)));
     if scale.len() == 7{ // we have an heptatonic scale on our hands
         let mo = find_scale(&scale);
         string = if let Some(mo) = mo{
@@ -109,8 +132,9 @@ pub fn notes_analysis(input_string: String, styling: ChordStyling) -> Vec<(Strin
         string.push_str(&steps.to_relative(&ionian::steps()).unwrap().string_ionian_rel());
         string.push('\n');
         string.push_str(&step_chords_string(&steps, root, styling));
+        string.push_str(&staff_out(scale.clone()));
         string.push('\n');
-        res.push(("Heptatonic Scale".to_string(), mem::replace(&mut string, String::new())));
+        res.push(("Heptatonic Scale".to_string(), mem::take(&mut string)));
     }
     let inversions = {
         let mut inversions = rchord.all_inversions();
@@ -122,47 +146,144 @@ pub fn notes_analysis(input_string: String, styling: ChordStyling) -> Vec<(Strin
         .filter(|(s,_)| !s.contains('[') && !s.is_empty())
         .map(|(mut s,c)| { s.push_str(&format!(": {:?}", c.to_scale().into_pcs())); s })
         .for_each(|s| { string.push_str(&format!("{}\n", s)); });
-    res.push(("Inversions".to_string(), mem::replace(&mut string, String::new())));
+    res.push(("Inversions".to_string(), mem::take(&mut string)));
     rchord
         .clone().into_subseq_chords()
         .into_iter().map(|c| (c.as_string(true, styling),c))
         .filter(|(s,_)| !s.contains('[') /* && !s.contains('(') */ && !s.is_empty())
         .map(|(mut s,c)| { s.push_str(&format!(": {:?}", c.to_scale().into_pcs())); s })
         .for_each(|s| { string.push_str(&format!("{}\n", s)); });
-    res.push(("SubChords".to_string(), mem::replace(&mut string, String::new())));
+    res.push(("SubChords".to_string(), mem::take(&mut string)));
     let ctwts = rchord.to_chordtone_wholetone_scale();
     let mo = find_scale(&ctwts);
     if let Some(m) = mo{
         included.insert((ctonic, m.steps.clone()));
+        let staffed = staff_out(m.steps.to_scale(root));
         let spelled_out = spell_out(m.steps.to_scale(root));
         string.push_str(&mode_format(map_pc_to_en(ctonic), m, spelled_out));
+        string.push_str(&staffed);
     }
     // if !ctwts.is_empty() {
     //     let ctwts = ctwts.into_steps();
     //     string.push_str(&print_step_chords(&ctwts, root, styling));
     // }
-    res.push(("Chordtone Wholetone Scale".to_string(), mem::replace(&mut string, String::new())));
+    res.push(("Chordtone Wholetone Scale".to_string(), mem::take(&mut string)));
     for modeobj in find_chordscales(&pcs){
         if included.contains(&(ctonic, modeobj.steps.clone())) { continue; }
         included.insert((ctonic, modeobj.steps.clone()));
+        let staffed = staff_out(modeobj.steps.to_scale(root));
         let spelled_out = spell_out(modeobj.steps.to_scale(root));
         string.push_str(&mode_format(map_pc_to_en(ctonic), modeobj, spelled_out));
+        string.push_str(&staffed);
     }
-    res.push(("Strict Chordscales".to_string(), mem::replace(&mut string, String::new())));
+    res.push(("Strict Chordscales".to_string(), mem::take(&mut string)));
     for (tonic,modeobj) in find_scale_superseq(&scale){
         if included.contains(&(tonic, modeobj.steps.clone())) { continue; }
         included.insert((tonic, modeobj.steps.clone()));
+        let staffed = staff_out(modeobj.steps.to_scale(tonic.to_note(0)));
         let spelled_out = spell_out(modeobj.steps.to_scale(tonic.to_note(0)));
         string.push_str(&mode_format(map_pc_to_en(tonic), modeobj, spelled_out));
+        string.push_str(&staffed);
     }
-    res.push(("Supersequences".to_string(), mem::replace(&mut string, String::new())));
+    res.push(("Supersequences".to_string(), mem::take(&mut string)));
     for (tonic,modeobj) in find_scale_superset(pcs, false){
         if included.contains(&(tonic, modeobj.steps.clone())) { continue; }
         included.insert((tonic, modeobj.steps.clone()));
+        let staffed = staff_out(modeobj.steps.to_scale(tonic.to_note(0)));
         let spelled_out = spell_out(modeobj.steps.to_scale(tonic.to_note(0)));
         string.push_str(&mode_format(map_pc_to_en(tonic), modeobj, spelled_out));
+        string.push_str(&staffed);
     }
-    res.push(("Supersets".to_string(), mem::replace(&mut string, String::new())));
+    res.push(("Supersets".to_string(), mem::take(&mut string)));
     res
 }
 
+// Chord by chord analysis of a score: the symbols written in it, a chord per beat and the scales per passage
+pub fn score_analysis(score: &Score, styling: ChordStyling) -> Vec<(String, String)>{
+    let mut res = Vec::new();
+    let mut string = String::new();
+    let number = |m: usize| score.measures[m].number.clone();
+    if !score.title.is_empty(){
+        string.push_str(&format!("Title: {}\n", score.title));
+    }
+    string.push_str(&format!("Parts: {}\n", score.parts.join(", ")));
+    string.push_str(&format!("Measures: {}\n", score.measures.len()));
+    for key in &score.keys{
+        string.push_str(&format!("Key from measure {}: {}\n", number(key.measure), key.as_string()));
+    }
+    res.push(("Score".to_string(), mem::take(&mut string)));
+    if !score.harmonies.is_empty(){
+        let mut last = None;
+        for h in &score.harmonies{
+            if last != Some(h.measure){
+                if last.is_some() { string.push('\n'); }
+                string.push_str(&format!("{}: ", number(h.measure)));
+                last = Some(h.measure);
+            } else {
+                string.push_str(", ");
+            }
+            string.push_str(&h.as_string(styling));
+        }
+        string.push('\n');
+        res.push(("Chord Symbols".to_string(), mem::take(&mut string)));
+    }
+    for s in score.sonorities(styling){
+        let notes = s.spelled.iter().map(|en| en.to_string_name()).collect::<Vec<_>>().join(", ");
+        string.push_str(&format!("{}.{} {}: {}\n", number(s.measure), s.beat + 1, s.as_string(styling), notes));
+    }
+    res.push(("Sonorities".to_string(), mem::take(&mut string)));
+    let namer = HeptatonicScaleNamer::new();
+    for p in score.passages(0){
+        let fifths = p.key.as_ref().map(|k| k.fifths).unwrap_or(0);
+        let spell = |pc: PC| spell_in_key(pc.0, &[], fifths).to_string_name();
+        string.push_str(&format!("Measures {}-{}", number(p.first_measure), number(p.last_measure)));
+        if let Some(key) = &p.key{
+            string.push_str(&format!(" in {}", key.as_string()));
+        }
+        string.push_str(&format!(": {}\n", p.pcs.iter().map(|pc| spell(*pc)).collect::<Vec<_>>().join(", ")));
+        for (tonic, mo) in p.scales.into_iter().take(5){
+            let mode_name = if mo.mode_name.is_empty() { namer.name(&mo.steps) } else { mo.mode_name.clone() };
+            string.push_str(&format!("  {} {}, {}ᵉ mode of {}\n", spell(tonic), mode_name, mo.mode_nr + 1, mo.fam_name));
+        }
+    }
+    res.push(("Passages".to_string(), mem::take(&mut string)));
+    res
+}
+
+// Modal analysis of a tune: its key, chord symbols and the modes that hold all of its notes
+pub fn tune_analysis(tune: &AbcTune) -> Vec<(String, String)>{
+    let mut res = Vec::new();
+    let mut string = String::new();
+    let spellings = tune.notes.iter().fold(HashMap::new(), |mut map, n|{ map.entry(n.note.to_pc()).or_insert(n.spelling); map });
+    let spell = |pc: PC| spellings.get(&pc).copied().unwrap_or_else(|| pc.to_note(0).to_enharmonic_note().expect("should not be None")).to_string_name();
+    string.push_str(&format!("{}: {}\n", tune.index, tune.title));
+    if let Some((n, d)) = tune.meter{
+        string.push_str(&format!("Meter: {}/{}\n", n, d));
+    }
+    if let Some(key) = &tune.key{
+        string.push_str(&format!("Key: {}\n", key.as_string()));
+    }
+    res.push(("Tune".to_string(), mem::take(&mut string)));
+    if !tune.chords.is_empty(){
+        string.push_str(&tune.chords.iter().map(|c| c.symbol.as_lead_sheet()).collect::<Vec<_>>().join(" "));
+        string.push('\n');
+        res.push(("Chord Symbols".to_string(), mem::take(&mut string)));
+    }
+    let pcs = tune.pcs();
+    string.push_str(&format!("Notes: {}\n", pcs.iter().map(|pc| spell(*pc)).collect::<Vec<_>>().join(", ")));
+    let namer = HeptatonicScaleNamer::new();
+    for (tonic, mo) in tune.modes().into_iter().take(5){
+        let mode_name = if mo.mode_name.is_empty() { namer.name(&mo.steps) } else { mo.mode_name.clone() };
+        string.push_str(&format!("{} {}, {}ᵉ mode of {}\n", spell(tonic), mode_name, mo.mode_nr + 1, mo.fam_name));
+    }
+    res.push(("Modes".to_string(), mem::take(&mut string)));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn it_works() {
+        assert_eq!(2 + 2, 4);
+    }
+}
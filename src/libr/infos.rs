@@ -2,6 +2,7 @@ use crate::libr::scales::{get_all_scale_objs,ionian,HeptatonicScaleNamer};
 use crate::theory::scale::RelativeTrait;
 use crate::theory::note::{ToRelative};
 use crate::theory::chord::{strs_scale_chords_roman,ChordStyling};
+use crate::theory::note::Steps;
 
 pub trait Intercalatable{
     type InterType;
@@ -41,7 +42,7 @@ pub fn format_splitted(strings: &[String], split: &str, end: &str) -> String{
         let x = format!("{}{}", s, split);
         string.push_str(&x);
     }
-    string.push_str(&end.to_string());
+    string.push_str(end);
     string
 }
 
@@ -87,6 +88,11 @@ pub fn print_to_grid_auto(strings: &[String], width: usize, padding: usize){
 }
 
 pub fn print_scales(styling: ChordStyling){
+    print_scales_with(styling, |_| String::new());
+}
+
+// Same as print_scales, with what extra gives for the steps of every mode printed below it
+pub fn print_scales_with(styling: ChordStyling, extra: impl Fn(&Steps) -> String){
     let namer = HeptatonicScaleNamer::new();
     let objs = get_all_scale_objs();
     let empty = String::from("");
@@ -108,6 +114,7 @@ pub fn print_scales(styling: ChordStyling){
             print_splitted(&c3, ", ", "\n");
             print!("\t");
             print_splitted(&c4, ", ", "\n");
+            print!("{}", extra(&mode.steps));
         }
     }
 }
@@ -1,6 +1,7 @@
 use crate::theory::scale::{Mode};
 use crate::theory::note::{Steps};
 use crate::theory::scale::ModeIteratorSpawner;
+use crate::theory::tuning::Edo;
 
 pub struct ScaleObj{
     pub steps: Steps,
@@ -27,6 +28,15 @@ impl ScaleObj{
         }
     }
 
+    // The same family with every step mapped onto the given edo.
+    pub fn to_edo(&self, edo: Edo) -> ScaleObj{
+        ScaleObj{
+            steps: edo.map_steps(&self.steps),
+            fam_name: self.fam_name.clone(),
+            modes: self.modes.clone(),
+        }
+    }
+
     pub fn get_modes(self) -> Vec<ModeObj>{
         let fname = self.family_name();
         let mut res = Vec::new();
@@ -53,6 +63,10 @@ pub fn get_all_scale_objs() -> Vec<ScaleObj>{
     enigmatic_major::obj(), enigmatic_minor::obj()]
 }
 
+pub fn get_all_scale_objs_edo(edo: Edo) -> Vec<ScaleObj>{
+    get_all_scale_objs().iter().map(|sc| sc.to_edo(edo)).collect::<Vec<_>>()
+}
+
 pub struct ModeObj{
     pub steps: Steps,
     pub fam_name: String,
@@ -78,10 +92,7 @@ macro_rules! DefScale{
             }
 
             pub fn obj() -> ScaleObj{
-                let mut modes = Vec::new();
-                $(
-                    modes.push(String::from($mode));
-                )*
+                let modes = vec![$( String::from($mode) ),*];
                 ScaleObj{
                     steps: steps(),
                     fam_name: String::from($name),
@@ -1,12 +1,17 @@
-use crate::theory::note::{Steps,Scale,Relative,RelativeNote,IntoPCs,PCs,PC,NoteSequence,ToScale,IntoScale,ToRelative,ToSteps,ToNote};
-use crate::theory::scale::{notes_to_octave_scale,StepsTrait,ModeIteratorSpawner};
+use crate::theory::note::{Steps,Scale,Relative,RelativeNote,PCs,PC,NoteSequence,ToScale,IntoScale,ToRelative};
+use crate::theory::scale::{StepsTrait,ModeIteratorSpawner};
 use crate::theory::interval::{SEMI};
+use crate::theory::tuning::{Edo,EDO12};
 use fnrs::Sequence;
-use crate::libr::scales::{get_all_scale_objs, ModeObj,ionian};
+use crate::libr::scales::{get_all_scale_objs,get_all_scale_objs_edo,ModeObj,HeptatonicScaleNamer,ionian};
 
 pub fn find_scale(scale: &Scale) -> Option<ModeObj>{
-    let steps = Steps(notes_to_octave_scale(scale));
-    let scales = get_all_scale_objs();
+    find_scale_edo(scale, EDO12)
+}
+
+pub fn find_scale_edo(scale: &Scale, edo: Edo) -> Option<ModeObj>{
+    let steps = edo.octave_steps(scale);
+    let scales = get_all_scale_objs_edo(edo);
     for sc in scales{
         if let Some((mode,msteps)) = sc.steps.clone().mode_nr_of_this(&steps){
             return Option::Some(ModeObj{
@@ -20,8 +25,26 @@ pub fn find_scale(scale: &Scale) -> Option<ModeObj>{
     Option::None
 }
 
+// A mode from the library by its name, like Lydian Dominant or Dorian, in any case.
+pub fn find_mode_by_name(name: &str) -> Option<ModeObj>{
+    let name = name.trim().to_lowercase();
+    get_all_scale_objs().into_iter().flat_map(|sc| sc.get_modes()).find(|m| m.mode_name.to_lowercase() == name)
+}
+
+// The name of the mode with these steps in the library, or a made up one.
+pub fn find_mode_name_by_steps(steps: &Steps, namer: &HeptatonicScaleNamer) -> String{
+    get_all_scale_objs().into_iter().flat_map(|sc| sc.get_modes())
+        .find(|m| &m.steps == steps && !m.mode_name.is_empty())
+        .map(|m| m.mode_name)
+        .unwrap_or_else(|| namer.name(steps))
+}
+
 pub fn find_steps_superseq(scale: &Steps) -> Vec<ModeObj>{
-    let scales = get_all_scale_objs();
+    find_steps_superseq_edo(scale, EDO12)
+}
+
+pub fn find_steps_superseq_edo(scale: &Steps, edo: Edo) -> Vec<ModeObj>{
+    let scales = get_all_scale_objs_edo(edo);
     let mut res = Vec::new();
     for sc in scales{
         for (i,mode) in sc.steps.clone().mode_iter().enumerate(){
@@ -41,16 +64,20 @@ pub fn find_steps_superseq(scale: &Steps) -> Vec<ModeObj>{
 }
 
 pub fn find_scale_superseq(scale: &Scale) -> Vec<(PC,ModeObj)>{
-    let steps = scale.to_steps();
-    let pcs = scale.clone().into_pcs();
-    let scales = get_all_scale_objs();
+    find_scale_superseq_edo(scale, EDO12)
+}
+
+pub fn find_scale_superseq_edo(scale: &Scale, edo: Edo) -> Vec<(PC,ModeObj)>{
+    let steps = edo.scale_to_steps(scale);
+    let pcs = edo.pcs(scale);
+    let scales = get_all_scale_objs_edo(edo);
     let mut res = Vec::new();
     for sc in scales{
         for (i,mode) in sc.steps.clone().mode_iter().enumerate(){
             if !mode.0.has_seq(&steps.0) { continue; }
-            for j in 0..12{
+            for j in 0..edo.divisions(){
                 let tonic = j * SEMI;
-                let modescale = mode.clone().into_scale(tonic).into_pcs();
+                let modescale = edo.pcs(&mode.clone().into_scale(tonic));
                 if modescale.has_seq(&pcs){
                     res.push((modescale[0],
                         ModeObj{
@@ -70,15 +97,19 @@ pub fn find_scale_superseq(scale: &Scale) -> Vec<(PC,ModeObj)>{
 // When same_tonic == true, it only gives scales that have the same note as the
 // first note in the set(ordered set shortly) as the tonic.
 pub fn find_scale_superset(scale: PCs, same_tonic: bool) -> Vec<(PC,ModeObj)>{
-    let target_tonic = scale[0].to_note(0);
-    let scales = get_all_scale_objs();
+    find_scale_superset_edo(scale, same_tonic, EDO12)
+}
+
+pub fn find_scale_superset_edo(scale: PCs, same_tonic: bool, edo: Edo) -> Vec<(PC,ModeObj)>{
+    let target_tonic = edo.note(scale[0], 0);
+    let scales = get_all_scale_objs_edo(edo);
     let mut res = Vec::new();
     for sc in scales{
         for (i,mode) in sc.steps.clone().mode_iter().enumerate(){
-            for tonic in 0..12{
+            for tonic in 0..edo.divisions(){
                 let tonic_note = tonic * SEMI;
                 if same_tonic && tonic_note != target_tonic { continue; }
-                let notes = mode.clone().into_scale(tonic_note).into_pcs();
+                let notes = edo.pcs(&mode.clone().into_scale(tonic_note));
                 let mut has = true;
                 'outer: for a in &scale{
                     for b in &notes{
@@ -103,13 +134,17 @@ pub fn find_scale_superset(scale: PCs, same_tonic: bool) -> Vec<(PC,ModeObj)>{
 }
 // Finds all the scales where the input is the I chord
 pub fn find_chordscales(pcs: &[PC]) -> Vec<ModeObj>{
+    find_chordscales_edo(pcs, EDO12)
+}
+
+pub fn find_chordscales_edo(pcs: &[PC], edo: Edo) -> Vec<ModeObj>{
     let mut res = Vec::new();
     if pcs.is_empty() { return res; }
-    let tonic = pcs[0].to_note(0);
-    let scales = get_all_scale_objs();
+    let tonic = edo.note(pcs[0], 0);
+    let scales = get_all_scale_objs_edo(edo);
     for sc in scales{
         'outer: for (i,mode) in sc.steps.clone().mode_iter().enumerate(){
-            let modescale = mode.to_scale(tonic).into_pcs();
+            let modescale = edo.pcs(&mode.to_scale(tonic));
             for j in 0..pcs.len(){
                 if j * 2 > modescale.len() - 1 {
                     continue 'outer;
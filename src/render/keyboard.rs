@@ -0,0 +1,248 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::fretted::{ FretMark, FretLabels, scale_marks, chord_marks };
+use crate::utils::centered;
+use super::svg::is_black;
+
+// Columns per white key: its left border and four inside.
+const KEY_WIDTH: usize = 5;
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum KeyLabels{
+    Names,
+    Degrees,
+    Fingers,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Hand{
+    Left,
+    Right,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct KeyboardOptions{
+    // The lowest and highest key, None fits the keyboard from a C to a B around the marked keys.
+    pub range: Option<(Note, Note)>,
+    pub labels: KeyLabels,
+    // Keys below this one are for the left hand.
+    pub split: Option<Note>,
+    pub unicode: bool,
+}
+
+impl Default for KeyboardOptions{
+    fn default() -> Self{
+        Self{ range: None, labels: KeyLabels::Names, split: None, unicode: true }
+    }
+}
+
+impl KeyboardOptions{
+    pub fn hand(&self, note: Note) -> Hand{
+        match self.split{
+            Some(split) if note < split => Hand::Left,
+            _ => Hand::Right,
+        }
+    }
+}
+
+/// A key to press, with its spelling and degree and the finger to press it with.
+#[derive(Clone,Copy,Debug)]
+pub struct KeyMark{
+    pub note: Note,
+    pub mark: FretMark,
+    pub finger: u8,
+    pub hand: Hand,
+}
+
+impl KeyMark{
+    pub fn label(&self, labels: KeyLabels, unicode: bool) -> String{
+        match labels{
+            KeyLabels::Names => self.mark.label(FretLabels::Names, unicode),
+            KeyLabels::Degrees => self.mark.label(FretLabels::Degrees, unicode),
+            KeyLabels::Fingers => self.finger.to_string(),
+        }
+    }
+}
+
+// Right hand fingers for notes going up, a simple rule of thumb: the thumb takes the white key
+// after a black one, or every fourth white key when there are no black ones, and crosses under
+// when the fourth finger is used up. The left hand is the same going down.
+pub fn scale_fingering(notes: &[Note], hand: Hand) -> Vec<u8>{
+    if hand == Hand::Left{
+        let rev = notes.iter().rev().copied().collect::<Vec<_>>();
+        return scale_fingering(&rev, Hand::Right).into_iter().rev().collect();
+    }
+    let all_white = notes.iter().all(|n| !is_black(*n));
+    let thumb = |i: usize| !is_black(notes[i]) && if all_white { matches!(i % 7, 0 | 3) } else { i == 0 || is_black(notes[i - 1]) };
+    let mut res: Vec<u8> = Vec::new();
+    for i in 0..notes.len(){
+        let finger = match res.last(){
+            // The top note ends the run with the next finger.
+            Some(prev) if i == notes.len() - 1 && *prev < 5 => prev + 1,
+            _ if thumb(i) => 1,
+            // Starting on a black key, so that the run reaches the first thumb with the fourth finger at most.
+            None => (1..notes.len()).find(|j| thumb(*j)).map(|j| (5 - j as u8).clamp(2, 4)).unwrap_or(2),
+            Some(prev) if *prev >= 4 => 1,
+            Some(prev) => prev + 1,
+        };
+        res.push(finger);
+    }
+    res
+}
+
+// Fingers for notes played together, the outer ones with thumb and little finger.
+pub fn chord_fingering(notes: &[Note], hand: Hand) -> Vec<u8>{
+    let res = match notes.len(){
+        0 => Vec::new(),
+        1 => vec![1],
+        2 => vec![1, 5],
+        3 => vec![1, 3, 5],
+        4 => vec![1, 2, 3, 5],
+        n => (1..=n).map(|f| f.min(5) as u8).collect(),
+    };
+    if hand == Hand::Left { res.into_iter().rev().collect() } else { res }
+}
+
+// Fingers for every note, each hand on its own part of the keys.
+fn fingers(notes: &[Note], options: &KeyboardOptions, fingering: fn(&[Note], Hand) -> Vec<u8>) -> Vec<(u8, Hand)>{
+    let mut res = Vec::new();
+    for hand in [Hand::Left, Hand::Right]{
+        let part = notes.iter().copied().filter(|n| options.hand(*n) == hand).collect::<Vec<_>>();
+        res.extend(fingering(&part, hand).into_iter().map(|f| (f, hand)));
+    }
+    res
+}
+
+fn key_marks(notes: &[Note], marks: &[FretMark], options: &KeyboardOptions, fingering: fn(&[Note], Hand) -> Vec<u8>) -> Vec<KeyMark>{
+    let mut notes = notes.iter().copied().zip(marks.iter().copied()).collect::<Vec<_>>();
+    notes.sort_by_key(|(n, _)| *n);
+    let sorted = notes.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+    notes.into_iter().zip(fingers(&sorted, options, fingering)).map(|((note, mark), (finger, hand))| KeyMark{ note, mark, finger, hand }).collect()
+}
+
+// The keys of a scale going up, spelled from the given tonic or its first note.
+pub fn scale_keys(scale: &Scale, tonic: Option<EnharmonicNote>, options: &KeyboardOptions) -> Vec<KeyMark>{
+    key_marks(&scale.0, &scale_marks(scale, tonic), options, scale_fingering)
+}
+
+pub fn chord_keys(chord: &RootedChord, root: Option<EnharmonicNote>, options: &KeyboardOptions) -> Vec<KeyMark>{
+    key_marks(&chord.to_scale().0, &chord_marks(chord, root), options, chord_fingering)
+}
+
+// Any notes played together, spelled and numbered as chord tones above the root: in the octave
+// of the root, or as 9, 11 and 13 when a second, fourth or sixth lies above it.
+pub fn voicing_keys(notes: &[Note], root: EnharmonicNote, options: &KeyboardOptions) -> Vec<KeyMark>{
+    let low = notes.iter().copied().min().unwrap_or(0);
+    let root_note = low - (low - root.to_note(0)).rem_euclid(OCTAVE);
+    let degree = |n: Note|{
+        let int = (n - root_note).rem_euclid(OCTAVE);
+        if n - root_note >= OCTAVE && [1, 2, 5, 6, 8, 9].contains(&int) { int + OCTAVE } else { int }
+    };
+    let mut ints = notes.iter().map(|n| degree(*n)).filter(|i| *i != 0).collect::<Vec<_>>();
+    ints.sort();
+    ints.dedup();
+    let marks = chord_marks(&RootedChord::from_intervals(root_note, &ints), Some(root));
+    let mark_of = |n: Note| ints.iter().position(|i| *i == degree(n)).map(|i| marks[i + 1]).unwrap_or(marks[0]);
+    key_marks(notes, &notes.iter().map(|n| mark_of(*n)).collect::<Vec<_>>(), options, chord_fingering)
+}
+
+// A label centered in a key, cut to fit so the keys to its right stay in place.
+fn key_label(label: &str, width: usize) -> Vec<char>{
+    centered(&label.chars().take(width).collect::<String>(), width, ' ').chars().collect()
+}
+
+// Keys as seen from above: black ones with their labels in the top rows, white ones below them.
+pub fn keyboard_string(keys: &[KeyMark], options: &KeyboardOptions) -> String{
+    let (first, last) = options.range.unwrap_or_else(||{
+        let low = keys.iter().map(|k| k.note).min().unwrap_or(C.0 + 36);
+        let high = keys.iter().map(|k| k.note).max().unwrap_or(low);
+        (low - (low - C.0).rem_euclid(OCTAVE), high + (B.0 - high).rem_euclid(OCTAVE))
+    });
+    let first = if is_black(first) { first - 1 } else { first };
+    let last = if is_black(last) { last + 1 } else { last.max(first) };
+    let (border, black, bottom, corner) = if options.unicode { ('│', '█', '─', '┴') } else { ('|', '#', '_', '|') };
+    let (bottom_left, bottom_right) = if options.unicode { ('└', '┘') } else { ('|', '|') };
+    let whites = (first..=last).filter(|n| !is_black(*n)).collect::<Vec<_>>();
+    let width = whites.len() * KEY_WIDTH + 1;
+    let label = |n: Note| keys.iter().find(|k| k.note == n).map(|k| k.label(options.labels, options.unicode));
+    let mut rows = vec![vec![' '; width]; 4];
+    for i in 0..=whites.len(){
+        for row in rows.iter_mut().take(3){
+            row[i * KEY_WIDTH] = border;
+        }
+        rows[3][i * KEY_WIDTH] = if i == 0 { bottom_left } else if i == whites.len() { bottom_right } else { corner };
+    }
+    for (i, white) in whites.iter().enumerate(){
+        let x = i * KEY_WIDTH;
+        for c in &mut rows[3][x + 1..x + KEY_WIDTH]{
+            *c = bottom;
+        }
+        if let Some(l) = label(*white){
+            rows[2].splice(x + 1..x + KEY_WIDTH, key_label(&l, KEY_WIDTH - 1));
+        }
+        // The black key above the border on the right.
+        let next = white + 1;
+        if next <= last && is_black(next){
+            let x = x + KEY_WIDTH;
+            rows[0].splice(x - 1..=x + 1, label(next).map(|l| key_label(&l, 3)).unwrap_or_else(|| vec![black; 3]));
+            rows[1].splice(x - 1..=x + 1, vec![black; 3]);
+        }
+    }
+    let mut res = rows.into_iter().map(|row| row.into_iter().collect::<String>()).collect::<Vec<_>>();
+    // Where the hands meet, at the first white key of the right hand.
+    if let Some(split) = options.split{
+        let at = whites.iter().position(|w| *w >= split).unwrap_or(whites.len()) * KEY_WIDTH;
+        let (fill, mid) = if options.unicode { ('─', '┼') } else { ('-', '+') };
+        let mut row = centered("LH", at, fill).chars().collect::<Vec<_>>();
+        if at > 0 && at < width - 1 { row.push(mid); }
+        if at < width - 1 { row.extend(centered("RH", width - at - 1, fill).chars()); }
+        res.push(row.into_iter().collect());
+    }
+    res.into_iter().map(|l| format!("{}\n", l.trim_end())).collect()
+}
+
+pub fn print_keyboard(keys: &[KeyMark], options: &KeyboardOptions){
+    print!("{}", keyboard_string(keys, options));
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_keyboard(){
+        let c4 = C.0 + 36;
+        let steps = |m: u8| ionian::steps().mode(m);
+        let notes = |root: Note| { let mut s = steps(0).to_scale(root).0; s.push(root + OCTAVE); s };
+        assert_eq!(scale_fingering(&notes(c4), Hand::Right), vec![1, 2, 3, 1, 2, 3, 4, 5]);
+        assert_eq!(scale_fingering(&notes(c4), Hand::Left), vec![5, 4, 3, 2, 1, 3, 2, 1]);
+        assert_eq!(scale_fingering(&notes(D.0 + 36), Hand::Right), vec![1, 2, 3, 1, 2, 3, 4, 5]);
+        assert_eq!(scale_fingering(&notes(F.0 + 36), Hand::Right), vec![1, 2, 3, 4, 1, 2, 3, 4]);
+        let options = KeyboardOptions{ unicode: false, ..Default::default() };
+        let c7 = RootedChord::from_intervals(c4, DOMINANT_SEVENTH);
+        let keys = chord_keys(&c7, None, &options);
+        assert_eq!(keys.iter().map(|k| k.finger).collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+        let diagram = keyboard_string(&keys, &options);
+        let rows = diagram.lines().collect::<Vec<_>>();
+        assert_eq!(rows[0], "|   ###  ###   |   ###  ###  Bb    |");
+        assert_eq!(rows[2], "| C  |    | E  |    | G  |    |    |");
+        assert_eq!(rows[3], "|____|____|____|____|____|____|____|");
+        // Labels wider than their key are cut.
+        let mut wide = keys.clone();
+        wide[3].mark.degree = 13;
+        wide[3].mark.accidental = -2;
+        let options = KeyboardOptions{ labels: KeyLabels::Degrees, ..options };
+        let rows = keyboard_string(&wide, &options).lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        assert_eq!(rows[0], "|   ###  ###   |   ###  ###  bb1   |");
+        // Two hands: a bass note left, the upper structure right.
+        let options = KeyboardOptions{ labels: KeyLabels::Degrees, split: Some(c4), unicode: false, ..Default::default() };
+        let keys = voicing_keys(&[c4 - 12, c4 + 4, c4 + 10, c4 + 14], "C".to_string().to_enharmonic_note().unwrap(), &options);
+        assert_eq!(keys.iter().map(|k| k.hand).collect::<Vec<_>>(), vec![Hand::Left, Hand::Right, Hand::Right, Hand::Right]);
+        let diagram = keyboard_string(&keys, &options);
+        assert!(diagram.contains(" b7 ") && diagram.contains(" 9 "));
+        // The hands meet on the left border of middle C, seven white keys in.
+        assert_eq!(diagram.lines().last().unwrap().find('+'), Some(35));
+    }
+}
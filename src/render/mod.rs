@@ -0,0 +1,7 @@
+pub mod staff;
+pub mod svg;
+pub mod keyboard;
+
+pub use staff::*;
+pub use svg::*;
+pub use keyboard::*;
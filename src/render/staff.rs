@@ -0,0 +1,190 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use std::collections::HashSet;
+
+// Staff positions count letters from C0, so middle C is 28 and every line is an even position.
+const TREBLE_LINES: [i32; 5] = [30, 32, 34, 36, 38];
+const BASS_LINES: [i32; 5] = [18, 20, 22, 24, 26];
+const MIDDLE_C: i32 = 28;
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Staff{
+    Treble,
+    Bass,
+    Grand,
+    // Treble or bass when the notes fit, grand otherwise.
+    Auto,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct StaffOptions{
+    pub staff: Staff,
+    pub unicode: bool,
+}
+
+impl Default for StaffOptions{
+    fn default() -> Self{
+        Self{ staff: Staff::Auto, unicode: true }
+    }
+}
+
+impl Staff{
+    pub fn parse(s: &str) -> Option<Self>{
+        match s.to_lowercase().as_str(){
+            "treble" => Some(Staff::Treble),
+            "bass" => Some(Staff::Bass),
+            "grand" => Some(Staff::Grand),
+            "auto" => Some(Staff::Auto),
+            _ => None,
+        }
+    }
+
+    fn lines(&self) -> Vec<i32>{
+        match self{
+            Staff::Treble => TREBLE_LINES.to_vec(),
+            Staff::Bass => BASS_LINES.to_vec(),
+            _ => BASS_LINES.iter().chain(TREBLE_LINES.iter()).copied().collect(),
+        }
+    }
+
+    // The lines of the staff a note at this position is written on.
+    fn lines_for(&self, pos: i32) -> [i32; 5]{
+        match self{
+            Staff::Treble => TREBLE_LINES,
+            Staff::Bass => BASS_LINES,
+            _ if pos >= MIDDLE_C => TREBLE_LINES,
+            _ => BASS_LINES,
+        }
+    }
+}
+
+// The staff position of a note as spelled: its letter and octave.
+pub fn staff_position(note: Note, en: EnharmonicNote) -> i32{
+    en.octave_of(note) * 7 + (en.letter() as i32 + 5) % 7
+}
+
+fn accidental_str(acc: i8, unicode: bool) -> &'static str{
+    match (acc, unicode){
+        (-2, true) => "𝄫",
+        (-1, true) => "♭",
+        (1, true) => "♯",
+        (2, true) => "𝄪",
+        (-2, false) => "bb",
+        (-1, false) => "b",
+        (1, false) => "#",
+        (2, false) => "x",
+        _ => "",
+    }
+}
+
+// Draws notes on a staff, every inner vec sounding together as a stack, a letter per row.
+pub fn staff_string(events: &[Vec<(Note, EnharmonicNote)>], options: &StaffOptions) -> String{
+    let positions = events.iter().flatten().map(|(n, en)| staff_position(*n, *en)).collect::<Vec<_>>();
+    let staff = match options.staff{
+        Staff::Auto if positions.iter().all(|p| *p >= MIDDLE_C - 1) => Staff::Treble,
+        Staff::Auto if positions.iter().all(|p| *p <= MIDDLE_C + 1) => Staff::Bass,
+        Staff::Auto => Staff::Grand,
+        s => s,
+    };
+    let lines = staff.lines();
+    let top = positions.iter().copied().chain(lines.iter().copied()).max().unwrap_or(0);
+    let bottom = positions.iter().copied().chain(lines.iter().copied()).min().unwrap_or(0);
+    let (fill, bar, head) = if options.unicode { ('─', '│', '●') } else { ('-', '|', 'o') };
+    let acc_width = events.iter().flatten().map(|(_, en)| accidental_str(en.accidental(), options.unicode).chars().count()).max().unwrap_or(0).max(1);
+    let width = acc_width + 4;
+    let rows = (top - bottom + 1) as usize;
+    let row_of = |pos: i32| (top - pos) as usize;
+    let line_set = lines.iter().copied().collect::<HashSet<_>>();
+    let inside = |pos: i32| lines.iter().min().map(|lo| pos >= *lo).unwrap_or(false) && lines.iter().max().map(|hi| pos <= *hi).unwrap_or(false)
+        && (staff == Staff::Grand || pos >= staff.lines_for(pos)[0]);
+    let mut grid = vec![Vec::new(); rows];
+    for (r, row) in grid.iter_mut().enumerate(){
+        let pos = top - r as i32;
+        let c = if line_set.contains(&pos) { fill } else { ' ' };
+        row.extend(std::iter::repeat_n(c, 3 + width * events.len() + 1));
+        if inside(pos) { row.push(bar); }
+    }
+    // The clefs on the line they name.
+    let clef = |grid: &mut Vec<Vec<char>>, pos: i32, sign: char|{
+        if pos <= top && pos >= bottom { grid[row_of(pos)][1] = sign; }
+    };
+    if staff != Staff::Bass { clef(&mut grid, 32, if options.unicode { '𝄞' } else { 'G' }); }
+    if staff != Staff::Treble { clef(&mut grid, 24, if options.unicode { '𝄢' } else { 'F' }); }
+    for (e, event) in events.iter().enumerate(){
+        let start = 3 + e * width;
+        let mut notes = event.iter().map(|(n, en)| (staff_position(*n, *en), *en)).collect::<Vec<_>>();
+        notes.sort_by_key(|(p, _)| *p);
+        // Ledger lines from the staff out to every note outside it.
+        for (pos, _) in &notes{
+            let staff_lines = staff.lines_for(*pos);
+            let ledgers = if *pos > staff_lines[4] { (staff_lines[4] + 2..=*pos).step_by(2).collect::<Vec<_>>() }
+                else if *pos < staff_lines[0] { (*pos..staff_lines[0] - 1).filter(|p| (p - staff_lines[0]) % 2 == 0).collect() }
+                else { Vec::new() };
+            for l in ledgers{
+                for c in &mut grid[row_of(l)][start + acc_width..=start + acc_width + 3]{
+                    *c = fill;
+                }
+            }
+        }
+        // A second above a note goes to the right of it.
+        let mut last: Option<(i32, bool)> = None;
+        for (pos, en) in notes{
+            let shifted = matches!(last, Some((p, false)) if pos - p <= 1);
+            let row = &mut grid[row_of(pos)];
+            row[start + acc_width + 1 + shifted as usize] = head;
+            let acc = accidental_str(en.accidental(), options.unicode).chars().collect::<Vec<_>>();
+            for (i, c) in acc.iter().enumerate(){
+                row[start + acc_width + 1 - acc.len() + i] = *c;
+            }
+            last = Some((pos, shifted));
+        }
+    }
+    grid.into_iter().map(|row| format!("{}\n", row.into_iter().collect::<String>().trim_end())).collect()
+}
+
+// A scale as notes one after the other, spelled from the given tonic or its first note.
+pub fn scale_staff(scale: &Scale, start: Option<EnharmonicNote>, options: &StaffOptions) -> String{
+    let mut names = scale.clone().into_enharmonic_notes_with_start(start);
+    if names.len() != scale.0.len(){
+        names = scale.0.iter().map(|n| n.to_enharmonic_note().expect("scale_staff: should not be None")).collect();
+    }
+    let events = scale.0.iter().zip(names).map(|(n, en)| vec![(*n, en)]).collect::<Vec<_>>();
+    staff_string(&events, options)
+}
+
+pub fn chord_staff(chords: &[RootedChord], options: &StaffOptions) -> String{
+    let events = chords.iter().map(|c| c.to_scale().0.into_iter().zip(c.spelled(None)).collect()).collect::<Vec<_>>();
+    staff_string(&events, options)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_staff(){
+        let c4 = C.0 + 36;
+        let en = |s: &str| s.to_string().to_enharmonic_note().unwrap();
+        assert_eq!(staff_position(c4, en("C")), 28);
+        assert_eq!(staff_position(c4, en("B#")), 27);
+        let options = StaffOptions{ unicode: false, ..Default::default() };
+        let dorian = ionian::steps().mode(1).to_scale(c4);
+        let staff = scale_staff(&dorian, None, &options);
+        let rows = staff.lines().collect::<Vec<_>>();
+        // From F5 at the top line down to the ledger line of middle C.
+        assert_eq!(rows.len(), 11);
+        assert_eq!(rows[0], "---------------------------------------|");
+        assert_eq!(rows[10], "    -o--");
+        assert!(rows[6].starts_with("-G-"));
+        assert_eq!(rows[8].find('b'), Some(14));
+        let chord = RootedChord::from_intervals(c4 - 12, DOMINANT_SEVENTH);
+        let grand = staff_string(&[chord.to_scale().0.into_iter().zip(chord.spelled(None)).collect(), vec![(c4 + 24, en("C"))]], &options);
+        // From C6 down to the G2 line of the bass staff.
+        assert_eq!(grand.lines().count(), 25);
+        assert!(grand.lines().any(|l| l.starts_with("-F-")));
+        let second = chord_staff(&[RootedChord::from_intervals(c4, &[2, 4])], &options);
+        // The D is a second above C and goes to the right of it.
+        assert_eq!(second.lines().rev().take(3).collect::<Vec<_>>(), vec!["    -o--", "      o", "-----o---|"]);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::theory::note::*;
+use crate::theory::chord::*;
+use crate::theory::interval::*;
+use crate::theory::spelling::*;
+use crate::theory::symbol::ChordSymbol;
+use crate::fretted::*;
+use std::fmt::Write;
+use std::fs;
+
+// Pitchclasses of the black keys, A based.
+const BLACK_KEYS: [Note; 5] = [1, 4, 6, 9, 11];
+
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct SvgOptions{
+    // Width of a white key, fret or ring, every other size follows from it.
+    pub unit: u32,
+    pub labels: FretLabels,
+    pub highlight: String,
+    pub root: String,
+}
+
+impl Default for SvgOptions{
+    fn default() -> Self{
+        Self{ unit: 24, labels: FretLabels::Names, highlight: String::from("#f4a259"), root: String::from("#d1495b") }
+    }
+}
+
+impl SvgOptions{
+    fn fill(&self, mark: &FretMark) -> &str{
+        if mark.is_root() { &self.root } else { &self.highlight }
+    }
+}
+
+fn escape(s: &str) -> String{
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn document(width: f32, height: f32, body: &str) -> String{
+    format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n{}</svg>\n", body, w = width, h = height)
+}
+
+fn text(res: &mut String, x: f32, y: f32, size: f32, fill: &str, s: &str){
+    let _ = writeln!(res, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{:.1}\" fill=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>", x, y, size, fill, escape(s));
+}
+
+fn circle(res: &mut String, x: f32, y: f32, r: f32, fill: &str, stroke: &str){
+    let _ = writeln!(res, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"{}\"/>", x, y, r, fill, stroke);
+}
+
+fn line(res: &mut String, (x1, y1): (f32, f32), (x2, y2): (f32, f32), width: f32){
+    let _ = writeln!(res, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\" stroke-width=\"{:.1}\"/>", x1, y1, x2, y2, width);
+}
+
+// A point on a circle, the angle in twelfths of a turn clockwise from the top.
+fn clock_point(cx: f32, cy: f32, r: f32, twelfths: f32) -> (f32, f32){
+    let a = twelfths * std::f32::consts::PI / 6.0;
+    (cx + r * a.sin(), cy - r * a.cos())
+}
+
+pub fn is_black(note: Note) -> bool{
+    BLACK_KEYS.contains(&note.to_pc().0)
+}
+
+// The keys from first to last, the marked ones coloured and labelled.
+pub fn keyboard_svg(first: Note, last: Note, marks: &[FretMark], options: &SvgOptions) -> String{
+    let u = options.unit as f32;
+    // A keyboard starts and ends on a white key.
+    let first = if is_black(first) { first - 1 } else { first };
+    let last = if is_black(last) { last + 1 } else { last.max(first) };
+    let mark = |n: Note| marks.iter().find(|m| m.pc == n.to_pc());
+    let whites = (first..=last).filter(|n| !is_black(*n)).count();
+    let (height, black_height, black_width) = (u * 5.5, u * 3.4, u * 0.6);
+    let mut res = String::new();
+    let mut x = 0.0;
+    let mut blacks = Vec::new();
+    for n in first..=last{
+        if is_black(n) { blacks.push((x, n)); continue; }
+        let fill = mark(n).map(|m| options.fill(m)).unwrap_or("white");
+        let _ = writeln!(res, "<rect x=\"{:.1}\" y=\"0\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" stroke=\"black\"/>", x, u, height, fill);
+        if let Some(m) = mark(n){
+            text(&mut res, x + u / 2.0, height - u * 0.6, u * 0.45, "black", &m.label(options.labels, true));
+        }
+        x += u;
+    }
+    // Black keys go on top, centered on the line between two white keys.
+    for (x, n) in blacks{
+        let fill = mark(n).map(|m| options.fill(m)).unwrap_or("black");
+        let _ = writeln!(res, "<rect x=\"{:.1}\" y=\"0\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" stroke=\"black\"/>", x - black_width / 2.0, black_width, black_height, fill);
+        if let Some(m) = mark(n){
+            text(&mut res, x, black_height - u * 0.5, u * 0.35, "black", &m.label(options.labels, true));
+        }
+    }
+    document(whites as f32 * u, height, &res)
+}
+
+// The neck as a chord chart turned sideways: the highest string on top, the nut on the left.
+pub fn fretboard_svg(instrument: &Instrument, marks: &[FretMark], frets: (Note, Note), options: &SvgOptions) -> String{
+    let u = options.unit as f32;
+    let (first, last) = (frets.0.max(0), frets.1.max(frets.0.max(0)));
+    let fret_width = u * 2.5;
+    let strings = instrument.strings.len();
+    let left = u * 1.5;
+    let top = u;
+    let x_of = |fret: Note| left + (fret - first) as f32 * fret_width;
+    let y_of = |string: usize| top + (strings - 1 - string) as f32 * u;
+    let bottom = y_of(0);
+    let mut res = String::new();
+    for fret in first..=last{
+        line(&mut res, (x_of(fret), top), (x_of(fret), bottom), if fret == 0 { 4.0 } else { 1.0 });
+        if fret > first{
+            text(&mut res, x_of(fret) - fret_width / 2.0, bottom + u, u * 0.45, "black", &fret.to_string());
+            let dots = match fret{ 12 | 24 => vec![-0.25, 0.25], _ if INLAYS.contains(&fret) => vec![0.0], _ => Vec::new() };
+            for d in dots{
+                circle(&mut res, x_of(fret) - fret_width / 2.0, top + (bottom - top) * (0.5 + d), u * 0.15, "#cccccc", "none");
+            }
+        }
+    }
+    for (i, open) in instrument.strings.iter().enumerate(){
+        line(&mut res, (x_of(first), y_of(i)), (x_of(last), y_of(i)), 1.0);
+        text(&mut res, u * 0.5, y_of(i), u * 0.45, "black", &open.to_pc().to_string_name());
+        // The open string left of the nut, every other fret between its wires.
+        let from = if first == 0 { 0 } else { first + 1 };
+        for fret in from..=last{
+            let Some(m) = marks.iter().find(|m| m.pc == instrument.note_at(i, fret).to_pc()) else { continue; };
+            let x = if fret == 0 { left - u * 0.5 } else { x_of(fret) - fret_width / 2.0 };
+            circle(&mut res, x, y_of(i), u * 0.4, options.fill(m), "black");
+            text(&mut res, x, y_of(i), u * 0.35, "black", &m.label(options.labels, true));
+        }
+    }
+    document(x_of(last) + u * 0.5, bottom + u * 1.5, &res)
+}
+
+// The twelve keys by fifths with their relative minors inside, the diatonic chords of the key marked.
+pub fn circle_of_fifths_svg(tonic: EnharmonicNote, steps: &Steps, options: &SvgOptions) -> String{
+    let u = options.unit as f32;
+    let (outer, inner, chords_r) = (u * 6.0, u * 4.2, u * 2.6);
+    let (cx, cy) = (outer + u, outer + u);
+    let key = key_signature(tonic, steps);
+    let home = key.fifths.rem_euclid(OCTAVE);
+    // A minor tonic triad is marked on the inner ring, under its relative major.
+    let minor_tonic = rooted_scale_chords(steps, tonic.to_note(0), 3).first().map(|c| c.chord.0.first() == Some(&MINOR_THIRD)).unwrap_or(false);
+    let tonic_at = (tonic.fifths() - if minor_tonic { 3 } else { 0 }).rem_euclid(OCTAVE);
+    let position = |pc: PC| ((pc.0 - C.0).rem_euclid(OCTAVE) * 7).rem_euclid(OCTAVE);
+    let scale = steps.to_scale(tonic.to_note(0));
+    let names = scale.clone().into_enharmonic_notes_with_start(Some(tonic));
+    let chords = rooted_scale_chords(steps, tonic.to_note(0), 3);
+    let mut res = String::new();
+    circle(&mut res, cx, cy, outer, "none", "black");
+    circle(&mut res, cx, cy, inner, "none", "black");
+    for i in 0..12{
+        // Six sharps and five flats, the one in between both ways.
+        let fifths = if i > 6 { i - 12 } else { i };
+        let en = EnharmonicNote::from_fifths(fifths);
+        // The key and its neighbours, with their relative minors, hold the diatonic triads of a major key.
+        let diatonic = matches!((i - home).rem_euclid(OCTAVE), 0 | 1 | 11);
+        for (r, minor) in [((outer + inner) / 2.0, false), ((inner + chords_r) / 2.0, true)]{
+            let fill = if i == tonic_at && minor == minor_tonic { &options.root } else if diatonic { &options.highlight } else { continue; };
+            let (x, y) = clock_point(cx, cy, r, i as f32);
+            circle(&mut res, x, y, u * 0.8, fill, "none");
+        }
+        let mut name = en.to_string_name();
+        if i == 6 { name = format!("{}/{}", name, en.next_enharmonic().to_string_name()); }
+        let (x, y) = clock_point(cx, cy, (outer + inner) / 2.0, i as f32);
+        text(&mut res, x, y, u * 0.6, "black", &name);
+        let minor = EnharmonicNote::from_fifths(fifths + 3);
+        let (x, y) = clock_point(cx, cy, (inner + chords_r) / 2.0, i as f32);
+        text(&mut res, x, y, u * 0.45, "black", &format!("{}m", minor.to_string_name()));
+        let (x1, y1) = clock_point(cx, cy, outer, i as f32 + 0.5);
+        let (x2, y2) = clock_point(cx, cy, chords_r, i as f32 + 0.5);
+        line(&mut res, (x1, y1), (x2, y2), 0.5);
+    }
+    circle(&mut res, cx, cy, chords_r, "none", "black");
+    // The chords of the key on their roots, inside the rings.
+    for (chord, name) in chords.iter().zip(names){
+        let symbol = ChordSymbol::from_rooted(chord, name).as_string(ChordStyling::Std);
+        let (x, y) = clock_point(cx, cy, chords_r - u * 0.8, position(chord.root.to_pc()) as f32);
+        text(&mut res, x, y, u * 0.45, &options.root, &symbol);
+    }
+    document(2.0 * cx, 2.0 * cy, &res)
+}
+
+// The twelve pitchclasses as on a clock with C on top, the marked ones joined into a polygon.
+pub fn pc_clock_svg(marks: &[FretMark], options: &SvgOptions) -> String{
+    let u = options.unit as f32;
+    let r = u * 4.0;
+    let (cx, cy) = (r + u * 1.5, r + u * 1.5);
+    let twelfths = |pc: PC| (pc.0 - C.0).rem_euclid(OCTAVE) as f32;
+    let mut sorted = marks.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|m| (m.pc.0 - C.0).rem_euclid(OCTAVE));
+    let mut res = String::new();
+    circle(&mut res, cx, cy, r, "none", "#999999");
+    let points = sorted.iter().map(|m| { let (x, y) = clock_point(cx, cy, r, twelfths(m.pc)); format!("{:.1},{:.1}", x, y) }).collect::<Vec<_>>();
+    let _ = writeln!(res, "<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"0.3\" stroke=\"black\"/>", points.join(" "), options.highlight);
+    for i in 0..12{
+        let pc = PC((C.0 + i).rem_euclid(OCTAVE));
+        let (x, y) = clock_point(cx, cy, r, i as f32);
+        match marks.iter().find(|m| m.pc == pc){
+            Some(m) => {
+                circle(&mut res, x, y, u * 0.6, options.fill(m), "black");
+                text(&mut res, x, y, u * 0.45, "black", &m.label(options.labels, true));
+            },
+            None => {
+                circle(&mut res, x, y, u * 0.6, "white", "#999999");
+                text(&mut res, x, y, u * 0.4, "#999999", &i.to_string());
+            },
+        }
+    }
+    document(2.0 * cx, 2.0 * cy, &res)
+}
+
+pub fn write_svg(path: &str, svg: &str) -> std::io::Result<()>{
+    fs::write(path, svg)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_svg(){
+        let c4 = C.0 + 36;
+        let c7 = RootedChord::from_intervals(c4, DOMINANT_SEVENTH);
+        let marks = chord_marks(&c7, None);
+        let keyboard = keyboard_svg(c4, c4 + 12, &marks, &SvgOptions::default());
+        assert!(keyboard.starts_with("<svg"));
+        // Eight white keys, five black ones, the B♭ a marked black key.
+        assert_eq!(keyboard.matches("<rect").count(), 13);
+        assert!(keyboard.contains("width=\"192\""));
+        assert!(keyboard.contains(">B♭</text>"));
+        let board = fretboard_svg(&Instrument::guitar(), &marks, (0, 3), &SvgOptions::default());
+        // On the first three frets and open: E G, B♭ C, E, G B♭, C, E G.
+        assert_eq!(board.matches("r=\"9.6\"").count(), 10);
+        let fifths = circle_of_fifths_svg("Eb".to_string().to_enharmonic_note().unwrap(), &ionian::steps(), &SvgOptions::default());
+        assert!(fifths.contains(">E♭</text>") && fifths.contains(">Cm</text>") && fifths.contains(">F♯/G♭</text>"));
+        assert!(fifths.contains(">A♭</text>"));
+        // D Dorian has its root on Dm, under F.
+        let dorian = circle_of_fifths_svg("D".to_string().to_enharmonic_note().unwrap(), &ionian::steps().mode(1), &SvgOptions::default());
+        let (x, y) = clock_point(168.0, 168.0, 81.6, 11.0);
+        assert!(dorian.contains(&format!("cx=\"{:.1}\" cy=\"{:.1}\" r=\"19.2\" fill=\"#d1495b\"", x, y)));
+        assert_eq!(dorian.matches("fill=\"#d1495b\" stroke=\"none\"").count(), 1);
+        let clock = pc_clock_svg(&marks, &SvgOptions{ labels: FretLabels::Degrees, ..Default::default() });
+        assert!(clock.contains(">♭7</text>"));
+        assert_eq!(clock.matches("<circle").count(), 13);
+    }
+}
@@ -115,7 +115,7 @@ impl Chord{
             sub_scales.insert(Scale(subscale).into_chord());
         }
         let mut res = sub_scales.into_iter().collect::<Vec<Chord>>();
-        res.sort_by(|a,b| a.len().cmp(&b.len()).then(a.cmp(&b)));
+        res.sort_by(|a,b| a.len().cmp(&b.len()).then(a.cmp(b)));
         res
     }
 
@@ -253,6 +253,11 @@ impl RootedChord{
         Scale(scale)
     }
 
+    // The same chord on the same pitchclass, in whatever octave.
+    pub fn same_in_any_octave(&self, other: &RootedChord) -> bool{
+        self.root.rem_euclid(OCTAVE) == other.root.rem_euclid(OCTAVE) && self.chord == other.chord
+    }
+
     fn normalized(self) -> Self{
         Self {
             root: self.root % OCTAVE,
@@ -434,7 +439,7 @@ pub fn scale_subseq_chords(scale: Scale) -> Vec<RootedChord>{
 pub fn steps_subseq_chords(steps: Steps) -> Vec<Vec<Chord>>{
     let mut scale = steps.into_scale(0);
     scale.0.pop();
-    let mut table = vec![0; 12];
+    let mut table = [0; 12];
     for (i,note) in scale.0.iter().enumerate(){
         table[(*note).max(0) as usize] = i;
     }
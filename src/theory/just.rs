@@ -0,0 +1,152 @@
+use super::note::*;
+use super::interval::*;
+use super::chord::RootedChord;
+
+/// A frequency ratio such as 5/4.
+#[derive(Clone,Copy,PartialEq,Eq,Hash,Debug)]
+pub struct Ratio(pub u32, pub u32);
+
+#[derive(Clone,Copy,PartialEq,Eq,Hash,Debug)]
+pub enum Limit{ Five, Seven }
+
+fn gcd(a: u32, b: u32) -> u32{
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+impl Ratio{
+    pub fn reduced(self) -> Self{
+        let g = gcd(self.0, self.1).max(1);
+        Ratio(self.0 / g, self.1 / g)
+    }
+
+    pub fn to_f32(&self) -> f32{
+        self.0 as f32 / self.1 as f32
+    }
+
+    pub fn cents(&self) -> f32{
+        cents(self.to_f32())
+    }
+
+    // How far this ratio is from the given equal tempered interval, in cents.
+    pub fn deviation(&self, semis: Note) -> f32{
+        self.cents() - (semis * 100) as f32
+    }
+}
+
+impl std::fmt::Display for Ratio{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{}/{}", self.0, self.1)
+    }
+}
+
+pub fn cents(ratio: f32) -> f32{
+    1200.0 * ratio.log2()
+}
+
+// Within the octave, indexed by semitones.
+const FIVE_LIMIT: [Ratio; 12] = [
+    Ratio(1, 1), Ratio(16, 15), Ratio(9, 8), Ratio(6, 5), Ratio(5, 4), Ratio(4, 3),
+    Ratio(45, 32), Ratio(3, 2), Ratio(8, 5), Ratio(5, 3), Ratio(9, 5), Ratio(15, 8),
+];
+
+// The 7-limit uses the septimal tritone and harmonic seventh.
+const SEVEN_LIMIT: [Ratio; 12] = [
+    Ratio(1, 1), Ratio(16, 15), Ratio(9, 8), Ratio(6, 5), Ratio(5, 4), Ratio(4, 3),
+    Ratio(7, 5), Ratio(3, 2), Ratio(8, 5), Ratio(5, 3), Ratio(7, 4), Ratio(15, 8),
+];
+
+// None when the octaves no longer fit the numerator or denominator.
+pub fn just_ratio(interval: Note, limit: Limit) -> Option<Ratio>{
+    let table = match limit{
+        Limit::Five => &FIVE_LIMIT,
+        Limit::Seven => &SEVEN_LIMIT,
+    };
+    let octaves = interval.div_euclid(OCTAVE);
+    let Ratio(mut num, mut den) = table[interval.rem_euclid(OCTAVE) as usize];
+    let factor = 2u32.checked_pow(octaves.unsigned_abs())?;
+    if octaves >= 0{
+        num = num.checked_mul(factor)?;
+    } else {
+        den = den.checked_mul(factor)?;
+    }
+    Some(Ratio(num, den).reduced())
+}
+
+#[derive(Clone,Copy,Debug)]
+pub struct JustNote{
+    pub note: Note,
+    // None when the note is too many octaves from the first one, it keeps its equal tempered pitch.
+    pub ratio: Option<Ratio>,
+    pub frequency: f32,
+    // Deviation from the equal tempered pitch of the same note.
+    pub cents: f32,
+}
+
+impl std::fmt::Display for JustNote{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self.ratio{
+            Some(ratio) => write!(f, "{} {} {:.2}Hz {:+.1}¢", self.note.to_pc(), ratio, self.frequency, self.cents),
+            None => write!(f, "{} - {:.2}Hz", self.note.to_pc(), self.frequency),
+        }
+    }
+}
+
+// Tune the notes justly against the first one, which keeps its equal tempered pitch.
+pub fn just_notes(notes: &[Note], limit: Limit) -> Vec<JustNote>{
+    if notes.is_empty() { return Vec::new(); }
+    let root = notes[0];
+    let root_freq = to_pitch(root);
+    notes.iter().map(|note| {
+        let ratio = just_ratio(note - root, limit);
+        let frequency = ratio.map(|r| root_freq * r.to_f32()).unwrap_or_else(|| to_pitch(*note));
+        JustNote{
+            note: *note,
+            ratio,
+            frequency,
+            cents: cents(frequency / to_pitch(*note)),
+        }
+    }).collect::<Vec<_>>()
+}
+
+pub trait ToJust{
+    fn to_just(&self, limit: Limit) -> Vec<JustNote>;
+}
+
+impl ToJust for Scale{
+    fn to_just(&self, limit: Limit) -> Vec<JustNote>{
+        just_notes(&self.0, limit)
+    }
+}
+
+impl ToJust for RootedChord{
+    fn to_just(&self, limit: Limit) -> Vec<JustNote>{
+        just_notes(&self.to_scale().0, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_just_ratios(){
+        assert_eq!(just_ratio(MAJOR_THIRD, Limit::Five), Some(Ratio(5, 4)));
+        assert_eq!(just_ratio(MINOR_SEVENTH, Limit::Seven), Some(Ratio(7, 4)));
+        assert_eq!(just_ratio(NINETH, Limit::Five), Some(Ratio(9, 4)));
+        assert_eq!(just_ratio(-PERFECT_FOURTH, Limit::Five), Some(Ratio(3, 4)));
+        assert_eq!(just_ratio(40 * OCTAVE, Limit::Five), None);
+        assert_eq!(just_ratio(-40 * OCTAVE + MAJOR_THIRD, Limit::Five), None);
+    }
+    #[test]
+    fn test_just_chord(){
+        let chord = RootedChord::from_intervals(A4, &[MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH]);
+        let tuned = chord.to_just(Limit::Seven);
+        assert_eq!(tuned[0].frequency.round() as i32, 440);
+        assert_eq!(tuned[1].cents.round() as i32, -14);
+        assert_eq!(tuned[2].cents.round() as i32, 2);
+        assert_eq!(tuned[3].cents.round() as i32, -31);
+        let wide = just_notes(&[A4, A4 + 40 * OCTAVE, A4 + MAJOR_THIRD], Limit::Five);
+        assert_eq!(wide.len(), 3);
+        assert_eq!(wide[1].ratio, None);
+        assert_eq!(wide[2].ratio, Some(Ratio(5, 4)));
+    }
+}
@@ -2,8 +2,18 @@ pub mod note;
 pub mod scale;
 pub mod chord;
 pub mod interval;
+pub mod tuning;
+pub mod just;
+pub mod spelling;
+pub mod symbol;
+pub mod progression;
 
 pub use note::*;
 pub use scale::*;
 pub use chord::*;
 pub use interval::*;
+pub use tuning::*;
+pub use just::*;
+pub use spelling::*;
+pub use symbol::*;
+pub use progression::*;
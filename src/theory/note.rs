@@ -1,4 +1,5 @@
 use super::interval::*;
+use super::tuning::{ Tuning, EDO12 };
 // use std::collections::{ HashMap, HashSet };
 
 pub const A4: Note = 48;
@@ -11,7 +12,6 @@ pub type Rank = u16;
 /// Once, it was all just ```Vec<Note>``` with different types such as ```type Scale = Vec<Note>```.
 /// This provides us with compile time checks.
 /// Interchanging the versions now only can be done explicitly.
-
 pub type Notes = Vec<Note>;
 #[derive(Clone,PartialEq,Eq,Hash,Default)]
 pub struct Steps(pub Vec<Note>);
@@ -164,8 +164,8 @@ pub trait IntoEnharmonicNotesWithStart{
     fn into_enharmonic_notes_with_start(self, start: Option<EnharmonicNote>) -> Vec<EnharmonicNote>;
 }
 
-impl ToString for RelativeNote{
-    fn to_string(&self) -> String{
+impl std::fmt::Display for RelativeNote{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
         let mut res = String::new();
         match self{
             RelativeNote::Natural => {  },
@@ -177,7 +177,7 @@ impl ToString for RelativeNote{
                 for _ in 0..*i { res.push('♭'); }
             }
         }
-        res
+        write!(f, "{}", res)
     }
 }
 
@@ -233,18 +233,13 @@ impl std::fmt::Debug for PC {
 
 impl ToNote for PC{
     fn to_note(&self, rank: Rank) -> Note{
-        self.0 + (rank as Note * OCTAVE)
+        EDO12.note(*self, rank)
     }
 }
 
 impl ToPC for Note{
     fn to_pc(&self) -> PC{
-        let mut inrank = self % 12;
-        if inrank < 0 {
-            inrank += 12;
-        }
-        if inrank >= 12 { panic!("as_pc: should never happen!"); }
-        PC(inrank)
+        EDO12.pc(*self)
     }
 }
 
@@ -299,6 +294,11 @@ impl std::fmt::Display for EnharmonicNote{
 }
 
 impl EnharmonicNote{
+    // Letter 0 is A, up to 6 for G.
+    pub fn new(letter: u8, accidental: i8) -> Self{
+        Self{ letter: letter % 7, accidental }
+    }
+
     pub fn letter(&self) -> u8{
         self.letter
     }
@@ -432,7 +432,7 @@ impl ToEnharmonicNote for String{
 
 impl IntoEnharmonicNotes for String{
     fn into_enharmonic_notes(self) -> Vec<EnharmonicNote>{
-        self.split(',').into_iter().map(|s| s.to_string().to_enharmonic_note()).flatten().collect::<Vec<_>>()
+        self.split(',').filter_map(|s| s.to_string().to_enharmonic_note()).collect::<Vec<_>>()
     }
 }
 
@@ -457,7 +457,7 @@ impl ToEnharmonicNote for Note{
 }
 // Could be used for hexatonics etc?
 fn _into_enharmonic_notes_with_start_subheptatonic(scale: Scale, start: Option<EnharmonicNote>) -> Vec<EnharmonicNote>{
-    let mut set = vec![0,0,0,0,0,0,0];
+    let mut set = [0,0,0,0,0,0,0];
     let mut res = Vec::new();
     let skip = if let Some(en) = start{
         set[en.letter() as usize] = 1;
@@ -533,8 +533,7 @@ impl IntoEnharmonicNotesWithStart for Scale{
 
 // note (48*SEMI) (48=12*4) is A4 at 440 hz
 pub fn to_pitch(note: Note) -> f32{
-    let x = note as i32 - 48;
-    (2.0f32).powf(x as f32 / OCTAVE as f32) * 440.0
+    EDO12.to_pitch(note)
 }
 
 #[cfg(test)]
@@ -546,7 +545,7 @@ mod tests{
     }
     #[test]
     fn test_note_to_pc(){
-        assert_eq!(23.to_pc().0 < 12, true);
-        assert_eq!((-450).to_pc().0 >= 0, true);
+        assert!(23.to_pc().0 < 12);
+        assert!((-450).to_pc().0 >= 0);
     }
 }
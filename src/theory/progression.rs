@@ -0,0 +1,323 @@
+use super::note::*;
+use super::chord::*;
+use super::spelling::*;
+use super::symbol::*;
+
+// Roots go in the octave starting at A3.
+const PROGRESSION_RANK: Rank = 3;
+
+/// A chord in a bar, spelled as written and lasting a number of beats.
+#[derive(Clone)]
+pub struct ProgressionChord{
+    pub chord: RootedChord,
+    pub root: EnharmonicNote,
+    pub bass: Option<EnharmonicNote>,
+    pub beats: u32,
+}
+
+impl ProgressionChord{
+    pub fn from_symbol(symbol: &ChordSymbol, beats: u32) -> Self{
+        Self{ chord: symbol.to_rooted_chord(PROGRESSION_RANK), root: symbol.root, bass: symbol.bass, beats }
+    }
+
+    pub fn parse(s: &str, beats: u32) -> Option<Self>{
+        Some(Self::from_symbol(&ChordSymbol::parse(s)?, beats))
+    }
+
+    pub fn symbol(&self) -> ChordSymbol{
+        ChordSymbol{ root: self.root, chord: self.chord.chord.clone(), bass: self.bass }
+    }
+
+    pub fn name(&self) -> String{
+        self.symbol().as_lead_sheet()
+    }
+
+    pub fn as_string(&self, styling: ChordStyling) -> String{
+        self.symbol().as_string(styling)
+    }
+
+    pub fn transposed(&self, from: &KeySignature, to: &KeySignature) -> Self{
+        let mut symbol = self.symbol();
+        symbol.root = transpose_spelled(symbol.root, from, to);
+        symbol.bass = symbol.bass.map(|b| transpose_spelled(b, from, to));
+        Self::from_symbol(&symbol, self.beats)
+    }
+}
+
+#[derive(Clone)]
+pub struct Bar{
+    pub chords: Vec<ProgressionChord>,
+    pub meter: (u32, u32),
+    pub label: Option<String>,
+    pub repeat_start: bool,
+    // How often the repeated part is played in total.
+    pub repeat_end: Option<u32>,
+    // The volta this bar starts.
+    pub ending: Option<u32>,
+    pub double_bar: bool,
+}
+
+impl Bar{
+    fn new(meter: (u32, u32)) -> Self{
+        Self{ chords: Vec::new(), meter, label: None, repeat_start: false, repeat_end: None, ending: None, double_bar: false }
+    }
+
+    pub fn beats(&self) -> u32{
+        self.meter.0
+    }
+
+    fn same_chords(&self, other: &Bar) -> bool{
+        self.meter == other.meter && self.chords.len() == other.chords.len()
+            && self.chords.iter().zip(&other.chords).all(|(a, b)| a.name() == b.name() && a.beats == b.beats)
+    }
+
+    // The chords with a dot for every extra beat, or just the chords when they are equally long.
+    fn chords_string(&self) -> String{
+        let unit = self.chords.iter().fold(0, |g, c| gcd(g, c.beats)).max(1);
+        let mut res = Vec::new();
+        for c in &self.chords{
+            res.push(c.name());
+            for _ in 1..c.beats / unit{
+                res.push(String::from("."));
+            }
+        }
+        res.join(" ")
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32{
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// A song or progression: bars of chords with repeats, endings and section labels.
+/// The text form looks like `[A] |: Dm7 G7 | Cmaj7 % :|`.
+#[derive(Clone,Default)]
+pub struct Progression{
+    pub bars: Vec<Bar>,
+}
+
+// A barline like |, ||, |:, :|, :|x3, |1., :|2. or |].
+#[derive(Default)]
+struct Barline{
+    repeat_end: Option<u32>,
+    double_bar: bool,
+    repeat_start: bool,
+    ending: Option<u32>,
+}
+
+fn parse_barline(token: &str) -> Option<Barline>{
+    let mut res = Barline::default();
+    let mut rest = token;
+    if let Some(r) = rest.strip_prefix(':') { rest = r; res.repeat_end = Some(2); }
+    rest = rest.strip_prefix('|')?;
+    if let Some(r) = rest.strip_prefix('|') { rest = r; res.double_bar = true; }
+    if let Some(r) = rest.strip_prefix(']') { rest = r; res.double_bar = true; }
+    if let Some(r) = rest.strip_prefix(':') { rest = r; res.repeat_start = true; }
+    if let Some(r) = rest.strip_prefix('x'){
+        let len = r.chars().take_while(|c| c.is_ascii_digit()).count();
+        res.repeat_end = Some(r[..len].parse().ok()?);
+        rest = &r[len..];
+    }
+    if let Some(r) = rest.strip_suffix('.'){
+        res.ending = Some(r.parse().ok()?);
+        rest = "";
+    }
+    if rest.is_empty() { Some(res) } else { None }
+}
+
+fn parse_meter(token: &str) -> Option<(u32, u32)>{
+    let (n, d) = token.split_once('/')?;
+    Some((n.parse().ok()?, d.parse().ok()?))
+}
+
+impl Progression{
+    // None when a chord, barline or bar can not be read.
+    pub fn parse(src: &str) -> Option<Self>{
+        let mut bars: Vec<Bar> = Vec::new();
+        let mut meter = (4, 4);
+        let mut next = Bar::new(meter);
+        // A chord or %, and the beats following it written as . or /
+        let mut slots: Vec<(String, u32)> = Vec::new();
+        let close = |bars: &mut Vec<Bar>, next: &mut Bar, slots: &mut Vec<(String, u32)>, meter| -> Option<()>{
+            if slots.is_empty() { return Some(()); }
+            let count = slots.iter().map(|(_, n)| n).sum::<u32>();
+            let mut bar = std::mem::replace(next, Bar::new(meter));
+            if slots.len() == 1 && slots[0].0 == "%" && count == 1{
+                bar.chords = bars.last()?.chords.clone();
+            } else {
+                if !bar.beats().is_multiple_of(count) { return None; }
+                let unit = bar.beats() / count;
+                for (s, n) in slots.drain(..){
+                    if s == "%"{
+                        match bar.chords.last_mut(){
+                            Some(c) => c.beats += n * unit,
+                            None => bar.chords.push(ProgressionChord{ beats: n * unit, ..bars.last()?.chords.last()?.clone() }),
+                        }
+                    } else {
+                        bar.chords.push(ProgressionChord::parse(&s, n * unit)?);
+                    }
+                }
+            }
+            slots.clear();
+            bars.push(bar);
+            Some(())
+        };
+        for token in src.split_whitespace(){
+            if token.starts_with('|') || token.starts_with(':'){
+                let line = parse_barline(token)?;
+                close(&mut bars, &mut next, &mut slots, meter)?;
+                if line.repeat_end.is_some() || line.double_bar{
+                    let last = bars.last_mut()?;
+                    if line.repeat_end.is_some() { last.repeat_end = line.repeat_end; }
+                    last.double_bar |= line.double_bar;
+                }
+                next.repeat_start |= line.repeat_start;
+                if line.ending.is_some() { next.ending = line.ending; }
+            } else if token.starts_with('[') && token.ends_with(']'){
+                next.label = Some(token[1..token.len() - 1].to_string());
+            } else if let Some(m) = parse_meter(token){
+                meter = m;
+                next.meter = m;
+            } else if token == "." || token == "/"{
+                slots.last_mut()?.1 += 1;
+            } else {
+                slots.push((token.to_string(), 1));
+            }
+        }
+        close(&mut bars, &mut next, &mut slots, meter)?;
+        Some(Self{ bars })
+    }
+
+    pub fn chords(&self) -> impl Iterator<Item = &ProgressionChord>{
+        self.bars.iter().flat_map(|b| b.chords.iter())
+    }
+
+    // The bars in the order they are played, following repeats and endings.
+    pub fn play_order(&self) -> Vec<usize>{
+        let mut res = Vec::new();
+        let (mut i, mut start, mut pass) = (0, 0, 1);
+        let mut ending = None;
+        while i < self.bars.len(){
+            let bar = &self.bars[i];
+            if bar.repeat_start && start != i{
+                start = i;
+                pass = 1;
+            }
+            if bar.ending.is_some() { ending = bar.ending; }
+            let skip = ending.map(|e| e != pass).unwrap_or(false);
+            if !skip { res.push(i); }
+            if bar.repeat_end.is_some() || bar.double_bar{
+                let last_ending = ending.is_some() && bar.repeat_end.is_none();
+                ending = None;
+                match bar.repeat_end{
+                    Some(n) if !skip && pass < n => {
+                        pass += 1;
+                        i = start;
+                        continue;
+                    },
+                    Some(_) if !skip => { start = i + 1; pass = 1; },
+                    _ if last_ending => { start = i + 1; pass = 1; },
+                    _ => {},
+                }
+            }
+            i += 1;
+        }
+        res
+    }
+
+    // Every chord as played with the beat it starts on.
+    pub fn timeline(&self) -> Vec<(u32, &ProgressionChord)>{
+        let mut res = Vec::new();
+        let mut beat = 0;
+        for i in self.play_order(){
+            for c in &self.bars[i].chords{
+                res.push((beat, c));
+                beat += c.beats;
+            }
+        }
+        res
+    }
+
+    pub fn transposed(&self, from: &KeySignature, to: &KeySignature) -> Self{
+        let mut res = self.clone();
+        for bar in &mut res.bars{
+            for c in &mut bar.chords{
+                *c = c.transposed(from, to);
+            }
+        }
+        res
+    }
+}
+
+fn closing_barline(bar: &Bar) -> String{
+    match bar.repeat_end{
+        Some(2) => String::from(":|"),
+        Some(n) => format!(":|x{}", n),
+        None if bar.double_bar => String::from("||"),
+        None => String::from("|"),
+    }
+}
+
+impl std::fmt::Display for Progression{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        let mut meter = (4, 4);
+        let mut prev: Option<&Bar> = None;
+        for bar in &self.bars{
+            let mut opening = String::new();
+            if bar.repeat_start { opening.push(':'); }
+            if let Some(e) = bar.ending { opening.push_str(&format!("{}.", e)); }
+            // A new section starts on a new line, a count would run into an ending.
+            match prev{
+                Some(p) if bar.label.is_some() => writeln!(f, "{}", closing_barline(p))?,
+                Some(p) if p.repeat_end.map(|n| n != 2).unwrap_or(false) && !opening.is_empty() => write!(f, "{} ", closing_barline(p))?,
+                Some(p) => opening.insert_str(0, &closing_barline(p)),
+                None => {},
+            }
+            if let Some(label) = &bar.label { write!(f, "[{}] ", label)?; }
+            if !opening.contains('|') { opening.insert(0, '|'); }
+            write!(f, "{} ", opening)?;
+            if bar.meter != meter{
+                meter = bar.meter;
+                write!(f, "{}/{} ", meter.0, meter.1)?;
+            }
+            let simile = bar.label.is_none() && !bar.repeat_start && bar.ending.is_none()
+                && prev.map(|p| p.same_chords(bar)).unwrap_or(false);
+            write!(f, "{} ", if simile { String::from("%") } else { bar.chords_string() })?;
+            prev = Some(bar);
+        }
+        match prev{
+            Some(p) => write!(f, "{}", closing_barline(p)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_progression(){
+        let prog = Progression::parse("| Dm7 G7 | Cmaj7 % | :|").unwrap();
+        assert_eq!(prog.bars.len(), 2);
+        assert_eq!(prog.bars[1].chords[0].beats, 4);
+        assert_eq!(prog.to_string(), "| Dm7 G7 | Cmaj7 :|");
+        assert_eq!(prog.play_order(), vec![0, 1, 0, 1]);
+        let src = "[A] |: C . . G7 | % |1. Am7 D7 :|2. F G ||\n3/4 [B] | Bb/D | Eb |]";
+        let prog = Progression::parse(src).unwrap();
+        assert_eq!(prog.to_string(), "[A] |: C . . G7 | % |1. Am7 D7 :|2. F G ||\n[B] | 3/4 Bb/D | Eb ||");
+        assert_eq!(Progression::parse(&prog.to_string()).unwrap().to_string(), prog.to_string());
+        assert_eq!(prog.play_order(), vec![0, 1, 2, 0, 1, 3, 4, 5]);
+        let timeline = prog.timeline();
+        assert_eq!(timeline[1].0, 3);
+        assert_eq!(timeline.last().map(|(b, c)| (*b, c.name())), Some((27, String::from("Eb"))));
+        assert_eq!(prog.bars[4].chords[0].chord.root, "Bb".to_string().to_enharmonic_note().unwrap().to_note(36));
+        let c = KeySignature{ fifths: 0, mode: "major" };
+        let up = prog.transposed(&c, &c.transposed(5));
+        assert_eq!(up.bars[4].chords[0].name(), "Eb/G");
+        let prog = Progression::parse("|: A :|x3 B |").unwrap();
+        assert_eq!(prog.to_string(), "|: A :|x3 B |");
+        assert_eq!(prog.play_order(), vec![0, 0, 0, 1]);
+        assert!(Progression::parse("| C G F |").is_none());
+        assert!(Progression::parse("| Xm |").is_none());
+    }
+}
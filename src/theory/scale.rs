@@ -1,21 +1,13 @@
 use super::note::*;
 use super::interval::{ OCTAVE, SEMI };
+use super::tuning::EDO12;
 use std::cmp::Ordering;
 
 pub type Mode = u8;
 
 impl ToSteps for Scale{
     fn to_steps(&self) -> Steps{
-        if self.0.is_empty() { return Steps::default(); }
-        let mut last = self.0[0];
-        let mut intervals = Vec::new();
-        for note in self.0.iter().skip(1){
-            let diff = note - last;
-            intervals.push(diff);
-            last = *note;
-        }
-        intervals.push(self.0[0] + OCTAVE - last);
-        Steps(intervals)
+        EDO12.scale_to_steps(self)
     }
 }
 
@@ -121,6 +113,7 @@ impl ToRelative for Steps{
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
 pub trait StepsTrait{
     fn as_mode(self, note: Note, mode: Mode) -> Scale;
     fn mode_nr_of_this(self, mode: &Self) -> Option<(usize,Self)>
@@ -169,6 +162,10 @@ impl RelativeTrait for Relative{
 }
 
 pub fn notes_to_octave_scale(scale: &Scale) -> Notes{
+    notes_to_octave_scale_in(scale, OCTAVE)
+}
+
+pub fn notes_to_octave_scale_in(scale: &Scale, octave: Note) -> Notes{
     let mut res = Vec::new();
     if scale.0.is_empty(){ return res; }
     let mut last = scale.0[0];
@@ -179,13 +176,13 @@ pub fn notes_to_octave_scale(scale: &Scale) -> Notes{
         last = *note;
         sum += diff;
     }
-    if sum > OCTAVE{
+    if sum > octave{
         return Vec::new();
     }
-    if sum == OCTAVE{
+    if sum == octave{
         return res;
     }
-    res.push(OCTAVE - sum);
+    res.push(octave - sum);
     res
 }
 
@@ -209,7 +206,7 @@ impl<'a> Iterator for ScaleIterator<'a>{
     }
 }
 
-pub fn note_iter(root: Note, scale: &[Note]) -> ScaleIterator{
+pub fn note_iter(root: Note, scale: &[Note]) -> ScaleIterator<'_>{
     ScaleIterator{
         scale,
         current: 0,
@@ -0,0 +1,178 @@
+use super::note::*;
+use super::interval::*;
+use super::chord::RootedChord;
+use super::scale::StepsTrait;
+use super::symbol::parse_root;
+use crate::libr::scales::ionian;
+
+// Semitones above C and position on the circle of fifths (C = 0) of the natural letters A..G.
+const LETTER_SEMIS_FROM_C: [Note; 7] = [9, 11, 0, 2, 4, 5, 7];
+const LETTER_FIFTHS_FROM_C: [Note; 7] = [3, 5, 0, 2, 4, -1, 1];
+// Key signature offsets of the modes of Ionian.
+const IONIAN_MODE_FIFTHS: [Note; 7] = [0, -2, -4, 1, -1, -3, -5];
+const IONIAN_MODE_NAMES: [&str; 7] = ["major", "dorian", "phrygian", "lydian", "mixolydian", "minor", "locrian"];
+
+impl EnharmonicNote{
+    // The note at a position on the circle of fifths, C = 0.
+    pub fn from_fifths(fifths: Note) -> Self{
+        // The naturals go from F = -1 to B = 5.
+        let natural = (fifths + 1).rem_euclid(7) - 1;
+        let letter = LETTER_FIFTHS_FROM_C.iter().position(|f| *f == natural).expect("EnharmonicNote::from_fifths: should be impossible");
+        EnharmonicNote::new(letter as u8, ((fifths - natural) / 7) as i8)
+    }
+
+    pub fn semis_from_c(&self) -> Note{
+        LETTER_SEMIS_FROM_C[self.letter() as usize] + self.accidental() as Note
+    }
+
+    // Position on the circle of fifths, C = 0, G = 1, F = -1.
+    pub fn fifths(&self) -> Note{
+        LETTER_FIFTHS_FROM_C[self.letter() as usize] + 7 * self.accidental() as Note
+    }
+
+    // The note when spelled like this in an octave starting at C: C4 = 39 and B♯3 = 39.
+    pub fn in_octave(&self, octave: Note) -> Note{
+        (octave + 1) * OCTAVE + self.semis_from_c() - 21
+    }
+
+    // The octave number, starting at C, of the note when spelled like this: C4 = 39 and B♯3 = 39.
+    pub fn octave_of(&self, note: Note) -> Note{
+        (note + 21 - self.semis_from_c()).div_euclid(OCTAVE) - 1
+    }
+}
+
+// A note with its octave as in C4 or Bb3, spelled as written.
+pub fn parse_pitch(s: &str) -> Option<(Note, EnharmonicNote)>{
+    let (en, rest) = parse_root(s.trim())?;
+    let octave = rest.parse::<Note>().ok()?;
+    Some((en.in_octave(octave), en))
+}
+
+pub fn spell_note(note: Note, letter: u8) -> EnharmonicNote{
+    note.to_enharmonic_note().expect("spell_note: should not be None").spelled_as(letter)
+}
+
+// How many letters an interval spans, as read in a chord symbol: ♭5 is a fifth, ♯9 a ninth.
+pub fn interval_letters(interval: Note) -> u8{
+    let within = match interval.rem_euclid(OCTAVE){
+        0 => 0,
+        1 | 2 => 1,
+        3 | 4 => 2,
+        5 => 3,
+        6 | 7 => 4,
+        8 | 9 => 5,
+        _ => 6,
+    };
+    (within + 7 * interval.div_euclid(OCTAVE)).rem_euclid(7) as u8
+}
+
+impl RootedChord{
+    // Spell the chord tones by their interval from the root.
+    pub fn spelled(&self, root: Option<EnharmonicNote>) -> Vec<EnharmonicNote>{
+        let root = root.unwrap_or_else(|| self.root.to_enharmonic_note().expect("RootedChord::spelled: should not be None"));
+        let mut res = vec![root];
+        for int in &self.chord.0{
+            res.push(spell_note(self.root + int, root.letter() + interval_letters(*int)));
+        }
+        res
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct KeySignature{
+    // Positive for sharps, negative for flats.
+    pub fifths: Note,
+    pub mode: &'static str,
+}
+
+impl KeySignature{
+    // The accidental of every letter A..G in this key.
+    pub fn accidentals(&self) -> [i8; 7]{
+        let mut res = [0; 7];
+        for f in self.fifths - 1..=self.fifths + 5{
+            let en = EnharmonicNote::from_fifths(f);
+            res[en.letter() as usize] = en.accidental();
+        }
+        res
+    }
+
+    pub fn tonic(&self) -> EnharmonicNote{
+        let mode = IONIAN_MODE_NAMES.iter().position(|m| *m == self.mode).unwrap_or(0);
+        EnharmonicNote::from_fifths(self.fifths - IONIAN_MODE_FIFTHS[mode])
+    }
+
+    // The same mode a number of semitones away, spelled with the fewest accidentals: G♭ over F♯.
+    pub fn transposed(&self, semis: Note) -> Self{
+        let offset = self.fifths - self.tonic().fifths();
+        let en = (self.tonic().to_note(0) + semis).rem_euclid(OCTAVE).to_enharmonic_note().expect("KeySignature::transposed: should not be None");
+        let fifths = [en, en.next_enharmonic(), en.prev_enharmonic()].iter()
+            .filter(|en| en.accidental().abs() <= 1)
+            .map(|en| en.fifths() + offset)
+            .min_by_key(|f| (f.abs(), *f))
+            .expect("KeySignature::transposed: should be impossible");
+        Self{ fifths, mode: self.mode }
+    }
+}
+
+// Modes of Ionian get their own signature, other scales the major or minor one of their tonic.
+pub fn key_signature(tonic: EnharmonicNote, steps: &Steps) -> KeySignature{
+    if let Some((mode, _)) = ionian::steps().mode_nr_of_this(steps){
+        let mode = mode % 7;
+        return KeySignature{ fifths: tonic.fifths() + IONIAN_MODE_FIFTHS[mode], mode: IONIAN_MODE_NAMES[mode] };
+    }
+    let minor = steps.0.len() > 1 && steps.0[0] + steps.0[1] == MINOR_THIRD;
+    if minor{
+        KeySignature{ fifths: tonic.fifths() - 3, mode: "minor" }
+    } else {
+        KeySignature{ fifths: tonic.fifths(), mode: "major" }
+    }
+}
+
+// Spell a note as it appears in the key, or with sharps or flats following the signature.
+pub fn spell_in_key(note: Note, key: &[EnharmonicNote], fifths: Note) -> EnharmonicNote{
+    let pc = note.to_pc();
+    if let Some(en) = key.iter().find(|en| en.to_pc() == pc){
+        return *en;
+    }
+    let en = note.to_enharmonic_note().expect("spell_in_key: should not be None");
+    if fifths < 0 && en.accidental() > 0 { en.next_enharmonic() } else { en }
+}
+
+// Move a spelled note from one key to another, keeping its letter relative to the tonic:
+// B♭ in C becomes E♭ in F, not D♯.
+pub fn transpose_spelled(en: EnharmonicNote, from: &KeySignature, to: &KeySignature) -> EnharmonicNote{
+    let (ft, tt) = (from.tonic(), to.tonic());
+    let note = (en.to_note(0) + tt.to_note(0) - ft.to_note(0)).rem_euclid(OCTAVE);
+    let res = spell_note(note, tt.letter() + (en.letter() + 7 - ft.letter()) % 7);
+    if res.accidental().abs() > 1 { spell_in_key(note, &[], to.fifths) } else { res }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::theory::chord::*;
+    use crate::theory::scale::ModeTrait;
+    #[test]
+    fn test_spelling(){
+        let fs = "F#".to_string().to_enharmonic_note().unwrap();
+        assert_eq!(fs.fifths(), 6);
+        assert_eq!("B#".to_string().to_enharmonic_note().unwrap().octave_of(39), 3);
+        assert_eq!(parse_pitch("C4").map(|(n, _)| n), Some(39));
+        assert_eq!(parse_pitch("Bb3").map(|(n, _)| n), Some(37));
+        let eb = "Eb".to_string().to_enharmonic_note().unwrap();
+        let chord = RootedChord::from_intervals(eb.to_note(36), HALF_DIMINISHED_SEVENTH);
+        let names = chord.spelled(Some(eb)).iter().map(|en| en.to_string_name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["E♭", "G♭", "B♭♭", "D♭"]);
+        let dorian = ionian::steps().mode(1);
+        assert_eq!(key_signature(eb, &dorian), KeySignature{ fifths: -5, mode: "dorian" });
+        assert_eq!(key_signature(eb, &dorian).tonic().to_string_name(), "E♭");
+        assert_eq!(EnharmonicNote::from_fifths(6).to_string_name(), "F♯");
+        assert_eq!(key_signature(eb, &dorian).accidentals(), [-1, -1, 0, -1, -1, 0, -1]);
+        let c = KeySignature{ fifths: 0, mode: "major" };
+        assert_eq!(c.transposed(6), KeySignature{ fifths: -6, mode: "major" });
+        assert_eq!(c.transposed(5).tonic().to_string_name(), "F");
+        let bb = "Bb".to_string().to_enharmonic_note().unwrap();
+        assert_eq!(transpose_spelled(bb, &c, &c.transposed(5)).to_string_name(), "E♭");
+        assert_eq!(transpose_spelled(bb, &c, &c.transposed(2)).to_string_name(), "C");
+    }
+}
@@ -0,0 +1,383 @@
+use super::note::*;
+use super::interval::*;
+use super::chord::*;
+
+#[derive(Clone,Copy)]
+struct SymbolParts{
+    third: Option<Note>,
+    fifth: Option<Note>,
+    sixth: bool,
+    seventh: Option<Note>,
+}
+
+/// A chord as written in lead sheets: a spelled root, the chord and an optional slash bass.
+#[derive(Clone)]
+pub struct ChordSymbol{
+    pub root: EnharmonicNote,
+    pub chord: Chord,
+    pub bass: Option<EnharmonicNote>,
+}
+
+// Reads the root of a symbol, giving it and what is left.
+pub fn parse_root(s: &str) -> Option<(EnharmonicNote, &str)>{
+    let mut end = s.chars().next()?.len_utf8();
+    for c in s[end..].chars(){
+        if c == 'b' || c == '#' || c == '♭' || c == '♯'{
+            end += c.len_utf8();
+        } else { break; }
+    }
+    Some((s[..end].to_string().to_enharmonic_note()?, &s[end..]))
+}
+
+// The interval of a spelled out extension like ♭9 or #11.
+fn parse_extension(s: &str) -> Option<(Note, usize)>{
+    let mut chars = s.char_indices();
+    let (_, acc) = chars.next()?;
+    let alter = match acc{
+        'b' | '♭' => -1,
+        '#' | '♯' | '+' => 1,
+        '♮' => 0,
+        _ => return None,
+    };
+    let start = acc.len_utf8();
+    let digits = s[start..].chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 { return None; }
+    let degree = s[start..start + digits].parse::<Note>().ok()?;
+    if !(2..=13).contains(&degree) { return None; }
+    // Unaltered steps as in a major scale with a minor seventh left to the alteration.
+    const NATURAL: [Note; 7] = [0, 2, 4, 5, 7, 9, 11];
+    let natural = NATURAL[((degree - 1) % 7) as usize] + OCTAVE * ((degree - 1) / 7);
+    Some((natural + alter, start + digits))
+}
+
+impl ChordSymbol{
+    // Reads common lead sheet symbols (Dm7, F#m7b5, Bb/D, C7(#9), Ebmaj9, G7alt) as well as
+    // the ones this crate prints (Dm-, C∆, B♭-∆, Cm°⁷, A[♭3♮5]).
+    pub fn parse(s: &str) -> Option<Self>{
+        let s = s.trim();
+        let (root, rest) = parse_root(s)?;
+        let (suffix, bass) = match rest.rfind('/'){
+            Some(i) if rest[i + 1..].starts_with(|c: char| ('A'..='G').contains(&c)) => {
+                (&rest[..i], Some(rest[i + 1..].to_string().to_enharmonic_note()?))
+            },
+            _ => (rest, None),
+        };
+        let chord = parse_chord_suffix(suffix)?;
+        Some(Self{ root, chord, bass })
+    }
+
+    pub fn from_rooted(chord: &RootedChord, root: EnharmonicNote) -> Self{
+        Self{ root, chord: chord.chord.clone(), bass: None }
+    }
+
+    // The root in the octave starting at rank (A based), the bass below it.
+    pub fn to_rooted_chord(&self, rank: Rank) -> RootedChord{
+        RootedChord::from_chord(self.root.to_note(0) + rank as Note * OCTAVE, self.chord.clone())
+    }
+
+    pub fn to_scale(&self, rank: Rank) -> Scale{
+        let mut scale = self.to_rooted_chord(rank).to_scale();
+        if let Some(bass) = self.bass{
+            let root = scale.0[0];
+            let bass = (bass.to_note(0) - root).rem_euclid(OCTAVE) + root - OCTAVE;
+            scale.0.insert(0, bass);
+        }
+        scale
+    }
+
+    // Plain text as lead sheets, ChordPro and ABC have it.
+    pub fn as_lead_sheet(&self) -> String{
+        let mut res = ascii_name(self.root);
+        res.push_str(&lead_sheet_suffix(&self.chord));
+        if let Some(bass) = self.bass{
+            res.push('/');
+            res.push_str(&ascii_name(bass));
+        }
+        res
+    }
+
+    pub fn as_string(&self, styling: ChordStyling) -> String{
+        let mut res = self.chord.quality(self.root.to_string_name(), false, styling);
+        if let Some(bass) = self.bass{
+            res.push('/');
+            res.push_str(&bass.to_string_name());
+        }
+        res
+    }
+}
+
+// Letter with # and b, as plain text formats want it.
+pub fn ascii_name(en: EnharmonicNote) -> String{
+    let mut res = en.to_string_name().chars().take(1).collect::<String>();
+    let acc = if en.accidental() > 0 { '#' } else { 'b' };
+    for _ in 0..en.accidental().abs(){
+        res.push(acc);
+    }
+    res
+}
+
+// The suffix of a chord as lead sheets write it: m7, maj7, m7b5, 7(#9).
+pub fn lead_sheet_suffix(chord: &Chord) -> String{
+    const NAMED: &[(&[Note], &str)] = &[
+        (MAJOR, ""), (MINOR, "m"), (MAJOR_AUGMENTED, "aug"), (MINOR_DIMINISHED, "dim"),
+        (SUS2, "sus2"), (SUS4, "sus4"), (MAJOR_SIXTH_CHORD, "6"), (MINOR_SIXTH_CHORD, "m6"),
+        (MAJOR_SEVENTH_CHORD, "maj7"), (MINOR_SEVENTH_CHORD, "m7"), (DOMINANT_SEVENTH, "7"),
+        (MINOR_MAJOR_SEVENTH, "m(maj7)"), (HALF_DIMINISHED_SEVENTH, "m7b5"), (DIMINISHED_SEVENTH_CHORD, "dim7"),
+        (AUGMENTED_SEVENTH_CHORD, "7#5"), (SIX_NINE_CHORD, "6/9"), (&[PERFECT_FIFTH], "5"),
+        (&[MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, NINETH], "9"),
+        (&[MAJOR_THIRD, PERFECT_FIFTH, MAJOR_SEVENTH, NINETH], "maj9"),
+        (&[MINOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, NINETH], "m9"),
+        (&[PERFECT_FOURTH, PERFECT_FIFTH, MINOR_SEVENTH], "7sus4"),
+        (&[MAJOR_THIRD, MINOR_SEVENTH, FLAT_NINETH, SHARP_NINETH, SHARP_ELEVENTH, FLAT_THIRTEENTH], "7alt"),
+        (&[MAJOR_THIRD, DIMINISHED_FIFTH, MINOR_SEVENTH], "7b5"),
+        (&[MAJOR_THIRD, DIMINISHED_FIFTH, MAJOR_SEVENTH], "maj7b5"),
+        (&[MAJOR_THIRD, AUGMENTED_FIFTH, MAJOR_SEVENTH], "maj7#5"),
+        (&[MINOR_THIRD, AUGMENTED_FIFTH, MINOR_SEVENTH], "m7#5"),
+        (&[MAJOR_SECOND, PERFECT_FIFTH, MINOR_SEVENTH], "7sus2"),
+    ];
+    if let Some((_, name)) = NAMED.iter().find(|(pattern, _)| chord.same_intervals(pattern)){
+        return name.to_string();
+    }
+    // Build on the largest known base, the rest as alterations.
+    if let Some((pattern, name)) = NAMED.iter().filter(|(p, _)| p.len() < chord.0.len() && chord.0.starts_with(p))
+        .max_by_key(|(p, _)| p.len()){
+        if let Some(exts) = chord.0[pattern.len()..].iter().map(|i| extension_token(*i)).collect::<Option<Vec<_>>>(){
+            return format!("{}({})", name, exts.join(","));
+        }
+    }
+    if let Some(suffix) = built_suffix(chord){
+        return suffix;
+    }
+    // Beyond what lead sheets can say.
+    chord.quality(String::new(), false, ChordStyling::SpelledOut).replace('♭', "b").replace('♯', "#")
+}
+
+// An interval as an added or altered step, never a fifth so it can't replace the chord's own.
+fn extension_token(interval: Note) -> Option<String>{
+    let token = match interval{
+        MINOR_SECOND => "b2", MAJOR_SECOND => "add2", MINOR_THIRD => "#2", MAJOR_THIRD => "add3",
+        PERFECT_FOURTH => "add4", TRITONE => "#4", PERFECT_FIFTH => "add5", MINOR_SIXTH => "b6",
+        MAJOR_SIXTH => "add6", MINOR_SEVENTH => "b7", MAJOR_SEVENTH => "add7", OCTAVE => "add8",
+        13 => "b9", 14 => "add9", 15 => "#9", 16 => "b11", 17 => "add11", 18 => "#11",
+        19 => "add12", 20 => "b13", 21 => "add13", 22 => "#13",
+        _ => return None,
+    };
+    Some(token.to_string())
+}
+
+// Third, seventh and fifth as parse_chord_suffix reads them, everything else added in parentheses.
+fn built_suffix(chord: &Chord) -> Option<String>{
+    let mut rest = chord.0.clone();
+    let mut take = |int: Note|{
+        let pos = rest.iter().position(|i| *i == int);
+        if let Some(pos) = pos { rest.remove(pos); }
+        pos.is_some()
+    };
+    let mut res = String::new();
+    let mut exts = Vec::new();
+    let third = [MAJOR_THIRD, MINOR_THIRD, PERFECT_FOURTH, MAJOR_SECOND].iter().copied().find(|i| take(*i));
+    if third == Some(MINOR_THIRD) { res.push('m'); }
+    if take(MINOR_SEVENTH) { res.push('7'); }
+    else if take(MAJOR_SEVENTH) { res.push_str("maj7"); }
+    match third{
+        Some(PERFECT_FOURTH) => res.push_str("sus4"),
+        Some(MAJOR_SECOND) => res.push_str("sus2"),
+        None => exts.push("omit3".to_string()),
+        _ => {},
+    }
+    // In parentheses, a leading b or # would be read as part of the root.
+    if !take(PERFECT_FIFTH){
+        if take(DIMINISHED_FIFTH) { exts.push("b5".to_string()); }
+        else if take(AUGMENTED_FIFTH) { exts.push("#5".to_string()); }
+        else { exts.push("omit5".to_string()); }
+    }
+    for int in rest{
+        exts.push(extension_token(int)?);
+    }
+    if !exts.is_empty(){
+        res.push_str(&format!("({})", exts.join(",")));
+    }
+    Some(res)
+}
+
+impl std::fmt::Display for ChordSymbol{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{}", self.as_string(ChordStyling::Std))
+    }
+}
+
+pub fn parse_chord_suffix(suffix: &str) -> Option<Chord>{
+    // Spelled out: [♭3♮5♭7]
+    if let Some(inner) = suffix.strip_prefix('[').and_then(|s| s.strip_suffix(']')){
+        let mut ints = Vec::new();
+        let mut rest = inner;
+        while !rest.is_empty(){
+            let (int, len) = parse_extension(rest)?;
+            ints.push(int);
+            rest = &rest[len..];
+        }
+        return Some(Chord::new(&ints).normalized_symbol());
+    }
+    let mut parts = SymbolParts{ third: Some(MAJOR_THIRD), fifth: Some(PERFECT_FIFTH), sixth: false, seventh: None };
+    let mut exts: Vec<Note> = Vec::new();
+    // Steps that come with a 9, 11 or 13, an alteration of the same step written out replaces them.
+    let mut implied: Vec<Note> = Vec::new();
+    let (mut minor, mut major7, mut dim, mut delta) = (false, false, false, false);
+    let mut rest = suffix;
+    let starts = |rest: &str, pats: &[&str]| pats.iter().find(|p| rest.starts_with(*p)).map(|p| p.len());
+    let ext_group = |int: Note| match int{ 13..=15 => Some(14), 16..=18 => Some(17), 20..=22 => Some(21), _ => None };
+    let set_seventh = |parts: &mut SymbolParts, major7: bool, dim: bool|{
+        if parts.seventh.is_none(){
+            parts.seventh = Some(if major7 { MAJOR_SEVENTH } else if dim { DIMINISHED_SEVENTH } else { MINOR_SEVENTH });
+        }
+    };
+    while !rest.is_empty(){
+        let len;
+        if let Some(l) = starts(rest, &["(", ")", ",", " "]) { len = l; }
+        else if let Some(l) = starts(rest, &["maj", "Maj", "ma", "M", "∆", "Δ", "^"]){
+            len = l;
+            major7 = true;
+            delta |= !rest.starts_with('M') && !rest.starts_with("ma") && !rest.starts_with("Ma");
+        }
+        else if let Some(l) = starts(rest, &["sus2"]) { len = l; parts.third = Some(MAJOR_SECOND); }
+        else if let Some(l) = starts(rest, &["sus4", "sus"]) { len = l; parts.third = Some(PERFECT_FOURTH); }
+        else if let Some(l) = starts(rest, &["ssus"]) { len = l; parts.third = Some(MAJOR_SECOND); parts.fifth = None; exts.push(PERFECT_FOURTH); }
+        else if let Some(l) = starts(rest, &["phry"]) { len = l; parts.third = Some(MINOR_SECOND); }
+        else if let Some(l) = starts(rest, &["lyd"]) { len = l; parts.third = Some(AUGMENTED_FOURTH); }
+        else if let Some(l) = starts(rest, &["loc2"]) { len = l; parts.third = Some(MINOR_SECOND); parts.fifth = Some(DIMINISHED_FIFTH); }
+        else if let Some(l) = starts(rest, &["loc4"]) { len = l; parts.third = Some(PERFECT_FOURTH); parts.fifth = Some(DIMINISHED_FIFTH); }
+        else if let Some(l) = starts(rest, &["μ", "mu"]) { len = l; exts.push(MAJOR_SECOND); }
+        else if let Some(l) = starts(rest, &["alt"]){
+            len = l;
+            set_seventh(&mut parts, false, false);
+            parts.fifth = None;
+            exts.extend_from_slice(&[FLAT_NINETH, SHARP_NINETH, SHARP_ELEVENTH, FLAT_THIRTEENTH]);
+        }
+        else if let Some(l) = starts(rest, &["add"]){
+            let (int, el) = parse_extension(&format!("♮{}", &rest[l..]))?;
+            len = l + el - '♮'.len_utf8();
+            exts.push(int);
+        }
+        else if let Some(l) = starts(rest, &["omit3", "no3"]) { len = l; parts.third = None; }
+        else if let Some(l) = starts(rest, &["omit5", "no5"]) { len = l; parts.fifth = None; }
+        else if let Some(l) = starts(rest, &["dim", "°", "o"]) { len = l; dim = true; parts.third = Some(MINOR_THIRD); parts.fifth = Some(DIMINISHED_FIFTH); }
+        else if let Some(l) = starts(rest, &["ø", "Ø"]) { len = l; parts.third = Some(MINOR_THIRD); parts.fifth = Some(DIMINISHED_FIFTH); set_seventh(&mut parts, false, false); }
+        else if let Some(l) = starts(rest, &["aug"]) { len = l; parts.fifth = Some(AUGMENTED_FIFTH); }
+        else if let Some(l) = starts(rest, &["min", "mi", "m"]) { len = l; minor = true; parts.third = Some(MINOR_THIRD); }
+        else if rest.starts_with('-'){
+            len = 1;
+            // The first dash is minor, a second one the minor seventh as in Dm-.
+            if minor { set_seventh(&mut parts, false, false); }
+            else { minor = true; parts.third = Some(MINOR_THIRD); }
+        }
+        else if let Some((int, l)) = parse_extension(rest){
+            len = l;
+            // Only a written 5 changes the fifth, #4 or b6 are added.
+            if rest[..l].ends_with('5') { parts.fifth = Some(int); }
+            else { exts.push(int); }
+        }
+        else if rest.starts_with('+') { len = 1; parts.fifth = Some(AUGMENTED_FIFTH); }
+        else if let Some(l) = starts(rest, &["6/9", "69"]) { len = l; parts.sixth = true; exts.push(NINETH); }
+        else {
+            let digits = rest.chars().take_while(|c| c.is_ascii_digit() || NUM_SUPS.contains(c)).collect::<String>();
+            if digits.is_empty() { return None; }
+            len = digits.len();
+            let num = digits.chars().map(|c| NUM_SUPS.iter().position(|s| *s == c).map(|p| p as u32).or_else(|| c.to_digit(10)))
+                .try_fold(0, |acc, d| d.map(|d| acc * 10 + d))?;
+            match num{
+                2 => exts.push(MAJOR_SECOND),
+                4 => parts.third = Some(PERFECT_FOURTH),
+                5 => parts.third = None,
+                6 => parts.sixth = true,
+                7 => set_seventh(&mut parts, major7, dim),
+                9 | 11 | 13 => {
+                    set_seventh(&mut parts, major7, dim);
+                    implied.push(NINETH);
+                    if num == 11 { implied.push(ELEVENTH); }
+                    if num == 13 { implied.push(THIRTEENTH); }
+                },
+                _ => return None,
+            }
+        }
+        rest = &rest[len..];
+    }
+    // A triangle on its own is a major seventh, "M" or "maj" on its own just major.
+    if delta { set_seventh(&mut parts, true, false); }
+    if major7 && parts.seventh == Some(MINOR_SEVENTH) { parts.seventh = Some(MAJOR_SEVENTH); }
+    let mut ints = Vec::new();
+    ints.extend(parts.third);
+    ints.extend(parts.fifth);
+    if parts.sixth { ints.push(MAJOR_SIXTH); }
+    ints.extend(parts.seventh);
+    ints.extend(implied.iter().filter(|i| !exts.iter().any(|e| ext_group(*e) == ext_group(**i))));
+    ints.extend(exts);
+    Some(Chord::new(&ints).normalized_symbol())
+}
+
+impl Chord{
+    // Sorted without doubles.
+    fn normalized_symbol(mut self) -> Self{
+        self.0.sort();
+        self.0.dedup();
+        self.0.retain(|i| *i != 0);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    #[test]
+    fn test_parse_symbols(){
+        let ints = |s: &str| ChordSymbol::parse(s).unwrap().chord.0;
+        assert_eq!(ints("Dm7"), MINOR_SEVENTH_CHORD);
+        assert_eq!(ints("Dm-"), MINOR_SEVENTH_CHORD);
+        assert_eq!(ints("G7"), DOMINANT_SEVENTH);
+        assert_eq!(ints("Cmaj7"), MAJOR_SEVENTH_CHORD);
+        assert_eq!(ints("C∆"), MAJOR_SEVENTH_CHORD);
+        assert_eq!(ints("CM"), MAJOR);
+        assert_eq!(ints("F#m7b5"), HALF_DIMINISHED_SEVENTH);
+        assert_eq!(ints("Bbø"), HALF_DIMINISHED_SEVENTH);
+        assert_eq!(ints("Ebm(maj7)"), MINOR_MAJOR_SEVENTH);
+        assert_eq!(ints("C-∆"), MINOR_MAJOR_SEVENTH);
+        assert_eq!(ints("Bdim7"), DIMINISHED_SEVENTH_CHORD);
+        assert_eq!(ints("C7#9"), vec![MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, SHARP_NINETH]);
+        assert_eq!(ints("C6/9"), SIX_NINE_CHORD);
+        assert_eq!(ints("Gsus4"), SUS4);
+        assert_eq!(ints("C(b5)"), MAJOR_DIMINISHED);
+        assert_eq!(ints("Bb7"), DOMINANT_SEVENTH);
+        assert_eq!(ints("C-7"), MINOR_SEVENTH_CHORD);
+        assert_eq!(ints("A[♭3♮5]"), MINOR);
+        assert_eq!(ints("C+"), MAJOR_AUGMENTED);
+        let sym = ChordSymbol::parse("Bb/D").unwrap();
+        assert_eq!(sym.as_string(ChordStyling::Std), "B♭/D");
+        assert_eq!(sym.to_scale(3).0, vec![D.0 + 24, AS.0 + 36, D.0 + 36, F.0 + 36]);
+        assert!(ChordSymbol::parse("H7").is_none());
+        for s in ["Bb/D", "F#m7b5", "Ebm(maj7)", "C7(#9)", "Abmaj9", "G7sus4", "Db7#5", "C6/9", "Cmaj7(#11)"]{
+            assert_eq!(ChordSymbol::parse(s).unwrap().as_lead_sheet(), s);
+        }
+        assert!(ChordSymbol::parse("Cxyz").is_none());
+        assert_eq!(ints("C7(b9,#9)"), vec![MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, FLAT_NINETH, SHARP_NINETH]);
+        assert_eq!(ints("C13(b9)"), vec![MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, FLAT_NINETH, THIRTEENTH]);
+        assert_eq!(ints("C(b6)"), vec![MAJOR_THIRD, PERFECT_FIFTH, MINOR_SIXTH]);
+    }
+    #[test]
+    fn test_lead_sheet_round_trip(){
+        assert_eq!(lead_sheet_suffix(&Chord::new(&[MAJOR_THIRD, DIMINISHED_FIFTH, MINOR_SEVENTH])), "7b5");
+        let mut chords = crate::theory::chord::STD_CHORD_BOOK.iter().map(|(p, _, _, _)| p.to_vec()).collect::<Vec<_>>();
+        chords.extend(vec![
+            vec![MAJOR_THIRD, PERFECT_FIFTH, MINOR_SEVENTH, FLAT_NINETH, SHARP_NINETH],
+            vec![MINOR_THIRD, DIMINISHED_FIFTH, MAJOR_SEVENTH, NINETH],
+            vec![MAJOR_THIRD, PERFECT_FIFTH, NINETH],
+            vec![MAJOR_THIRD, MINOR_SEVENTH, ELEVENTH, SHARP_ELEVENTH],
+            vec![MINOR_SECOND, MAJOR_THIRD, AUGMENTED_FIFTH, MAJOR_SIXTH],
+            vec![PERFECT_FOURTH, MINOR_SEVENTH, THIRTEENTH],
+        ]);
+        for ints in chords{
+            let suffix = lead_sheet_suffix(&Chord::new(&ints));
+            assert!(suffix.is_ascii(), "{}", suffix);
+            let sym = ChordSymbol::parse(&format!("C{}", suffix)).unwrap();
+            assert_eq!(sym.chord.0, ints, "{}", suffix);
+        }
+    }
+}
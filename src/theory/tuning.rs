@@ -0,0 +1,426 @@
+use super::note::*;
+use super::interval::OCTAVE;
+use super::scale::notes_to_octave_scale_in;
+use super::chord::ChordStyling;
+
+/// Anything that can turn notes into frequencies.
+/// The octave is the amount of notes before the pitch doubles.
+pub trait Tuning{
+    fn octave(&self) -> Note;
+    fn to_pitch(&self, note: Note) -> f32;
+
+    // The note A4 is on, in rank 4 when the notes count steps from A0.
+    fn reference_note(&self) -> Note{
+        4 * self.octave()
+    }
+}
+
+/// Equal division of the octave.
+/// The number is the amount of equal steps in one octave, 12 being the usual one.
+/// Notes, steps and pitchclasses keep being plain numbers, they just count
+/// steps of this size instead of semitones.
+#[derive(Clone,Copy,PartialEq,Eq,Hash,Debug)]
+pub struct Edo(Note);
+
+pub const EDO12: Edo = Edo(OCTAVE);
+pub const EDO19: Edo = Edo(19);
+pub const EDO22: Edo = Edo(22);
+pub const EDO24: Edo = Edo(24);
+pub const EDO31: Edo = Edo(31);
+pub const EDO53: Edo = Edo(53);
+
+// Position of the natural letters A..G on the chain of fifths, relative to A.
+const LETTER_FIFTHS: [Note; 7] = [0, 2, -3, -1, 1, -4, -2];
+const LETTERS: [char; 7] = ['A', 'B', 'C', 'D', 'E', 'F', 'G'];
+
+impl Edo{
+    // None for less than one step in the octave.
+    pub fn new(divisions: Note) -> Option<Self>{
+        if divisions > 0 { Some(Edo(divisions)) } else { None }
+    }
+
+    pub fn divisions(&self) -> Note{
+        self.0
+    }
+
+    // Like A4 = 48 in 12 edo: A in rank 4.
+    pub fn a4(&self) -> Note{
+        4 * self.0
+    }
+
+    // The closest approximation of the 3/2 perfect fifth.
+    pub fn fifth(&self) -> Note{
+        (self.0 as f32 * 1.5f32.log2()).round() as Note
+    }
+
+    pub fn whole(&self) -> Note{
+        2 * self.fifth() - self.0
+    }
+
+    // Diatonic semitone, E to F.
+    pub fn semi(&self) -> Note{
+        3 * self.0 - 5 * self.fifth()
+    }
+
+    // Size of a sharp or a flat, C to C♯.
+    pub fn chroma(&self) -> Note{
+        7 * self.fifth() - 4 * self.0
+    }
+
+    pub fn pc(&self, note: Note) -> PC{
+        PC(note.rem_euclid(self.0))
+    }
+
+    pub fn pcs(&self, scale: &Scale) -> PCs{
+        scale.0.iter().map(|n| self.pc(*n)).collect::<Vec<_>>()
+    }
+
+    pub fn note(&self, pc: PC, rank: Rank) -> Note{
+        pc.0 + rank as Note * self.0
+    }
+
+    pub fn pcs_to_scale(&self, pcs: &[PC], rank: Rank) -> Scale{
+        let mut rank = rank;
+        if pcs.is_empty() { return Scale::default(); }
+        let mut last = self.note(pcs[0], rank);
+        let mut res = vec![last];
+        for pc in pcs.iter().skip(1){
+            let mut note = self.note(*pc, rank);
+            if note <= last{
+                rank += 1;
+                note = self.note(*pc, rank);
+            }
+            last = note;
+            res.push(note);
+        }
+        Scale(res)
+    }
+
+    pub fn scale_to_steps(&self, scale: &Scale) -> Steps{
+        if scale.0.is_empty() { return Steps::default(); }
+        let mut last = scale.0[0];
+        let mut intervals = Vec::new();
+        for note in scale.0.iter().skip(1){
+            intervals.push(note - last);
+            last = *note;
+        }
+        intervals.push(scale.0[0] + self.0 - last);
+        Steps(intervals)
+    }
+
+    pub fn octave_steps(&self, scale: &Scale) -> Steps{
+        Steps(notes_to_octave_scale_in(scale, self.0))
+    }
+
+    // Maps a 12 edo interval onto this edo by walking the chain of fifths:
+    // a major third is four fifths up, so it becomes four of our fifths up.
+    pub fn map_interval(&self, semis: Note) -> Note{
+        let octaves = semis.div_euclid(OCTAVE);
+        let mut fifths = (semis.rem_euclid(OCTAVE) * 7) % OCTAVE;
+        if fifths > 6 { fifths -= OCTAVE; }
+        (fifths * self.fifth()).rem_euclid(self.0) + octaves * self.0
+    }
+
+    // Same as map_interval, with the letters the interval spans deciding between the two
+    // ways round the chain: six semitones over four letters are a ♭5, over three a ♯4.
+    pub fn map_interval_spelled(&self, semis: Note, letters: Note) -> Note{
+        let octaves = semis.div_euclid(OCTAVE);
+        let fifths = (semis.rem_euclid(OCTAVE) * 7) % OCTAVE;
+        // A fifth spans four letters.
+        let fifths = [fifths, fifths - OCTAVE].iter().copied()
+            .find(|f| (f * 4).rem_euclid(7) == letters.rem_euclid(7))
+            .unwrap_or(if fifths > 6 { fifths - OCTAVE } else { fifths });
+        (fifths * self.fifth()).rem_euclid(self.0) + octaves * self.0
+    }
+
+    // The 12 edo interval that maps onto this one, if any.
+    pub fn unmap_interval(&self, interval: Note) -> Option<Note>{
+        let octaves = interval.div_euclid(self.0);
+        let rest = interval.rem_euclid(self.0);
+        (0..OCTAVE).find(|semis| self.map_interval(*semis) == rest).map(|semis| semis + octaves * OCTAVE)
+    }
+
+    pub fn map_steps(&self, steps: &Steps) -> Steps{
+        let mut res = Vec::new();
+        let mut acc = 0;
+        let mut last = 0;
+        // Every degree of a seven note scale has its own letter.
+        let heptatonic = steps.len() == 7;
+        for (i, step) in steps.0.iter().enumerate(){
+            acc += step;
+            let mapped = if heptatonic { self.map_interval_spelled(acc, i as Note + 1) } else { self.map_interval(acc) };
+            res.push(mapped - last);
+            last = mapped;
+        }
+        Steps(res)
+    }
+
+    pub fn map_chord(&self, chord: &Chord) -> Chord{
+        // Six semitones are a ♭5 within the octave and a ♯11 above it.
+        Chord(chord.0.iter().map(|i| match (i.rem_euclid(OCTAVE), *i < OCTAVE){
+            (6, true) => self.map_interval_spelled(*i, 4),
+            (6, false) => self.map_interval_spelled(*i, 3),
+            _ => self.map_interval(*i),
+        }).collect::<Vec<_>>())
+    }
+
+    pub fn unmap_chord(&self, chord: &Chord) -> Option<Chord>{
+        let mut res = Vec::new();
+        for int in &chord.0{
+            res.push(self.unmap_interval(*int)?);
+        }
+        Some(Chord(res))
+    }
+
+    // Spell a pitchclass with a letter, sharps or flats and ups or downs (↑, ↓) for
+    // the steps in between. When half a sharp is exactly one step up, as in 24 edo,
+    // half sharps (𝄲) and half flats (𝄳) are used instead of the arrows.
+    pub fn pc_name(&self, pc: PC) -> String{
+        let n = self.0;
+        let p = pc.0.rem_euclid(n);
+        let chroma = self.chroma();
+        // Double accidentals only as a last resort, then the least arrows, then the least accidentals.
+        let mut best = ((true, Note::MAX, Note::MAX, true, 0usize), 0, 0);
+        for (letter, fifths) in LETTER_FIFTHS.iter().enumerate(){
+            let natural = (fifths * self.fifth()).rem_euclid(n);
+            for sharps in -2..=2{
+                let mut ups = (p - natural - sharps * chroma).rem_euclid(n);
+                if ups * 2 > n { ups -= n; }
+                let cost = (sharps.abs() > 1, ups.abs(), sharps.abs(), sharps < 0, letter);
+                if cost < best.0{
+                    best = (cost, sharps, ups);
+                }
+            }
+        }
+        let ((_, _, _, _, letter), sharps, ups) = best;
+        let mut res = String::new();
+        let half = chroma > 0 && chroma % 2 == 0 && sharps == 0 && ups.abs() * 2 == chroma;
+        if !half{
+            for _ in 0..ups.abs() { res.push(if ups > 0 { '↑' } else { '↓' }); }
+        }
+        res.push(LETTERS[letter]);
+        res.push_str(&(if sharps < 0 { RelativeNote::Flat(-sharps) } else { RelativeNote::Sharp(sharps) }).to_string());
+        if half{
+            res.push(if ups > 0 { '𝄲' } else { '𝄳' });
+        }
+        res
+    }
+
+    pub fn pc_names(&self) -> Vec<String>{
+        (0..self.0).map(|p| self.pc_name(PC(p))).collect::<Vec<_>>()
+    }
+
+    // Name a chord by mapping it back to 12 edo, spelled out in steps otherwise.
+    pub fn chord_quality(&self, chord: &Chord, basestr: String, lower: bool, style: ChordStyling) -> String{
+        if let Some(twelve) = self.unmap_chord(chord){
+            return twelve.quality(basestr, lower, style);
+        }
+        let mut res = basestr;
+        res.push('[');
+        res.push_str(&chord.0.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","));
+        res.push(']');
+        res
+    }
+}
+
+impl Tuning for Edo{
+    fn octave(&self) -> Note{
+        self.0
+    }
+
+    fn to_pitch(&self, note: Note) -> f32{
+        A440.to_pitch_edo(note, *self)
+    }
+}
+
+/// The frequency of A4 in Hz.
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct ConcertPitch(pub f32);
+
+pub const A440: ConcertPitch = ConcertPitch(440.0);
+pub const A442: ConcertPitch = ConcertPitch(442.0);
+pub const A432: ConcertPitch = ConcertPitch(432.0);
+pub const A430: ConcertPitch = ConcertPitch(430.0); // classical
+pub const A415: ConcertPitch = ConcertPitch(415.0); // baroque
+
+impl Default for ConcertPitch{
+    fn default() -> Self{
+        A440
+    }
+}
+
+impl ConcertPitch{
+    pub fn to_pitch_edo(&self, note: Note, edo: Edo) -> f32{
+        let x = note - edo.a4();
+        (2.0f32).powf(x as f32 / edo.0 as f32) * self.0
+    }
+
+    // The nearest note to a frequency and how many cents the frequency is off.
+    pub fn from_frequency(&self, hz: f32) -> PitchReading{
+        self.from_frequency_edo(hz, EDO12)
+    }
+
+    pub fn from_frequency_edo(&self, hz: f32, edo: Edo) -> PitchReading{
+        let steps = edo.0 as f32 * (hz / self.0).log2();
+        let rounded = steps.round();
+        PitchReading{
+            note: edo.a4() + rounded as Note,
+            cents: (steps - rounded) * 1200.0 / edo.0 as f32,
+            edo,
+        }
+    }
+
+    pub fn from_frequencies(&self, hzs: &[f32]) -> Vec<PitchReading>{
+        hzs.iter().map(|hz| self.from_frequency(*hz)).collect::<Vec<_>>()
+    }
+
+    // Pitchclasses in order of first appearance, ready for the analysis.
+    pub fn pcs_from_frequencies(&self, hzs: &[f32]) -> PCs{
+        let mut res = Vec::new();
+        for reading in self.from_frequencies(hzs){
+            let pc = reading.pc();
+            if !res.contains(&pc){
+                res.push(pc);
+            }
+        }
+        res
+    }
+
+    pub fn with_edo(self, edo: Edo) -> Temperament{
+        Temperament{ edo, concert: self }
+    }
+}
+
+impl Tuning for ConcertPitch{
+    fn octave(&self) -> Note{
+        OCTAVE
+    }
+
+    fn to_pitch(&self, note: Note) -> f32{
+        self.to_pitch_edo(note, EDO12)
+    }
+}
+
+/// An edo tuned to a concert pitch.
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct Temperament{
+    pub edo: Edo,
+    pub concert: ConcertPitch,
+}
+
+impl Default for Temperament{
+    fn default() -> Self{
+        A440.with_edo(EDO12)
+    }
+}
+
+impl Tuning for Temperament{
+    fn octave(&self) -> Note{
+        self.edo.0
+    }
+
+    fn to_pitch(&self, note: Note) -> f32{
+        self.concert.to_pitch_edo(note, self.edo)
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct PitchReading{
+    pub note: Note,
+    pub cents: f32,
+    pub edo: Edo,
+}
+
+impl PitchReading{
+    pub fn pc(&self) -> PC{
+        self.edo.pc(self.note)
+    }
+
+    // Octave number as usual, starting at C: A4 is followed by C5.
+    pub fn octave(&self) -> Note{
+        let c4 = self.edo.a4() + self.edo.map_interval(-9);
+        4 + (self.note - c4).div_euclid(self.edo.0)
+    }
+
+    pub fn name(&self) -> String{
+        format!("{}{}", self.edo.pc_name(self.pc()), self.octave())
+    }
+}
+
+impl std::fmt::Display for PitchReading{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{} {:+.1}¢", self.name(), self.cents)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::libr::scales::ionian;
+    #[test]
+    fn test_edo12_is_identity(){
+        for semis in 0..24{
+            assert_eq!(EDO12.map_interval(semis), semis);
+        }
+        let names = EDO12.pc_names();
+        for (p, name) in names.iter().enumerate(){
+            assert_eq!(name, &PC(p as Note).to_string_name());
+        }
+    }
+    #[test]
+    fn test_edo_major_scales(){
+        assert_eq!(EDO19.map_steps(&ionian::steps()).0, vec![3,3,2,3,3,3,2]);
+        assert_eq!(EDO24.map_steps(&ionian::steps()).0, vec![4,4,2,4,4,4,2]);
+        assert_eq!(EDO31.map_steps(&ionian::steps()).0, vec![5,5,3,5,5,5,3]);
+        assert_eq!(EDO53.map_steps(&ionian::steps()).0, vec![9,9,4,9,9,9,4]);
+    }
+    #[test]
+    fn test_edo_flat_five(){
+        // In 31 edo the ♭5 is a step above the ♯4.
+        assert_eq!(EDO31.map_interval_spelled(6, 3), 15);
+        assert_eq!(EDO31.map_interval_spelled(6, 4), 16);
+        assert_eq!(EDO31.map_interval(6), 15);
+        use crate::theory::scale::ModeTrait;
+        let locrian = EDO31.map_steps(&ionian::steps().mode(6));
+        assert_eq!(locrian.0.iter().take(4).sum::<Note>(), 16);
+        let lydian = EDO31.map_steps(&ionian::steps().mode(3));
+        assert_eq!(lydian.0.iter().take(3).sum::<Note>(), 15);
+        assert_eq!(EDO31.map_chord(&Chord(vec![3, 6, 10])).0, vec![8, 16, 26]);
+        assert_eq!(EDO31.map_chord(&Chord(vec![4, 7, 10, 18])).0[3], 46);
+        // Without a fifth the ♯11 of C7♯11 stays a ♯11.
+        assert_eq!(EDO31.map_chord(&Chord(vec![4, 10, 18])).0[2], 46);
+        assert_eq!(Edo::new(31), Some(EDO31));
+        assert_eq!(Edo::new(0), None);
+        assert_eq!(Edo::new(-12), None);
+    }
+    #[test]
+    fn test_edo_names(){
+        assert_eq!(EDO19.pc_name(PC(4)), "B♯");
+        assert_eq!(EDO24.pc_name(PC(1)), "A𝄲");
+        assert_eq!(EDO24.pc_name(PC(5)), "B𝄲");
+        assert_eq!(EDO53.pc_name(PC(1)), "↑A");
+    }
+    #[test]
+    fn test_edo_queries(){
+        use crate::theory::scale::ModeTrait;
+        let dorian = EDO31.map_steps(&ionian::steps()).mode(1).into_scale(0);
+        assert_eq!(crate::query::find_scale_edo(&dorian, EDO31).unwrap().mode_name, "Dorian");
+    }
+    #[test]
+    fn test_from_frequency(){
+        let reading = A440.from_frequency(261.63);
+        assert_eq!(reading.name(), "C4");
+        assert!(reading.cents.abs() < 0.1);
+        let reading = A415.from_frequency(440.0);
+        assert_eq!(reading.name(), "A♯4");
+        assert_eq!(reading.cents.round() as i32, 1);
+        assert_eq!(A432.pcs_from_frequencies(&[432.0, 216.0, 544.29]), vec![A, CS]);
+        let reading = A440.from_frequency_edo(A440.to_pitch_edo(EDO31.a4() + 3, EDO31), EDO31);
+        assert_eq!(reading.note, EDO31.a4() + 3);
+    }
+    #[test]
+    fn test_edo_pitch(){
+        assert_eq!(EDO31.to_pitch(EDO31.a4()).round() as i32, 440);
+        assert_eq!(EDO19.to_pitch(EDO19.a4() + 19).round() as i32, 880);
+    }
+}
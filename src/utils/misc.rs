@@ -17,3 +17,11 @@ pub fn both_differences<T>(a: &[T], b: &[T]) -> (Vec<T>,Vec<T>)
     (notina, notinb)
 }
 
+// The label in the middle of width columns, the rest filled.
+pub fn centered(label: &str, width: usize, fill: char) -> String{
+    let len = label.chars().count();
+    let left = width.saturating_sub(len) / 2;
+    let right = width.saturating_sub(len + left);
+    format!("{}{}{}", fill.to_string().repeat(left), label, fill.to_string().repeat(right))
+}
+